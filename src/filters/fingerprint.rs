@@ -0,0 +1,519 @@
+//! A filter that computes a (probably) unique ID for each event
+
+use std::io::{Cursor, Write};
+
+use anyhow::anyhow;
+use gasket::framework::*;
+use pallas::network::miniprotocols::Point;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::framework::legacy_v1::*;
+use crate::framework::*;
+
+struct FingerprintBuilder {
+    seed: u32,
+    prefix: Option<&'static str>,
+    slot: Option<u64>,
+    hasheable: Vec<u8>,
+}
+
+impl FingerprintBuilder {
+    fn new(seed: u32) -> Self {
+        FingerprintBuilder {
+            seed,
+            prefix: None,
+            slot: None,
+            hasheable: Vec::with_capacity(50),
+        }
+    }
+
+    fn with_slot(mut self, slot: &Option<u64>) -> Self {
+        self.slot = *slot;
+        self
+    }
+
+    fn with_prefix(mut self, prefix: &'static str) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    fn append_slice<T>(mut self, value: T) -> anyhow::Result<Self>
+    where
+        T: AsRef<[u8]>,
+    {
+        self.hasheable.write_all(value.as_ref())?;
+        Ok(self)
+    }
+
+    fn append_optional<T>(mut self, value: &Option<T>) -> anyhow::Result<Self>
+    where
+        T: AsRef<[u8]>,
+    {
+        match value {
+            None => Err(anyhow!("fingerprint component not available")),
+            Some(x) => {
+                let slice = x.as_ref();
+                self.hasheable.write_all(slice)?;
+                Ok(self)
+            }
+        }
+    }
+
+    fn append_optional_to_string<T>(self, value: &Option<T>) -> anyhow::Result<Self>
+    where
+        T: ToString,
+    {
+        let mapped = value.as_ref().map(|x| x.to_string());
+        self.append_optional(&mapped)
+    }
+
+    fn append_to_string<T>(self, value: &T) -> anyhow::Result<Self>
+    where
+        T: ToString,
+    {
+        let str = value.to_string();
+        self.append_slice(str)
+    }
+
+    fn build(self) -> anyhow::Result<String> {
+        let slot = self.slot.ok_or(anyhow!("missing slot value"))?;
+        let prefix = self.prefix.ok_or(anyhow!("missing prefix value"))?;
+        let hash = murmur3::murmur3_x64_128(&mut Cursor::new(self.hasheable), self.seed)?;
+        Ok(format!("{slot}.{prefix}.{hash}"))
+    }
+}
+
+#[inline]
+fn build_fingerprint(event: &Event, seed: u32) -> anyhow::Result<String> {
+    let mut b = FingerprintBuilder::new(seed);
+
+    b = match &event.data {
+        EventData::Block { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("blck")
+            .append_optional(&event.context.block_hash)?,
+        EventData::BlockEnd { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("blckend")
+            .append_optional(&event.context.block_hash)?,
+        EventData::Transaction { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("tx")
+            .append_optional(&event.context.tx_hash)?,
+        EventData::TransactionEnd { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("txend")
+            .append_optional(&event.context.tx_hash)?,
+        EventData::TxInput { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("stxi")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.input_idx)?,
+        EventData::TxOutput { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("utxo")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.output_idx)?,
+        EventData::OutputAsset(OutputAssetRecord { policy, asset, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("asst")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.output_idx)?
+            .append_slice(policy)?
+            .append_slice(asset)?,
+        EventData::Metadata(MetadataRecord { label, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("meta")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(label)?,
+        EventData::Mint(MintRecord { policy, asset, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("mint")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(policy)?
+            .append_slice(asset)?,
+        EventData::Collateral { tx_id, index } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("coll")
+            .append_slice(tx_id)?
+            .append_to_string(index)?,
+        EventData::NativeScript { policy_id, .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("scpt")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(policy_id)?,
+        EventData::PlutusScript { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("plut")
+            .append_optional(&event.context.tx_hash)?,
+        EventData::PlutusWitness(PlutusWitnessRecord { script_hash, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("witp")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(script_hash)?,
+        EventData::NativeWitness(NativeWitnessRecord { policy_id, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("witn")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(policy_id)?,
+        EventData::VKeyWitness(VKeyWitnessRecord { vkey_hex, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("witv")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(vkey_hex)?,
+        EventData::PlutusRedeemer(PlutusRedeemerRecord { input_idx, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("rdmr")
+            .append_optional(&event.context.tx_hash)?
+            .append_to_string(input_idx)?,
+        EventData::PlutusDatum(PlutusDatumRecord { datum_hash, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("dtum")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(datum_hash)?,
+        EventData::StakeRegistration { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("skre")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::StakeDeregistration { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("skde")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::StakeDelegation { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("dele")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::PoolRegistration { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("pool")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::PoolRetirement { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("reti")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::GenesisKeyDelegation { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("gene")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::MoveInstantaneousRewardsCert { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("move")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::InlineDatum(PlutusDatumRecord { datum_hash, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("idtm")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.output_idx)?
+            .append_slice(datum_hash)?,
+        EventData::ReferenceScript(ReferenceScriptRecord { script_hash, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("rscr")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.output_idx)?
+            .append_slice(script_hash)?,
+        EventData::StakeRegistrationWithDeposit { .. }
+        | EventData::StakeDeregistrationWithDeposit { .. }
+        | EventData::VoteDelegation { .. }
+        | EventData::StakeVoteDelegation { .. }
+        | EventData::StakeRegistrationDelegation { .. }
+        | EventData::VoteRegistrationDelegation { .. }
+        | EventData::StakeVoteRegistrationDelegation { .. }
+        | EventData::CommitteeHotAuthorization { .. }
+        | EventData::CommitteeColdResignation { .. }
+        | EventData::DRepRegistration { .. }
+        | EventData::DRepDeregistration { .. }
+        | EventData::DRepUpdate { .. } => b
+            .with_slot(&event.context.slot)
+            .with_prefix("cert")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.certificate_idx)?,
+        EventData::GovProposal(..) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("prop")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.proposal_idx)?,
+        EventData::GovVote(..) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("vote")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.vote_idx)?,
+        EventData::RollBack {
+            block_slot,
+            block_hash,
+        } => b
+            .with_slot(&Some(*block_slot))
+            .with_prefix("back")
+            .append_slice(block_hash)?,
+        EventData::CIP25Asset(CIP25AssetRecord { policy, asset, .. }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("cip25")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(policy)?
+            .append_slice(asset)?,
+        EventData::CIP68Asset(CIP68AssetRecord {
+            policy,
+            reference_asset,
+            ..
+        }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("cip68")
+            .append_optional(&event.context.tx_hash)?
+            .append_optional_to_string(&event.context.output_idx)?
+            .append_slice(policy)?
+            .append_slice(reference_asset)?,
+        EventData::CIP36Registration(CIP36RegistrationRecord {
+            stake_pub, nonce, ..
+        }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("cip36")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(stake_pub)?
+            .append_to_string(nonce)?,
+        EventData::CIP20Message(..) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("cip20")
+            .append_optional(&event.context.tx_hash)?,
+        EventData::CIP15Asset(CIP15AssetRecord {
+            voting_key, nonce, ..
+        }) => b
+            .with_slot(&event.context.slot)
+            .with_prefix("cip15")
+            .append_optional(&event.context.tx_hash)?
+            .append_slice(voting_key)?
+            .append_to_string(nonce)?,
+    };
+
+    b.build()
+}
+
+#[derive(Stage)]
+#[stage(name = "filter-fingerprint", unit = "ChainEvent", worker = "Worker")]
+pub struct Stage {
+    seed: u32,
+
+    pub input: FilterInputPort,
+    pub output: FilterOutputPort,
+
+    #[metric]
+    ops_count: gasket::metrics::Counter,
+
+    #[metric]
+    failed_count: gasket::metrics::Counter,
+}
+
+#[derive(Default)]
+pub struct Worker;
+
+impl From<&Stage> for Worker {
+    fn from(_: &Stage) -> Self {
+        Self
+    }
+}
+
+/// Records other than legacy events are identified by a hash of their
+/// content: the raw bytes of CBOR records and the JSON encoding of the rest
+fn build_content_fingerprint(record: &Record, slot: u64, seed: u32) -> anyhow::Result<String> {
+    let (prefix, content) = match record {
+        Record::CborBlock(x) => ("cblk", x.clone()),
+        Record::CborTx(x) => ("ctx", x.clone()),
+        Record::GenericJson(x) => ("json", serde_json::to_vec(x)?),
+        Record::ParsedTx(x) => ("ptx", serde_json::to_vec(x)?),
+        Record::ParsedBlock(x) => ("pblk", serde_json::to_vec(x)?),
+        Record::OuraV1Event(x) => return build_fingerprint(x, seed),
+    };
+
+    FingerprintBuilder::new(seed)
+        .with_slot(&Some(slot))
+        .with_prefix(prefix)
+        .append_slice(content)?
+        .build()
+}
+
+/// Computes the id of the record, which legacy events also keep in their own
+/// field for compatibility with the v1 schema
+fn with_record_fingerprint(
+    mut record: Record,
+    point: &Point,
+    stage: &mut Stage,
+) -> (Record, Option<String>) {
+    let fingerprint = match build_content_fingerprint(&record, point.slot_or_default(), stage.seed)
+    {
+        Ok(x) => Some(x),
+        Err(err) => {
+            warn!(%err, "failed to compute fingerprint");
+            stage.failed_count.inc(1);
+            None
+        }
+    };
+
+    if let Record::OuraV1Event(event) = &mut record {
+        event.fingerprint.clone_from(&fingerprint);
+    }
+
+    (record, fingerprint)
+}
+
+gasket::impl_mapper!(|_worker: Worker, stage: Stage, unit: ChainEvent| => {
+    let out = match unit.clone() {
+        ChainEvent::Apply(p, x, _) => {
+            let (x, id) = with_record_fingerprint(x, &p, stage);
+            ChainEvent::Apply(p, x, id)
+        }
+        ChainEvent::Undo(p, x, _) => {
+            let (x, id) = with_record_fingerprint(x, &p, stage);
+            ChainEvent::Undo(p, x, id)
+        }
+        x => x,
+    };
+
+    stage.ops_count.inc(1);
+
+    out
+});
+
+#[derive(Default, Deserialize)]
+pub struct Config {
+    pub seed: Option<u32>,
+}
+
+impl Config {
+    pub fn bootstrapper(self, _ctx: &Context) -> Result<Stage, Error> {
+        let stage = Stage {
+            seed: self.seed.unwrap_or(0),
+            input: Default::default(),
+            output: Default::default(),
+            ops_count: Default::default(),
+            failed_count: Default::default(),
+        };
+
+        Ok(stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx_event(tx_hash: Option<&str>) -> Event {
+        Event {
+            context: EventContext {
+                slot: Some(4492800),
+                tx_hash: tx_hash.map(String::from),
+                output_idx: Some(1),
+                ..Default::default()
+            },
+            data: EventData::TxOutput(TxOutputRecord {
+                address: "addr1".to_string(),
+                amount: 2000000,
+                assets: None,
+                datum_hash: None,
+                inline_datum: None,
+                reference_script: None,
+                cbor_hex: None,
+            }),
+            fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn legacy_fingerprint() {
+        let event = tx_event(Some("abcd"));
+
+        let a = build_fingerprint(&event, 0).unwrap();
+        let b = build_fingerprint(&event, 0).unwrap();
+        let c = build_fingerprint(&event, 1).unwrap();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.starts_with("4492800.utxo."));
+
+        assert!(build_fingerprint(&tx_event(None), 0).is_err());
+    }
+
+    fn stage(seed: u32) -> Stage {
+        Stage {
+            seed,
+            input: Default::default(),
+            output: Default::default(),
+            ops_count: Default::default(),
+            failed_count: Default::default(),
+        }
+    }
+
+    #[test]
+    fn content_fingerprint() {
+        let mut stage = stage(0);
+        let point = Point::Specific(4492800, vec![0xab, 0xcd]);
+
+        let json = Record::GenericJson(serde_json::json!({ "a": 1 }));
+        let (json, id) = with_record_fingerprint(json, &point, &mut stage);
+        let id = id.unwrap();
+        assert!(id.starts_with("4492800.json."));
+
+        // the payload is left untouched
+        assert!(matches!(&json, Record::GenericJson(x) if x.get("fingerprint").is_none()));
+
+        let other = Record::GenericJson(serde_json::json!({ "a": 2 }));
+        let (_, other) = with_record_fingerprint(other, &point, &mut stage);
+        assert_ne!(other.as_deref(), Some(id.as_str()));
+
+        let cbor = Record::CborTx(vec![1, 2, 3]);
+        let (_, a) = with_record_fingerprint(cbor.clone(), &point, &mut stage);
+        let (_, b) = with_record_fingerprint(cbor, &point, &mut stage);
+        assert_eq!(a, b);
+        assert!(a.unwrap().starts_with("4492800.ctx."));
+
+        let parsed = Record::ParsedTx(ParsedTx::default());
+        let (_, id) = with_record_fingerprint(parsed, &point, &mut stage);
+        assert!(id.unwrap().starts_with("4492800.ptx."));
+    }
+
+    #[test]
+    fn legacy_event_keeps_fingerprint() {
+        let mut stage = stage(0);
+        let point = Point::Specific(4492800, vec![0xab, 0xcd]);
+
+        let record = Record::OuraV1Event(tx_event(Some("abcd")));
+        let (record, id) = with_record_fingerprint(record, &point, &mut stage);
+
+        let Record::OuraV1Event(event) = record else {
+            unreachable!()
+        };
+
+        assert!(id.is_some());
+        assert_eq!(event.fingerprint, id);
+
+        let (_, id) =
+            with_record_fingerprint(Record::OuraV1Event(tx_event(None)), &point, &mut stage);
+        assert_eq!(id, None);
+    }
+
+    #[test]
+    fn envelope_fingerprint() {
+        let point = Point::Specific(1, vec![]);
+        let record = Record::CborTx(vec![1]);
+
+        let evt = ChainEvent::Apply(point.clone(), record.clone(), Some("1.ctx.2".into()));
+        assert_eq!(evt.fingerprint(), Some("1.ctx.2"));
+
+        let json = serde_json::Value::from(evt);
+        assert_eq!(json["fingerprint"], "1.ctx.2");
+
+        // a new record coming out of a filter has no id
+        let evt = ChainEvent::Undo(point.clone(), record, Some("1.ctx.2".into()));
+        let split = evt
+            .try_map_record_to_many::<_, ()>(|x| Ok(vec![x.clone(), x]))
+            .unwrap();
+        assert!(split.iter().all(|x| x.fingerprint().is_none()));
+
+        assert_eq!(ChainEvent::Reset(point).fingerprint(), None);
+    }
+}
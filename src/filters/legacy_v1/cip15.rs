@@ -3,6 +3,7 @@ use serde_json::Value as JsonValue;
 
 use pallas::ledger::primitives::alonzo::Metadatum;
 
+use super::cip36::is_cip36_registration;
 use super::EventWriter;
 use crate::framework::legacy_v1::*;
 
@@ -29,14 +30,27 @@ impl EventWriter<'_> {
             stake_pub: extract_json_string_property(&raw_json, "2").unwrap_or_default(),
             reward_address: extract_json_string_property(&raw_json, "3").unwrap_or_default(),
             nonce: extract_json_int_property(&raw_json, "4").unwrap_or_default(),
-            raw_json,
+            raw_json: self.to_raw_json(EventKind::CIP15Asset, raw_json),
         }
     }
 
     pub(crate) fn crawl_metadata_label_61284(
         &mut self,
         content: &Metadatum,
+        witness: Option<&Metadatum>,
     ) -> Result<(), WorkerError> {
+        if is_cip36_registration(content) {
+            if !self.config.emits_kind(EventKind::CIP36Registration) {
+                return Ok(());
+            }
+
+            return self.crawl_cip36_registration(content, witness);
+        }
+
+        if !self.config.emits_kind(EventKind::CIP15Asset) {
+            return Ok(());
+        }
+
         self.append_from(self.to_cip15_asset_record(content))?;
 
         Ok(())
@@ -0,0 +1,92 @@
+use gasket::framework::WorkerError;
+use pallas::ledger::primitives::alonzo::Metadatum;
+use tracing::warn;
+
+use super::EventWriter;
+use crate::framework::legacy_v1::*;
+
+impl EventWriter<'_> {
+    fn to_cip20_message_record(&self, content: &Metadatum) -> Option<CIP20MessageRecord> {
+        let Metadatum::Map(entries) = content else {
+            return None;
+        };
+
+        let messages = entries.iter().find_map(|(key, value)| match (key, value) {
+            (Metadatum::Text(k), Metadatum::Array(lines)) if k == "msg" => Some(lines),
+            _ => None,
+        })?;
+
+        let messages = messages
+            .iter()
+            .filter_map(|x| match x {
+                Metadatum::Text(x) => Some(x.to_owned()),
+                _ => None,
+            })
+            .collect();
+
+        Some(CIP20MessageRecord {
+            messages,
+            raw_json: self.to_raw_json(EventKind::CIP20Message, self.to_metadatum_json(content)),
+        })
+    }
+
+    pub(crate) fn crawl_metadata_label_674(
+        &mut self,
+        content: &Metadatum,
+    ) -> Result<(), WorkerError> {
+        match self.to_cip20_message_record(content) {
+            Some(record) => self.append_from(record)?,
+            None => warn!("invalid metadatum for 674 label"),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::codec::utils::KeyValuePairs;
+    use pallas::ledger::traverse::wellknown::GenesisValues;
+    use pallas::network::miniprotocols::Point;
+
+    use super::*;
+    use crate::filters::legacy_v1::Config;
+    use crate::framework::MapperOutputPort;
+
+    #[test]
+    fn text_lines_only() {
+        let output = MapperOutputPort::default();
+        let config = Config::default();
+        let genesis = GenesisValues::mainnet();
+        let mut buffer = vec![];
+
+        let writer = EventWriter::new(
+            Point::Origin,
+            false,
+            &output,
+            &config,
+            &genesis,
+            &mut buffer,
+        );
+
+        let content = Metadatum::Map(KeyValuePairs::Def(vec![(
+            Metadatum::Text("msg".to_string()),
+            Metadatum::Array(vec![
+                Metadatum::Text("hello".to_string()),
+                Metadatum::Int(1.into()),
+                Metadatum::Bytes(vec![1, 2].into()),
+                Metadatum::Text("world".to_string()),
+            ]),
+        )]));
+
+        let record = writer.to_cip20_message_record(&content).unwrap();
+        assert_eq!(record.messages, vec!["hello", "world"]);
+
+        let content = Metadatum::Map(KeyValuePairs::Def(vec![(
+            Metadatum::Text("msg".to_string()),
+            Metadatum::Text("not a list".to_string()),
+        )]));
+
+        assert!(writer.to_cip20_message_record(&content).is_none());
+    }
+}
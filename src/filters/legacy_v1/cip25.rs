@@ -3,7 +3,7 @@ use pallas::ledger::primitives::alonzo::Metadatum;
 use serde_json::Value as JsonValue;
 use tracing::warn;
 
-use crate::framework::legacy_v1::CIP25AssetRecord;
+use crate::framework::legacy_v1::{CIP25AssetRecord, EventKind};
 
 use super::EventWriter;
 
@@ -66,7 +66,7 @@ impl EventWriter<'_> {
             media_type: extract_json_property(&raw_json, "mediaType"),
             image: extract_json_property(&raw_json, "image"),
             description: extract_json_property(&raw_json, "description"),
-            raw_json,
+            raw_json: self.to_raw_json(EventKind::CIP25Asset, raw_json),
         }
     }
 
@@ -0,0 +1,206 @@
+use gasket::framework::WorkerError;
+use pallas::ledger::primitives::alonzo::Metadatum;
+use tracing::warn;
+
+use super::EventWriter;
+use crate::framework::legacy_v1::*;
+
+fn find_entry(content: &Metadatum, key: i128) -> Option<&Metadatum> {
+    match content {
+        Metadatum::Map(entries) => entries.iter().find_map(|(k, v)| match k {
+            Metadatum::Int(x) if i128::from(*x) == key => Some(v),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+fn as_hex(content: &Metadatum) -> Option<String> {
+    match content {
+        Metadatum::Bytes(x) => Some(hex::encode(x.as_slice())),
+        _ => None,
+    }
+}
+
+fn as_int(content: &Metadatum) -> Option<i128> {
+    match content {
+        Metadatum::Int(x) => Some(i128::from(*x)),
+        _ => None,
+    }
+}
+
+/// CIP-36 registrations either delegate to a list of keys or state a voting
+/// purpose, otherwise they are read as the older CIP-15 format
+pub(crate) fn is_cip36_registration(content: &Metadatum) -> bool {
+    matches!(find_entry(content, 1), Some(Metadatum::Array(_))) || find_entry(content, 5).is_some()
+}
+
+fn to_delegation_records(content: &Metadatum) -> Vec<CIP36DelegationRecord> {
+    match content {
+        // a single key takes all the voting power
+        Metadatum::Bytes(_) => as_hex(content)
+            .map(|voting_key| CIP36DelegationRecord {
+                voting_key,
+                weight: 1,
+            })
+            .into_iter()
+            .collect(),
+        Metadatum::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Metadatum::Array(pair) if pair.len() == 2 => Some(CIP36DelegationRecord {
+                    voting_key: as_hex(&pair[0])?,
+                    weight: as_int(&pair[1]).and_then(|x| u64::try_from(x).ok())?,
+                }),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+impl EventWriter<'_> {
+    fn to_cip36_registration_record(
+        &self,
+        content: &Metadatum,
+        witness: Option<&Metadatum>,
+    ) -> Option<CIP36RegistrationRecord> {
+        // a registration without stake key or reward address can't be used
+        let stake_pub = find_entry(content, 2).and_then(as_hex)?;
+        let reward_address = find_entry(content, 3).and_then(as_hex)?;
+
+        Some(CIP36RegistrationRecord {
+            delegations: find_entry(content, 1)
+                .map(to_delegation_records)
+                .unwrap_or_default(),
+            stake_pub,
+            reward_address,
+            nonce: find_entry(content, 4)
+                .and_then(as_int)
+                .and_then(|x| i64::try_from(x).ok())
+                .unwrap_or_default(),
+            voting_purpose: find_entry(content, 5)
+                .and_then(as_int)
+                .and_then(|x| u64::try_from(x).ok())
+                .unwrap_or_default(),
+            signature: witness.and_then(|x| find_entry(x, 1)).and_then(as_hex),
+            raw_json: self.to_raw_json(
+                EventKind::CIP36Registration,
+                self.to_metadatum_json(content),
+            ),
+        })
+    }
+
+    pub(crate) fn crawl_cip36_registration(
+        &mut self,
+        content: &Metadatum,
+        witness: Option<&Metadatum>,
+    ) -> Result<(), WorkerError> {
+        match self.to_cip36_registration_record(content, witness) {
+            Some(record) => self.append_from(record)?,
+            None => warn!("invalid cip36 registration in 61284 label"),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::codec::utils::KeyValuePairs;
+    use pallas::ledger::traverse::wellknown::GenesisValues;
+    use pallas::network::miniprotocols::Point;
+
+    use super::*;
+    use crate::filters::legacy_v1::Config;
+    use crate::framework::MapperOutputPort;
+
+    fn bytes(x: u8) -> Metadatum {
+        Metadatum::Bytes(vec![x; 32].into())
+    }
+
+    fn int(x: i64) -> Metadatum {
+        Metadatum::Int(x.into())
+    }
+
+    fn registration(delegations: Metadatum, with_reward_address: bool) -> Metadatum {
+        let mut entries = vec![(int(1), delegations), (int(2), bytes(2))];
+
+        if with_reward_address {
+            entries.push((int(3), bytes(3)));
+        }
+
+        entries.push((int(4), int(42)));
+        entries.push((int(5), int(0)));
+
+        Metadatum::Map(KeyValuePairs::Def(entries))
+    }
+
+    #[test]
+    fn delegations() {
+        assert_eq!(
+            to_delegation_records(&bytes(1)),
+            vec![CIP36DelegationRecord {
+                voting_key: hex::encode([1; 32]),
+                weight: 1,
+            }]
+        );
+
+        let weighted = Metadatum::Array(vec![
+            Metadatum::Array(vec![bytes(1), int(3)]),
+            Metadatum::Array(vec![bytes(2), int(1)]),
+            // malformed items are skipped
+            Metadatum::Array(vec![bytes(3), int(-1)]),
+            Metadatum::Array(vec![bytes(4)]),
+        ]);
+
+        assert_eq!(
+            to_delegation_records(&weighted),
+            vec![
+                CIP36DelegationRecord {
+                    voting_key: hex::encode([1; 32]),
+                    weight: 3,
+                },
+                CIP36DelegationRecord {
+                    voting_key: hex::encode([2; 32]),
+                    weight: 1,
+                },
+            ]
+        );
+
+        assert!(to_delegation_records(&int(1)).is_empty());
+    }
+
+    #[test]
+    fn required_keys() {
+        let output = MapperOutputPort::default();
+        let config = Config::default();
+        let genesis = GenesisValues::mainnet();
+        let mut buffer = vec![];
+
+        let writer = EventWriter::new(
+            Point::Origin,
+            false,
+            &output,
+            &config,
+            &genesis,
+            &mut buffer,
+        );
+
+        let complete = registration(bytes(1), true);
+        assert!(is_cip36_registration(&complete));
+
+        let record = writer
+            .to_cip36_registration_record(&complete, None)
+            .unwrap();
+        assert_eq!(record.stake_pub, hex::encode([2; 32]));
+        assert_eq!(record.reward_address, hex::encode([3; 32]));
+        assert_eq!(record.nonce, 42);
+        assert_eq!(record.delegations.len(), 1);
+
+        let incomplete = registration(bytes(1), false);
+        assert!(writer
+            .to_cip36_registration_record(&incomplete, None)
+            .is_none());
+    }
+}
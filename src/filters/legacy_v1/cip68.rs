@@ -0,0 +1,391 @@
+use std::ops::Deref as _;
+
+use gasket::framework::WorkerError;
+use pallas::ledger::primitives::alonzo::{BigInt, PlutusData};
+use pallas::ledger::primitives::babbage::DatumOption;
+use pallas::ledger::traverse::{MultiEraOutput, MultiEraTx};
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::framework::legacy_v1::*;
+
+use super::EventWriter;
+
+// asset name prefixes of the CIP-67 labels, including their checksum
+const REFERENCE_PREFIX: [u8; 4] = [0x00, 0x06, 0x43, 0xb0];
+
+const USER_PREFIXES: [(u32, [u8; 4]); 3] = [
+    (222, [0x00, 0x0d, 0xe1, 0x40]),
+    (333, [0x00, 0x14, 0xdf, 0x10]),
+    (444, [0x00, 0x1b, 0xc2, 0x80]),
+];
+
+// metadata keys with a field of their own in the record, the rest are
+// considered attributes unless an explicit `attributes` entry is present
+const WELL_KNOWN_KEYS: [&str; 5] = ["name", "image", "mediaType", "description", "files"];
+
+/// Renders the datum the way CIP-68 metadata is usually read: bytes as text
+/// when they are valid utf-8 and maps as objects
+fn plutus_data_to_metadata_json(data: &PlutusData) -> JsonValue {
+    match data {
+        PlutusData::Constr(x) => {
+            let fields: Vec<_> = x.fields.iter().map(plutus_data_to_metadata_json).collect();
+            json!({ "constructor": x.constructor_value(), "fields": fields })
+        }
+        PlutusData::Map(x) => {
+            let map: Map<_, _> = x
+                .iter()
+                .map(|(k, v)| (plutus_data_to_key(k), plutus_data_to_metadata_json(v)))
+                .collect();
+
+            JsonValue::Object(map)
+        }
+        PlutusData::BigInt(BigInt::Int(x)) => json!(i128::from(*x.deref())),
+        PlutusData::BigInt(BigInt::BigUInt(x)) => json!(hex::encode(x.as_slice())),
+        PlutusData::BigInt(BigInt::BigNInt(x)) => json!(hex::encode(x.as_slice())),
+        PlutusData::BoundedBytes(x) => match std::str::from_utf8(x.as_slice()) {
+            Ok(text) => json!(text),
+            Err(_) => json!(hex::encode(x.as_slice())),
+        },
+        PlutusData::Array(x) => {
+            let items: Vec<_> = x.iter().map(plutus_data_to_metadata_json).collect();
+            json!(items)
+        }
+    }
+}
+
+fn plutus_data_to_key(data: &PlutusData) -> String {
+    match plutus_data_to_metadata_json(data) {
+        JsonValue::String(x) => x,
+        x => x.to_string(),
+    }
+}
+
+/// Reads a text property, joining it when split in chunks (as done with long
+/// image uris)
+fn extract_text_property(metadata: &Map<String, JsonValue>, key: &str) -> Option<String> {
+    match metadata.get(key)? {
+        JsonValue::String(x) => Some(x.to_owned()),
+        JsonValue::Array(x) => x
+            .iter()
+            .map(|x| x.as_str())
+            .collect::<Option<Vec<_>>>()
+            .map(|x| x.concat()),
+        _ => None,
+    }
+}
+
+fn extract_attributes(metadata: &Map<String, JsonValue>) -> Option<JsonValue> {
+    if let Some(x) = metadata.get("attributes") {
+        return Some(x.clone());
+    }
+
+    let rest: Map<_, _> = metadata
+        .iter()
+        .filter(|(k, _)| !WELL_KNOWN_KEYS.contains(&k.as_str()))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+
+    match rest.is_empty() {
+        true => None,
+        false => Some(JsonValue::Object(rest)),
+    }
+}
+
+/// Finds the user token paired with a reference token, looking at the assets
+/// minted or moved by the same tx. There's no guessing when it isn't there,
+/// since the label of the user token can't be told from the reference token.
+fn find_user_token(tx: &MultiEraTx, policy: &[u8], suffix: &[u8]) -> Option<(u32, Vec<u8>)> {
+    let outputs = tx.outputs();
+    let values: Vec<_> = outputs.iter().map(|x| x.value()).collect();
+
+    let minted = tx.mints();
+    let moved = values.iter().flat_map(|x| x.assets());
+
+    for assets in minted.into_iter().chain(moved) {
+        if assets.policy().as_ref() != policy {
+            continue;
+        }
+
+        for asset in assets.assets() {
+            let name = asset.name();
+
+            let found = USER_PREFIXES.iter().find(|(_, prefix)| {
+                name.len() >= 4 && name[..4] == *prefix && name[4..] == *suffix
+            });
+
+            if let Some((label, _)) = found {
+                return Some((*label, name.to_vec()));
+            }
+        }
+    }
+
+    None
+}
+
+impl EventWriter<'_> {
+    fn to_cip68_asset_record(
+        &self,
+        tx: &MultiEraTx,
+        policy: &[u8],
+        reference_asset: &[u8],
+        datum: &PlutusData,
+    ) -> Option<CIP68AssetRecord> {
+        // the datum is expected to be `Constr 0 [metadata, version, extra]`
+        let PlutusData::Constr(constr) = datum else {
+            return None;
+        };
+
+        let mut fields = constr.fields.iter();
+        let metadata = fields.next()?;
+        let version = fields.next();
+
+        let PlutusData::Map(_) = metadata else {
+            return None;
+        };
+
+        let raw_json = plutus_data_to_metadata_json(metadata);
+        let metadata = raw_json.as_object()?;
+
+        let user_token = find_user_token(tx, policy, &reference_asset[4..]);
+
+        Some(CIP68AssetRecord {
+            policy: hex::encode(policy),
+            reference_asset: hex::encode(reference_asset),
+            user_asset: user_token.as_ref().map(|(_, x)| hex::encode(x)),
+            user_label: user_token.map(|(x, _)| x),
+            version: version.and_then(|x| match x {
+                PlutusData::BigInt(BigInt::Int(x)) => i64::try_from(*x.deref()).ok(),
+                _ => None,
+            }),
+            name: extract_text_property(metadata, "name"),
+            image: extract_text_property(metadata, "image"),
+            media_type: extract_text_property(metadata, "mediaType"),
+            description: extract_text_property(metadata, "description"),
+            attributes: extract_attributes(metadata),
+            raw_json: self.to_raw_json(EventKind::CIP68Asset, raw_json),
+        })
+    }
+
+    pub(crate) fn crawl_cip68_reference_output(
+        &mut self,
+        tx: &MultiEraTx,
+        output: &MultiEraOutput,
+    ) -> Result<(), WorkerError> {
+        let Some(DatumOption::Data(datum)) = output.datum() else {
+            return Ok(());
+        };
+
+        for policy in output.value().assets() {
+            for asset in policy.assets() {
+                let name = asset.name();
+
+                if !name.starts_with(&REFERENCE_PREFIX) {
+                    continue;
+                }
+
+                let record =
+                    self.to_cip68_asset_record(tx, policy.policy().as_ref(), name, &datum.0);
+
+                if let Some(record) = record {
+                    self.append_from(record)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use pallas::codec::minicbor;
+    use pallas::codec::utils::{KeyValuePairs, MaybeIndefArray};
+    use pallas::ledger::primitives::alonzo::Constr;
+    use pallas::ledger::traverse::wellknown::GenesisValues;
+    use pallas::network::miniprotocols::Point;
+
+    use super::*;
+    use crate::filters::legacy_v1::Config;
+    use crate::framework::{ChainEvent, MapperOutputPort, Record};
+
+    const POLICY: [u8; 28] = [7; 28];
+
+    fn bytes(x: &str) -> PlutusData {
+        PlutusData::BoundedBytes(x.as_bytes().to_vec().into())
+    }
+
+    fn asset_name(prefix: [u8; 4]) -> Vec<u8> {
+        [prefix.as_slice(), b"token"].concat()
+    }
+
+    /// `Constr 0 [metadata, version, extra]` with the image split in chunks
+    fn datum() -> PlutusData {
+        let metadata = PlutusData::Map(KeyValuePairs::Def(vec![
+            (bytes("name"), bytes("Token")),
+            (
+                bytes("image"),
+                PlutusData::Array(MaybeIndefArray::Def(vec![
+                    bytes("ipfs://abc"),
+                    bytes("def"),
+                ])),
+            ),
+            (bytes("rarity"), bytes("epic")),
+        ]));
+
+        PlutusData::Constr(Constr {
+            tag: 121,
+            any_constructor: None,
+            fields: MaybeIndefArray::Def(vec![
+                metadata,
+                PlutusData::BigInt(BigInt::Int(2.into())),
+                PlutusData::Constr(Constr {
+                    tag: 121,
+                    any_constructor: None,
+                    fields: MaybeIndefArray::Def(vec![]),
+                }),
+            ]),
+        })
+    }
+
+    /// A tx with an output holding `held` tokens and the given datum, minting
+    /// the `minted` tokens
+    fn tx_cbor(held: &[&[u8]], minted: &[&[u8]], datum: &PlutusData) -> Vec<u8> {
+        let mut e = minicbor::Encoder::new(vec![]);
+
+        e.array(4).unwrap();
+
+        e.map(if minted.is_empty() { 3 } else { 4 }).unwrap();
+        e.u8(0).unwrap().array(1).unwrap();
+        e.array(2).unwrap().bytes(&[1; 32]).unwrap().u8(0).unwrap();
+
+        e.u8(1).unwrap().array(1).unwrap().map(3).unwrap();
+        let mut address = vec![0x61];
+        address.extend([2; 28]);
+        e.u8(0).unwrap().bytes(&address).unwrap();
+        e.u8(1).unwrap().array(2).unwrap().u64(2000000).unwrap();
+        e.map(1).unwrap().bytes(&POLICY).unwrap();
+        e.map(held.len() as u64).unwrap();
+        for name in held {
+            e.bytes(name).unwrap().u8(1).unwrap();
+        }
+        e.u8(2).unwrap().array(2).unwrap().u8(1).unwrap();
+        e.tag(minicbor::data::Tag::new(24)).unwrap();
+        e.bytes(&minicbor::to_vec(datum).unwrap()).unwrap();
+
+        e.u8(2).unwrap().u64(170000).unwrap();
+
+        if !minted.is_empty() {
+            e.u8(9).unwrap().map(1).unwrap().bytes(&POLICY).unwrap();
+            e.map(minted.len() as u64).unwrap();
+            for name in minted {
+                e.bytes(name).unwrap().u8(1).unwrap();
+            }
+        }
+
+        e.map(0).unwrap();
+        e.bool(true).unwrap();
+        e.null().unwrap();
+
+        e.into_writer()
+    }
+
+    fn crawl(cbor: &[u8]) -> Vec<CIP68AssetRecord> {
+        let tx = MultiEraTx::decode(cbor).unwrap();
+
+        let config = Config {
+            events: Some(HashSet::from([EventKind::CIP68Asset])),
+            ..Default::default()
+        };
+
+        let output = MapperOutputPort::default();
+        let genesis = GenesisValues::mainnet();
+        let mut buffer = vec![];
+
+        let mut writer = EventWriter::new(
+            Point::Origin,
+            false,
+            &output,
+            &config,
+            &genesis,
+            &mut buffer,
+        );
+
+        writer
+            .crawl_cip68_reference_output(&tx, &tx.outputs()[0])
+            .unwrap();
+
+        buffer
+            .into_iter()
+            .map(|x| match x {
+                ChainEvent::Apply(_, Record::OuraV1Event(x), _) => match x.data {
+                    EventData::CIP68Asset(x) => x,
+                    x => panic!("unexpected event {x:?}"),
+                },
+                x => panic!("unexpected event {x:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reference_datum() {
+        let reference = asset_name(REFERENCE_PREFIX);
+        let user = asset_name(USER_PREFIXES[0].1);
+
+        let records = crawl(&tx_cbor(&[&reference], &[&user], &datum()));
+
+        assert_eq!(records.len(), 1);
+
+        let record = &records[0];
+        assert_eq!(record.policy, hex::encode(POLICY));
+        assert_eq!(record.reference_asset, hex::encode(&reference));
+        assert_eq!(record.user_asset, Some(hex::encode(&user)));
+        assert_eq!(record.user_label, Some(222));
+        assert_eq!(record.version, Some(2));
+        assert_eq!(record.name.as_deref(), Some("Token"));
+        assert_eq!(record.image.as_deref(), Some("ipfs://abcdef"));
+        assert_eq!(record.attributes, Some(json!({ "rarity": "epic" })));
+    }
+
+    #[test]
+    fn label_prefixes() {
+        let reference = asset_name(REFERENCE_PREFIX);
+
+        // a user token with another suffix isn't the pair of the reference
+        let other = [USER_PREFIXES[1].1.as_slice(), b"other"].concat();
+        let records = crawl(&tx_cbor(&[&reference], &[&other], &datum()));
+        assert_eq!(records[0].user_asset, None);
+        assert_eq!(records[0].user_label, None);
+
+        // the user token is only found when the tx mints or moves it
+        let records = crawl(&tx_cbor(&[&reference], &[], &datum()));
+        assert_eq!(records[0].user_asset, None);
+
+        let fungible = asset_name(USER_PREFIXES[1].1);
+        let records = crawl(&tx_cbor(&[&reference, &fungible], &[], &datum()));
+        assert_eq!(records[0].user_asset, Some(hex::encode(&fungible)));
+        assert_eq!(records[0].user_label, Some(333));
+
+        // tokens without the reference label don't carry metadata
+        let records = crawl(&tx_cbor(&[&fungible], &[], &datum()));
+        assert!(records.is_empty());
+    }
+
+    #[test]
+    fn unexpected_datum() {
+        let reference = asset_name(REFERENCE_PREFIX);
+
+        let records = crawl(&tx_cbor(&[&reference], &[], &bytes("nope")));
+        assert!(records.is_empty());
+
+        let datum = PlutusData::Constr(Constr {
+            tag: 121,
+            any_constructor: None,
+            fields: MaybeIndefArray::Def(vec![bytes("not a map")]),
+        });
+
+        let records = crawl(&tx_cbor(&[&reference], &[], &datum));
+        assert!(records.is_empty());
+    }
+}
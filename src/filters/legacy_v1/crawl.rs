@@ -6,8 +6,57 @@ use pallas::network::miniprotocols::Point;
 use crate::framework::legacy_v1::*;
 use crate::framework::Error as OuraError;
 
+use super::utxos::{ResolvedInputs, UtxoCache};
 use super::EventWriter;
 
+const OUTPUT_KINDS: &[EventKind] = &[
+    EventKind::TxOutput,
+    EventKind::OutputAsset,
+    EventKind::InlineDatum,
+    EventKind::ReferenceScript,
+    EventKind::CIP68Asset,
+];
+
+const CERTIFICATE_KINDS: &[EventKind] = &[
+    EventKind::StakeRegistration,
+    EventKind::StakeDeregistration,
+    EventKind::StakeDelegation,
+    EventKind::PoolRegistration,
+    EventKind::PoolRetirement,
+    EventKind::GenesisKeyDelegation,
+    EventKind::MoveInstantaneousRewardsCert,
+    EventKind::StakeRegistrationWithDeposit,
+    EventKind::StakeDeregistrationWithDeposit,
+    EventKind::VoteDelegation,
+    EventKind::StakeVoteDelegation,
+    EventKind::StakeRegistrationDelegation,
+    EventKind::VoteRegistrationDelegation,
+    EventKind::StakeVoteRegistrationDelegation,
+    EventKind::CommitteeHotAuthorization,
+    EventKind::CommitteeColdResignation,
+    EventKind::DRepRegistration,
+    EventKind::DRepDeregistration,
+    EventKind::DRepUpdate,
+];
+
+const GOVERNANCE_KINDS: &[EventKind] = &[EventKind::GovProposal, EventKind::GovVote];
+
+const METADATA_KINDS: &[EventKind] = &[
+    EventKind::Metadata,
+    EventKind::CIP20Message,
+    EventKind::CIP25Asset,
+    EventKind::CIP15Asset,
+    EventKind::CIP36Registration,
+];
+
+const WITNESS_KINDS: &[EventKind] = &[
+    EventKind::VKeyWitness,
+    EventKind::NativeWitness,
+    EventKind::PlutusWitness,
+    EventKind::PlutusRedeemer,
+    EventKind::PlutusDatum,
+];
+
 impl From<pallas::ledger::traverse::Era> for Era {
     fn from(other: pallas::ledger::traverse::Era) -> Self {
         match other {
@@ -17,6 +66,7 @@ impl From<pallas::ledger::traverse::Era> for Era {
             pallas::ledger::traverse::Era::Mary => Era::Mary,
             pallas::ledger::traverse::Era::Alonzo => Era::Alonzo,
             pallas::ledger::traverse::Era::Babbage => Era::Babbage,
+            pallas::ledger::traverse::Era::Conway => Era::Conway,
             _ => Era::Unknown,
         }
     }
@@ -34,13 +84,30 @@ impl EventWriter<'_> {
         let metadata = tx.metadata();
         let metadata = metadata.collect::<Vec<_>>();
 
+        // the signature of a CIP-36 registration comes in a label of its own
+        let witness = metadata
+            .iter()
+            .find(|(label, _)| *label == 61285)
+            .map(|(_, content)| *content);
+
         for (label, content) in metadata.iter() {
-            let record = self.to_metadata_record(label, content);
-            self.append_from(record)?;
+            if self.config.emits_kind(EventKind::Metadata) {
+                let record = self.to_metadata_record(label, content);
+                self.append_from(record)?;
+            }
+
+            let cip15 = [EventKind::CIP15Asset, EventKind::CIP36Registration];
 
             match label {
-                721u64 => self.crawl_metadata_label_721(content)?,
-                61284u64 => self.crawl_metadata_label_61284(content)?,
+                674u64 if self.config.emits_kind(EventKind::CIP20Message) => {
+                    self.crawl_metadata_label_674(content)?
+                }
+                721u64 if self.config.emits_kind(EventKind::CIP25Asset) => {
+                    self.crawl_metadata_label_721(content)?
+                }
+                61284u64 if self.config.emits_any(&cip15) => {
+                    self.crawl_metadata_label_61284(content, witness)?
+                }
                 _ => (),
             }
         }
@@ -48,117 +115,232 @@ impl EventWriter<'_> {
         Ok(())
     }
 
-    fn crawl_transaction_output(&mut self, output: &MultiEraOutput) -> Result<(), WorkerError> {
-        let record = self.to_transaction_output_record(output);
-        self.append(record.into()).or_panic()?;
+    fn crawl_transaction_output(
+        &mut self,
+        tx: &MultiEraTx,
+        output: &MultiEraOutput,
+    ) -> Result<(), WorkerError> {
+        if self.config.emits_kind(EventKind::TxOutput) {
+            let record = self.to_transaction_output_record(output);
+            self.append(record.into()).or_panic()?;
+        }
+
+        if !self.config.emits_any(&OUTPUT_KINDS[1..]) {
+            return Ok(());
+        }
 
         let address = output.address().or_panic()?;
-        let value = output.value();
 
         let mut child = self.child_writer(EventContext {
             output_address: Some(address.to_string()),
             ..EventContext::default()
         });
 
-        for policy in value.assets() {
-            for asset in policy.assets() {
-                child.append_from(OutputAssetRecord::from(&asset))?;
+        if child.config.emits_kind(EventKind::OutputAsset) {
+            for policy in output.value().assets() {
+                for asset in policy.assets() {
+                    child.append_from(OutputAssetRecord::from(&asset))?;
+                }
+            }
+        }
+
+        if child.config.emits_kind(EventKind::InlineDatum) {
+            if let Some(DatumOption::Data(datum)) = &output.datum() {
+                let record = child.to_plutus_datum_record(&datum.0);
+                child.append(EventData::InlineDatum(record))?;
+            }
+        }
+
+        if child.config.emits_kind(EventKind::ReferenceScript) {
+            if let Some(script) = output.script_ref() {
+                let record = child.to_reference_script_record(&script);
+                child.append_from(record)?;
             }
         }
 
-        if let Some(DatumOption::Data(datum)) = &output.datum() {
-            child.append_from(PlutusDatumRecord::from(&datum.0))?;
+        if child.config.emits_kind(EventKind::CIP68Asset) {
+            child.crawl_cip68_reference_output(tx, output)?;
         }
 
         Ok(())
     }
 
     fn crawl_witnesses(&mut self, tx: &MultiEraTx) -> Result<(), WorkerError> {
-        for script in tx.native_scripts() {
-            self.append_from(self.to_native_witness_record(script))?;
+        if self.config.emits_kind(EventKind::VKeyWitness) {
+            for witness in tx.vkey_witnesses() {
+                self.append_from(self.to_vkey_witness_record(witness))?;
+            }
         }
 
-        for script in tx.plutus_v1_scripts() {
-            self.append_from(self.to_plutus_v1_witness_record(script))?;
+        if self.config.emits_kind(EventKind::NativeWitness) {
+            for script in tx.native_scripts() {
+                self.append_from(self.to_native_witness_record(script))?;
+            }
         }
 
-        for script in tx.plutus_v2_scripts() {
-            self.append_from(self.to_plutus_v2_witness_record(script))?;
+        if self.config.emits_kind(EventKind::PlutusWitness) {
+            for script in tx.plutus_v1_scripts() {
+                self.append_from(self.to_plutus_v1_witness_record(script))?;
+            }
+
+            for script in tx.plutus_v2_scripts() {
+                self.append_from(self.to_plutus_v2_witness_record(script))?;
+            }
+
+            for script in tx.plutus_v3_scripts() {
+                self.append_from(self.to_plutus_v3_witness_record(script))?;
+            }
         }
 
-        for redeemer in tx.redeemers() {
-            self.append_from(self.to_plutus_redeemer_record(&redeemer))?;
+        if self.config.emits_kind(EventKind::PlutusRedeemer) {
+            for redeemer in tx.redeemers() {
+                self.append_from(self.to_plutus_redeemer_record(tx, &redeemer))?;
+            }
         }
 
-        for datum in tx.plutus_data() {
-            self.append_from(PlutusDatumRecord::from(datum))?;
+        if self.config.emits_kind(EventKind::PlutusDatum) {
+            for datum in tx.plutus_data() {
+                self.append_from(self.to_plutus_datum_record(datum))?;
+            }
         }
 
         Ok(())
     }
 
-    fn crawl_transaction(&mut self, tx: &MultiEraTx) -> Result<(), WorkerError> {
-        let record = self.to_transaction_record(tx);
-        self.append_from(record.clone())?;
+    fn crawl_governance(&mut self, tx: &MultiEraTx) -> Result<(), WorkerError> {
+        let Some(body) = tx.as_conway().map(|x| &x.transaction_body) else {
+            return Ok(());
+        };
+
+        let proposals = body
+            .proposal_procedures
+            .as_ref()
+            .filter(|_| self.config.emits_kind(EventKind::GovProposal));
+
+        for (idx, proposal) in proposals.into_iter().flatten().enumerate() {
+            let record = self.to_gov_proposal_record(proposal);
 
-        // crawl inputs
-        for (idx, input) in tx.inputs().iter().enumerate() {
             let mut child = self.child_writer(EventContext {
-                input_idx: Some(idx),
+                proposal_idx: Some(idx),
                 ..EventContext::default()
             });
 
-            child.append_from(TxInputRecord::from(input))?;
+            child.append_from(record)?;
         }
 
-        for (idx, output) in tx.outputs().iter().enumerate() {
+        let votes = body
+            .voting_procedures
+            .as_ref()
+            .filter(|_| self.config.emits_kind(EventKind::GovVote))
+            .map(|x| self.to_gov_vote_records(x))
+            .unwrap_or_default();
+
+        for (idx, vote) in votes.into_iter().enumerate() {
             let mut child = self.child_writer(EventContext {
-                output_idx: Some(idx),
+                vote_idx: Some(idx),
                 ..EventContext::default()
             });
 
-            child.crawl_transaction_output(output)?;
+            child.append_from(vote)?;
         }
 
-        //crawl certs
-        for (idx, cert) in tx.certs().iter().enumerate() {
-            if let Some(evt) = self.to_certificate_event(cert) {
+        Ok(())
+    }
+
+    fn crawl_transaction(&mut self, tx: &MultiEraTx) -> Result<(), WorkerError> {
+        let include_end = self.config.include_transaction_end_events
+            && self.config.emits_kind(EventKind::TransactionEnd);
+
+        let record = match self.config.emits_kind(EventKind::Transaction) || include_end {
+            true => Some(self.to_transaction_record(tx)),
+            false => None,
+        };
+
+        if let Some(record) = &record {
+            self.append_from(record.clone())?;
+        }
+
+        // crawl inputs
+        if self.config.emits_kind(EventKind::TxInput) {
+            for (idx, input) in tx.inputs().iter().enumerate() {
                 let mut child = self.child_writer(EventContext {
-                    certificate_idx: Some(idx),
+                    input_idx: Some(idx),
                     ..EventContext::default()
                 });
 
-                child.append(evt)?;
+                let record = child.to_transaction_input_record(input);
+                child.append_from(record)?;
             }
         }
 
-        for collateral in tx.collateral().iter() {
-            // TODO: collateral context?
-            self.crawl_collateral(collateral)?;
+        if self.config.emits_any(OUTPUT_KINDS) {
+            for (idx, output) in tx.outputs().iter().enumerate() {
+                let mut child = self.child_writer(EventContext {
+                    output_idx: Some(idx),
+                    ..EventContext::default()
+                });
+
+                child.crawl_transaction_output(tx, output)?;
+            }
+        }
+
+        //crawl certs
+        if self.config.emits_any(CERTIFICATE_KINDS) {
+            for (idx, cert) in tx.certs().iter().enumerate() {
+                if let Some(evt) = self.to_certificate_event(cert) {
+                    let mut child = self.child_writer(EventContext {
+                        certificate_idx: Some(idx),
+                        ..EventContext::default()
+                    });
+
+                    child.append(evt)?;
+                }
+            }
+        }
+
+        if self.config.emits_any(GOVERNANCE_KINDS) {
+            self.crawl_governance(tx)?;
+        }
+
+        if self.config.emits_kind(EventKind::Collateral) {
+            for collateral in tx.collateral().iter() {
+                // TODO: collateral context?
+                self.crawl_collateral(collateral)?;
+            }
         }
 
         // crawl mints
-        for policy in tx.mints() {
-            for asset in policy.assets() {
-                self.append_from(self.to_mint_record(&asset))?;
+        if self.config.emits_kind(EventKind::Mint) {
+            for policy in tx.mints() {
+                for asset in policy.assets() {
+                    self.append_from(self.to_mint_record(&asset))?;
+                }
             }
         }
 
-        self.crawl_metadata(tx)?;
+        if self.config.emits_any(METADATA_KINDS) {
+            self.crawl_metadata(tx)?;
+        }
 
         // crawl aux native scripts
-        for script in tx.aux_native_scripts() {
-            self.append(self.to_aux_native_script_event(script))?;
+        if self.config.emits_kind(EventKind::NativeScript) {
+            for script in tx.aux_native_scripts() {
+                self.append(self.to_aux_native_script_event(script))?;
+            }
         }
 
         // crawl aux plutus v1 scripts
-        for script in tx.aux_plutus_v1_scripts() {
-            self.append(self.to_aux_plutus_script_event(script))?;
+        if self.config.emits_kind(EventKind::PlutusScript) {
+            for script in tx.aux_plutus_v1_scripts() {
+                self.append(self.to_aux_plutus_script_event(script))?;
+            }
         }
 
-        self.crawl_witnesses(tx)?;
+        if self.config.emits_any(WITNESS_KINDS) {
+            self.crawl_witnesses(tx)?;
+        }
 
-        if self.config.include_transaction_end_events {
+        if let Some(record) = record.filter(|_| include_end) {
             self.append(EventData::TransactionEnd(record))?;
         }
 
@@ -166,8 +348,17 @@ impl EventWriter<'_> {
     }
 
     fn crawl_block(&mut self, block: &MultiEraBlock, cbor: &[u8]) -> Result<(), WorkerError> {
-        let record = self.to_block_record(block, cbor);
-        self.append(EventData::Block(record.clone()))?;
+        let include_end =
+            self.config.include_block_end_events && self.config.emits_kind(EventKind::BlockEnd);
+
+        let record = match self.config.emits_kind(EventKind::Block) || include_end {
+            true => Some(self.to_block_record(block, cbor)),
+            false => None,
+        };
+
+        if let Some(record) = &record {
+            self.append(EventData::Block(record.clone()))?;
+        }
 
         for (idx, tx) in block.txs().iter().enumerate() {
             let mut child = self.child_writer(EventContext {
@@ -179,19 +370,35 @@ impl EventWriter<'_> {
             child.crawl_transaction(tx)?;
         }
 
-        if self.config.include_block_end_events {
+        if let Some(record) = record.filter(|_| include_end) {
             self.append(EventData::BlockEnd(record))?;
         }
 
         Ok(())
     }
 
+    /// Tracks the outputs of the block in the cache, resolving its inputs
+    fn resolve_inputs(&self, block: &MultiEraBlock, utxos: &mut UtxoCache) -> ResolvedInputs {
+        let map = |x: &MultiEraOutput| self.to_full_transaction_output_record(x);
+
+        match self.undo {
+            false => utxos.apply(&self.point, block, map),
+            true => utxos.apply_undone(&self.point, block, map),
+        }
+    }
+
     /// Mapper entry-point for raw cbor blocks
-    pub fn crawl_cbor(&mut self, cbor: &[u8]) -> Result<(), WorkerError> {
+    pub fn crawl_cbor(
+        &mut self,
+        cbor: &[u8],
+        utxos: Option<&mut UtxoCache>,
+    ) -> Result<(), WorkerError> {
         let block = pallas::ledger::traverse::MultiEraBlock::decode(cbor)
             .map_err(OuraError::parse)
             .or_panic()?;
 
+        let resolved = utxos.map(|x| self.resolve_inputs(&block, x));
+
         let hash = block.hash();
 
         let mut child = self.child_writer(EventContext {
@@ -202,6 +409,8 @@ impl EventWriter<'_> {
             ..EventContext::default()
         });
 
+        child.resolved = resolved.as_ref();
+
         child.crawl_block(&block, cbor)
     }
 
@@ -209,3 +418,251 @@ impl EventWriter<'_> {
         self.append(point.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use pallas::codec::minicbor;
+    use pallas::ledger::traverse::wellknown::GenesisValues;
+
+    use super::*;
+    use crate::filters::legacy_v1::Config;
+    use crate::framework::{ChainEvent, MapperOutputPort, Record};
+
+    /// Kinds that are checked on their own instead of through a group
+    const SINGLE_KINDS: &[EventKind] = &[
+        EventKind::Block,
+        EventKind::BlockEnd,
+        EventKind::Transaction,
+        EventKind::TransactionEnd,
+        EventKind::TxInput,
+        EventKind::Collateral,
+        EventKind::Mint,
+        EventKind::NativeScript,
+        EventKind::PlutusScript,
+        EventKind::RollBack,
+    ];
+
+    /// Every kind of event. The match below doesn't compile when a kind is
+    /// added, as a reminder to list it here too.
+    const ALL_KINDS: &[EventKind] = &[
+        EventKind::Block,
+        EventKind::BlockEnd,
+        EventKind::Transaction,
+        EventKind::TransactionEnd,
+        EventKind::TxInput,
+        EventKind::TxOutput,
+        EventKind::OutputAsset,
+        EventKind::Metadata,
+        EventKind::VKeyWitness,
+        EventKind::NativeWitness,
+        EventKind::PlutusWitness,
+        EventKind::PlutusRedeemer,
+        EventKind::PlutusDatum,
+        EventKind::InlineDatum,
+        EventKind::ReferenceScript,
+        EventKind::CIP25Asset,
+        EventKind::CIP15Asset,
+        EventKind::CIP68Asset,
+        EventKind::CIP36Registration,
+        EventKind::CIP20Message,
+        EventKind::Mint,
+        EventKind::Collateral,
+        EventKind::NativeScript,
+        EventKind::PlutusScript,
+        EventKind::StakeRegistration,
+        EventKind::StakeDeregistration,
+        EventKind::StakeDelegation,
+        EventKind::PoolRegistration,
+        EventKind::PoolRetirement,
+        EventKind::GenesisKeyDelegation,
+        EventKind::MoveInstantaneousRewardsCert,
+        EventKind::StakeRegistrationWithDeposit,
+        EventKind::StakeDeregistrationWithDeposit,
+        EventKind::VoteDelegation,
+        EventKind::StakeVoteDelegation,
+        EventKind::StakeRegistrationDelegation,
+        EventKind::VoteRegistrationDelegation,
+        EventKind::StakeVoteRegistrationDelegation,
+        EventKind::CommitteeHotAuthorization,
+        EventKind::CommitteeColdResignation,
+        EventKind::DRepRegistration,
+        EventKind::DRepDeregistration,
+        EventKind::DRepUpdate,
+        EventKind::GovProposal,
+        EventKind::GovVote,
+        EventKind::RollBack,
+    ];
+
+    fn listed_in_all_kinds(kind: EventKind) {
+        match kind {
+            EventKind::Block
+            | EventKind::BlockEnd
+            | EventKind::Transaction
+            | EventKind::TransactionEnd
+            | EventKind::TxInput
+            | EventKind::TxOutput
+            | EventKind::OutputAsset
+            | EventKind::Metadata
+            | EventKind::VKeyWitness
+            | EventKind::NativeWitness
+            | EventKind::PlutusWitness
+            | EventKind::PlutusRedeemer
+            | EventKind::PlutusDatum
+            | EventKind::InlineDatum
+            | EventKind::ReferenceScript
+            | EventKind::CIP25Asset
+            | EventKind::CIP15Asset
+            | EventKind::CIP68Asset
+            | EventKind::CIP36Registration
+            | EventKind::CIP20Message
+            | EventKind::Mint
+            | EventKind::Collateral
+            | EventKind::NativeScript
+            | EventKind::PlutusScript
+            | EventKind::StakeRegistration
+            | EventKind::StakeDeregistration
+            | EventKind::StakeDelegation
+            | EventKind::PoolRegistration
+            | EventKind::PoolRetirement
+            | EventKind::GenesisKeyDelegation
+            | EventKind::MoveInstantaneousRewardsCert
+            | EventKind::StakeRegistrationWithDeposit
+            | EventKind::StakeDeregistrationWithDeposit
+            | EventKind::VoteDelegation
+            | EventKind::StakeVoteDelegation
+            | EventKind::StakeRegistrationDelegation
+            | EventKind::VoteRegistrationDelegation
+            | EventKind::StakeVoteRegistrationDelegation
+            | EventKind::CommitteeHotAuthorization
+            | EventKind::CommitteeColdResignation
+            | EventKind::DRepRegistration
+            | EventKind::DRepDeregistration
+            | EventKind::DRepUpdate
+            | EventKind::GovProposal
+            | EventKind::GovVote
+            | EventKind::RollBack => (),
+        }
+    }
+
+    #[test]
+    fn every_kind_is_gated() {
+        let groups = [
+            OUTPUT_KINDS,
+            CERTIFICATE_KINDS,
+            GOVERNANCE_KINDS,
+            METADATA_KINDS,
+            WITNESS_KINDS,
+            SINGLE_KINDS,
+        ];
+
+        for kind in ALL_KINDS {
+            listed_in_all_kinds(*kind);
+
+            assert!(
+                groups.iter().any(|x| x.contains(kind)),
+                "{kind:?} can't be reached through any gate"
+            );
+        }
+    }
+
+    /// A conway tx with an input, an output, a vkey witness and both CIP-25
+    /// and CIP-20 metadata
+    fn tx_cbor() -> Vec<u8> {
+        let mut e = minicbor::Encoder::new(vec![]);
+
+        e.array(4).unwrap();
+
+        e.map(3).unwrap();
+        e.u8(0).unwrap().array(1).unwrap();
+        e.array(2).unwrap().bytes(&[1; 32]).unwrap().u8(0).unwrap();
+        e.u8(1).unwrap().array(1).unwrap();
+        let mut address = vec![0x61];
+        address.extend([2; 28]);
+        e.array(2)
+            .unwrap()
+            .bytes(&address)
+            .unwrap()
+            .u64(1000000)
+            .unwrap();
+        e.u8(2).unwrap().u64(170000).unwrap();
+
+        e.map(1).unwrap();
+        e.u8(0).unwrap().array(1).unwrap();
+        e.array(2)
+            .unwrap()
+            .bytes(&[3; 32])
+            .unwrap()
+            .bytes(&[4; 64])
+            .unwrap();
+
+        e.bool(true).unwrap();
+
+        e.map(2).unwrap();
+        e.u16(721).unwrap().map(1).unwrap();
+        e.str(&hex::encode([5; 28])).unwrap().map(1).unwrap();
+        e.str("token").unwrap().map(1).unwrap();
+        e.str("name").unwrap().str("Token").unwrap();
+        e.u16(674).unwrap().map(1).unwrap();
+        e.str("msg")
+            .unwrap()
+            .array(1)
+            .unwrap()
+            .str("hello")
+            .unwrap();
+
+        e.into_writer()
+    }
+
+    fn crawl(config: &Config) -> Vec<EventKind> {
+        let cbor = tx_cbor();
+        let tx = MultiEraTx::decode(&cbor).unwrap();
+
+        let output = MapperOutputPort::default();
+        let genesis = GenesisValues::mainnet();
+        let mut buffer = vec![];
+
+        let mut writer =
+            EventWriter::new(Point::Origin, false, &output, config, &genesis, &mut buffer);
+
+        writer.crawl_transaction(&tx).unwrap();
+
+        buffer
+            .iter()
+            .map(|x| match x {
+                ChainEvent::Apply(_, Record::OuraV1Event(x), _) => EventKind::from(&x.data),
+                x => panic!("unexpected event {x:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn allowlisted_kinds_only() {
+        let everything = crawl(&Config::default());
+
+        for kind in [
+            EventKind::Transaction,
+            EventKind::TxInput,
+            EventKind::TxOutput,
+            EventKind::VKeyWitness,
+            EventKind::Metadata,
+            EventKind::CIP20Message,
+            EventKind::CIP25Asset,
+        ] {
+            assert!(everything.contains(&kind), "{kind:?} missing");
+        }
+
+        let config = Config {
+            events: Some(HashSet::from([
+                EventKind::Transaction,
+                EventKind::CIP25Asset,
+            ])),
+            ..Default::default()
+        };
+
+        let kinds = crawl(&config);
+
+        assert_eq!(kinds, vec![EventKind::Transaction, EventKind::CIP25Asset]);
+    }
+}
@@ -3,8 +3,10 @@ use pallas::ledger::primitives::conway;
 use serde_json::{json, Value as JsonValue};
 use std::collections::HashMap;
 use std::ops::Deref as _;
+use std::str::FromStr as _;
 use tracing::warn;
 
+use pallas::codec::minicbor;
 use pallas::ledger::primitives::babbage::{DatumOption, NetworkId};
 use pallas::ledger::primitives::{
     alonzo::{
@@ -55,6 +57,159 @@ impl From<&alonzo::StakeCredential> for StakeCredential {
     }
 }
 
+impl From<&conway::DRep> for DRep {
+    fn from(other: &conway::DRep) -> Self {
+        match other {
+            conway::DRep::Key(x) => DRep::AddrKeyhash(x.to_hex()),
+            conway::DRep::Script(x) => DRep::Scripthash(x.to_hex()),
+            conway::DRep::Abstain => DRep::Abstain,
+            conway::DRep::NoConfidence => DRep::NoConfidence,
+        }
+    }
+}
+
+impl From<&conway::Anchor> for AnchorRecord {
+    fn from(other: &conway::Anchor) -> Self {
+        Self {
+            url: other.url.clone(),
+            data_hash: other.content_hash.to_hex(),
+        }
+    }
+}
+
+fn gov_action_to_string(action: &conway::GovAction) -> String {
+    match action {
+        conway::GovAction::ParameterChange(..) => "parameter_change",
+        conway::GovAction::HardForkInitiation(..) => "hard_fork_initiation",
+        conway::GovAction::TreasuryWithdrawals(..) => "treasury_withdrawals",
+        conway::GovAction::NoConfidence(..) => "no_confidence",
+        conway::GovAction::UpdateCommittee(..) => "update_committee",
+        conway::GovAction::NewConstitution(..) => "new_constitution",
+        conway::GovAction::Information => "info",
+    }
+    .to_string()
+}
+
+fn voter_to_record_parts(voter: &conway::Voter) -> (String, StakeCredential) {
+    let (role, credential) = match voter {
+        conway::Voter::ConstitutionalCommitteeKey(x) => (
+            "constitutional_committee",
+            StakeCredential::AddrKeyhash(x.to_hex()),
+        ),
+        conway::Voter::ConstitutionalCommitteeScript(x) => (
+            "constitutional_committee",
+            StakeCredential::Scripthash(x.to_hex()),
+        ),
+        conway::Voter::DRepKey(x) => ("drep", StakeCredential::AddrKeyhash(x.to_hex())),
+        conway::Voter::DRepScript(x) => ("drep", StakeCredential::Scripthash(x.to_hex())),
+        conway::Voter::StakePoolKey(x) => ("stake_pool", StakeCredential::AddrKeyhash(x.to_hex())),
+    };
+
+    (role.to_string(), credential)
+}
+
+fn credential_hex(credential: &StakeCredential) -> &str {
+    match credential {
+        StakeCredential::AddrKeyhash(x) => x,
+        StakeCredential::Scripthash(x) => x,
+    }
+}
+
+/// Sort key that matches the order in which the ledger indexes voters for
+/// vote redeemers: committee before dreps before pools and, within a role,
+/// scripts before keys, then by hash
+fn voter_ledger_order(voter: &conway::Voter) -> (u8, u8, Hash<28>) {
+    match voter {
+        conway::Voter::ConstitutionalCommitteeScript(x) => (0, 0, *x),
+        conway::Voter::ConstitutionalCommitteeKey(x) => (0, 1, *x),
+        conway::Voter::DRepScript(x) => (1, 0, *x),
+        conway::Voter::DRepKey(x) => (1, 1, *x),
+        conway::Voter::StakePoolKey(x) => (2, 1, *x),
+    }
+}
+
+/// Describes the certificate a cert redeemer points at as `kind:credential`,
+/// where the credential is the one the certificate has to be witnessed by
+fn certificate_event_to_target(evt: &EventData) -> Option<String> {
+    let (kind, credential) = match evt {
+        EventData::StakeRegistration { credential } => ("stake_registration", credential),
+        EventData::StakeDeregistration { credential } => ("stake_deregistration", credential),
+        EventData::StakeDelegation { credential, .. } => ("stake_delegation", credential),
+        EventData::StakeRegistrationWithDeposit { credential, .. } => {
+            ("stake_registration_with_deposit", credential)
+        }
+        EventData::StakeDeregistrationWithDeposit { credential, .. } => {
+            ("stake_deregistration_with_deposit", credential)
+        }
+        EventData::VoteDelegation { credential, .. } => ("vote_delegation", credential),
+        EventData::StakeVoteDelegation { credential, .. } => ("stake_vote_delegation", credential),
+        EventData::StakeRegistrationDelegation { credential, .. } => {
+            ("stake_registration_delegation", credential)
+        }
+        EventData::VoteRegistrationDelegation { credential, .. } => {
+            ("vote_registration_delegation", credential)
+        }
+        EventData::StakeVoteRegistrationDelegation { credential, .. } => {
+            ("stake_vote_registration_delegation", credential)
+        }
+        EventData::CommitteeHotAuthorization {
+            committee_cold_credential,
+            ..
+        } => ("committee_hot_authorization", committee_cold_credential),
+        EventData::CommitteeColdResignation {
+            committee_cold_credential,
+            ..
+        } => ("committee_cold_resignation", committee_cold_credential),
+        EventData::DRepRegistration { credential, .. } => ("drep_registration", credential),
+        EventData::DRepDeregistration { credential, .. } => ("drep_deregistration", credential),
+        EventData::DRepUpdate { credential, .. } => ("drep_update", credential),
+        EventData::PoolRegistration { operator, .. } => {
+            return Some(format!("pool_registration:{operator}"))
+        }
+        EventData::PoolRetirement { pool, .. } => return Some(format!("pool_retirement:{pool}")),
+        _ => return None,
+    };
+
+    Some(format!("{kind}:{}", credential_hex(credential)))
+}
+
+/// Converts the Conway certificates that already existed in previous eras, so
+/// that they map to the same events as before
+fn conway_to_alonzo_certificate(cert: &conway::Certificate) -> Option<Certificate> {
+    let out = match cert.clone() {
+        conway::Certificate::StakeRegistration(x) => Certificate::StakeRegistration(x),
+        conway::Certificate::StakeDeregistration(x) => Certificate::StakeDeregistration(x),
+        conway::Certificate::StakeDelegation(x, pool) => Certificate::StakeDelegation(x, pool),
+        conway::Certificate::PoolRegistration {
+            operator,
+            vrf_keyhash,
+            pledge,
+            cost,
+            margin,
+            reward_account,
+            pool_owners,
+            relays,
+            pool_metadata,
+        } => Certificate::PoolRegistration {
+            operator,
+            vrf_keyhash,
+            pledge,
+            cost,
+            margin,
+            reward_account,
+            pool_owners: pool_owners.to_vec(),
+            relays,
+            pool_metadata,
+        },
+        conway::Certificate::PoolRetirement(pool, epoch) => {
+            Certificate::PoolRetirement(pool, epoch)
+        }
+        _ => return None,
+    };
+
+    Some(out)
+}
+
 fn ip_string_from_bytes(bytes: &[u8]) -> String {
     format!("{}.{}.{}.{}", bytes[0], bytes[1], bytes[2], bytes[3])
 }
@@ -74,15 +229,6 @@ impl From<Point> for EventData {
     }
 }
 
-impl From<&MultiEraInput<'_>> for TxInputRecord {
-    fn from(value: &MultiEraInput) -> Self {
-        Self {
-            tx_id: value.hash().to_string(),
-            index: value.index(),
-        }
-    }
-}
-
 impl From<&MultiEraAsset<'_>> for OutputAssetRecord {
     fn from(value: &MultiEraAsset<'_>) -> Self {
         Self {
@@ -94,11 +240,89 @@ impl From<&MultiEraAsset<'_>> for OutputAssetRecord {
     }
 }
 
-impl From<&KeepRaw<'_, alonzo::PlutusData>> for PlutusDatumRecord {
-    fn from(value: &KeepRaw<'_, alonzo::PlutusData>) -> Self {
-        Self {
-            datum_hash: value.original_hash().to_hex(),
-            plutus_data: value.to_json(),
+/// Renders big-endian unsigned bytes as a decimal string, adding one to the
+/// value first when `plus_one` is set (CBOR negative big ints hold `-1 - n`)
+fn big_bytes_to_decimal(bytes: &[u8], plus_one: bool) -> String {
+    let mut value = bytes.to_vec();
+
+    if plus_one {
+        let carry = value.iter_mut().rev().try_for_each(|x| {
+            let (sum, overflow) = x.overflowing_add(1);
+            *x = sum;
+            if overflow {
+                Ok(())
+            } else {
+                Err(())
+            }
+        });
+
+        if carry.is_ok() {
+            value.insert(0, 1);
+        }
+    }
+
+    let mut digits = vec![];
+
+    while value.iter().any(|x| *x != 0) {
+        let mut remainder = 0u32;
+
+        for byte in value.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 10) as u8;
+            remainder = acc % 10;
+        }
+
+        digits.push(char::from(b'0' + remainder as u8));
+    }
+
+    match digits.is_empty() {
+        true => "0".to_string(),
+        false => digits.iter().rev().collect(),
+    }
+}
+
+fn decimal_to_json(decimal: &str) -> JsonValue {
+    serde_json::Number::from_str(decimal)
+        .map(JsonValue::Number)
+        .unwrap_or_else(|_| JsonValue::String(decimal.to_string()))
+}
+
+/// Renders plutus data in the detailed schema of cardano-cli. Unlike the
+/// canonical JSON of pallas, big integers are numbers instead of hex strings.
+fn plutus_data_to_detailed_json(data: &alonzo::PlutusData) -> JsonValue {
+    match data {
+        alonzo::PlutusData::Constr(x) => {
+            let fields: Vec<_> = x.fields.iter().map(plutus_data_to_detailed_json).collect();
+            json!({ "constructor": x.constructor_value(), "fields": fields })
+        }
+        alonzo::PlutusData::Map(x) => {
+            let map: Vec<_> = x
+                .iter()
+                .map(|(k, v)| {
+                    json!({
+                        "k": plutus_data_to_detailed_json(k),
+                        "v": plutus_data_to_detailed_json(v)
+                    })
+                })
+                .collect();
+
+            json!({ "map": map })
+        }
+        alonzo::PlutusData::BigInt(x) => {
+            let decimal = match x {
+                alonzo::BigInt::Int(x) => i128::from(*x.deref()).to_string(),
+                alonzo::BigInt::BigUInt(x) => big_bytes_to_decimal(x.as_slice(), false),
+                alonzo::BigInt::BigNInt(x) => {
+                    format!("-{}", big_bytes_to_decimal(x.as_slice(), true))
+                }
+            };
+
+            json!({ "int": decimal_to_json(&decimal) })
+        }
+        alonzo::PlutusData::BoundedBytes(x) => json!({ "bytes": hex::encode(x.as_slice()) }),
+        alonzo::PlutusData::Array(x) => {
+            let list: Vec<_> = x.iter().map(plutus_data_to_detailed_json).collect();
+            json!({ "list": list })
         }
     }
 }
@@ -138,7 +362,57 @@ fn metadatum_to_string_key(datum: &Metadatum) -> String {
 }
 
 impl EventWriter<'_> {
-    pub fn to_transaction_output_record(&self, output: &MultiEraOutput) -> TxOutputRecord {
+    /// Pallas doesn't keep the original bytes of vkey witnesses or plutus
+    /// scripts, so their CBOR is re-encoded from the decoded value and might
+    /// differ from the on-chain bytes when those weren't canonical.
+    fn to_reencoded_cbor<T: minicbor::Encode<()>>(&self, value: &T) -> Option<String> {
+        match self.config.include_witness_cbor {
+            true => minicbor::to_vec(value).ok().map(hex::encode),
+            false => None,
+        }
+    }
+
+    pub fn to_plutus_data_json(&self, data: &alonzo::PlutusData) -> JsonValue {
+        match self.config.detailed_plutus_data {
+            true => plutus_data_to_detailed_json(data),
+            false => data.to_json(),
+        }
+    }
+
+    pub fn to_plutus_datum_record(
+        &self,
+        datum: &KeepRaw<'_, alonzo::PlutusData>,
+    ) -> PlutusDatumRecord {
+        PlutusDatumRecord {
+            datum_hash: datum.original_hash().to_hex(),
+            plutus_data: self.to_plutus_data_json(datum),
+            cbor_hex: match self.config.include_witness_cbor {
+                true => Some(datum.raw_cbor().to_hex()),
+                false => None,
+            },
+        }
+    }
+
+    pub fn to_transaction_input_record(&self, input: &MultiEraInput) -> TxInputRecord {
+        let tx_id = input.hash().to_string();
+        let index = input.index();
+
+        let spent_output = self
+            .resolved
+            .and_then(|x| x.get(&(tx_id.clone(), index)))
+            .cloned();
+
+        TxInputRecord {
+            tx_id,
+            index,
+            spent_output,
+        }
+    }
+
+    /// The output with all of its details, regardless of the ones configured
+    /// for `TxOutput` events. The cache of `resolve_inputs` keeps these, so
+    /// that spent outputs are complete.
+    pub fn to_full_transaction_output_record(&self, output: &MultiEraOutput) -> TxOutputRecord {
         let address = output.address().or_panic();
         let value = output.value();
 
@@ -158,9 +432,59 @@ impl EventWriter<'_> {
                 None => None,
             },
             inline_datum: match &output.datum() {
-                Some(DatumOption::Data(x)) => Some(PlutusDatumRecord::from(x.deref())),
+                Some(DatumOption::Data(x)) => Some(self.to_plutus_datum_record(x.deref())),
                 _ => None,
             },
+            reference_script: output
+                .script_ref()
+                .map(|x| self.to_reference_script_record(&x)),
+            cbor_hex: match self.config.include_output_cbor {
+                true => Some(hex::encode(output.encode())),
+                false => None,
+            },
+        }
+    }
+
+    pub fn to_transaction_output_record(&self, output: &MultiEraOutput) -> TxOutputRecord {
+        let mut record = self.to_full_transaction_output_record(output);
+
+        if !self.config.is_detailed(EventKind::TxOutput) {
+            record.assets = None;
+            record.inline_datum = None;
+            record.reference_script = None;
+        }
+
+        record
+    }
+
+    /// The raw metadata of CIP events is left out when not detailed
+    pub fn to_raw_json(&self, kind: EventKind, raw_json: JsonValue) -> JsonValue {
+        match self.config.is_detailed(kind) {
+            true => raw_json,
+            false => JsonValue::Null,
+        }
+    }
+
+    pub fn to_reference_script_record(&self, script: &conway::ScriptRef) -> ReferenceScriptRecord {
+        let (script_hash, language, script_hex) = match script {
+            conway::ScriptRef::NativeScript(x) => {
+                (x.compute_hash(), "native", x.raw_cbor().to_hex())
+            }
+            conway::ScriptRef::PlutusV1Script(x) => {
+                (x.compute_hash(), "plutus_v1", x.as_ref().to_hex())
+            }
+            conway::ScriptRef::PlutusV2Script(x) => {
+                (x.compute_hash(), "plutus_v2", x.as_ref().to_hex())
+            }
+            conway::ScriptRef::PlutusV3Script(x) => {
+                (x.compute_hash(), "plutus_v3", x.as_ref().to_hex())
+            }
+        };
+
+        ReferenceScriptRecord {
+            script_hash: script_hash.to_hex(),
+            language: language.to_string(),
+            script_hex,
         }
     }
     pub fn to_withdrawal_record(&self, withdrawal: (&[u8], u64)) -> WithdrawalRecord {
@@ -184,6 +508,10 @@ impl EventWriter<'_> {
                 NetworkId::Testnet => 1,
                 NetworkId::Mainnet => 2,
             }),
+            cbor_hex: match self.config.include_transaction_cbor {
+                true => Some(hex::encode(tx.encode())),
+                false => None,
+            },
             ..Default::default()
         };
 
@@ -196,7 +524,11 @@ impl EventWriter<'_> {
         record.output_count = outputs.len();
         record.total_output = outputs.iter().map(|o| o.amount).sum();
 
-        let inputs: Vec<_> = tx.inputs().iter().map(TxInputRecord::from).collect();
+        let inputs: Vec<_> = tx
+            .inputs()
+            .iter()
+            .map(|x| self.to_transaction_input_record(x))
+            .collect();
 
         record.input_count = inputs.len();
 
@@ -209,7 +541,11 @@ impl EventWriter<'_> {
 
         record.mint_count = mints.len();
 
-        let collateral_inputs: Vec<_> = tx.collateral().iter().map(TxInputRecord::from).collect();
+        let collateral_inputs: Vec<_> = tx
+            .collateral()
+            .iter()
+            .map(|x| self.to_transaction_input_record(x))
+            .collect();
 
         record.collateral_input_count = collateral_inputs.len();
 
@@ -217,12 +553,16 @@ impl EventWriter<'_> {
 
         record.has_collateral_output = collateral_return.is_some();
 
+        let conway_body = tx.as_conway().map(|x| &x.transaction_body);
+
+        record.treasury_donation = conway_body.and_then(|x| x.donation.as_ref()).map(u64::from);
+
         // TODO
         // TransactionBodyComponent::ScriptDataHash(_)
         // TransactionBodyComponent::RequiredSigners(_)
         // TransactionBodyComponent::AuxiliaryDataHash(_)
 
-        if self.config.include_transaction_details {
+        if self.config.is_detailed(EventKind::Transaction) {
             record.outputs = Some(outputs);
             record.inputs = Some(inputs);
             record.mint = Some(mints);
@@ -266,19 +606,25 @@ impl EventWriter<'_> {
                 .map(|x| self.to_plutus_v2_witness_record(x))
                 .collect::<Vec<_>>();
 
-            record.plutus_witnesses = Some([v1_scripts, v2_scripts].concat());
+            let v3_scripts = tx
+                .plutus_v3_scripts()
+                .iter()
+                .map(|x| self.to_plutus_v3_witness_record(x))
+                .collect::<Vec<_>>();
+
+            record.plutus_witnesses = Some([v1_scripts, v2_scripts, v3_scripts].concat());
 
             record.plutus_redeemers = tx
                 .redeemers()
                 .iter()
-                .map(|x| self.to_plutus_redeemer_record(x))
+                .map(|x| self.to_plutus_redeemer_record(tx, x))
                 .collect::<Vec<_>>()
                 .into();
 
             record.plutus_data = tx
                 .plutus_data()
                 .iter()
-                .map(PlutusDatumRecord::from)
+                .map(|x| self.to_plutus_datum_record(x))
                 .collect::<Vec<_>>()
                 .into();
 
@@ -290,13 +636,21 @@ impl EventWriter<'_> {
                 .collect::<Vec<_>>()
                 .into();
 
+            record.proposal_procedures = conway_body
+                .and_then(|x| x.proposal_procedures.as_ref())
+                .map(|x| x.iter().map(|x| self.to_gov_proposal_record(x)).collect());
+
+            record.voting_procedures = conway_body
+                .and_then(|x| x.voting_procedures.as_ref())
+                .map(|x| self.to_gov_vote_records(x));
+
             record.reference_inputs = if tx.reference_inputs().is_empty() {
                 None
             } else {
                 Some(
                     tx.reference_inputs()
                         .iter()
-                        .map(TxInputRecord::from)
+                        .map(|x| self.to_transaction_input_record(x))
                         .collect(),
                 )
             };
@@ -331,7 +685,9 @@ impl EventWriter<'_> {
             transactions: None,
         };
 
-        if self.config.include_block_details || self.config.include_transaction_details {
+        if self.config.is_detailed(EventKind::Block)
+            || self.config.is_detailed(EventKind::Transaction)
+        {
             let txs = source
                 .txs()
                 .iter()
@@ -409,7 +765,61 @@ impl EventWriter<'_> {
         }
     }
 
-    pub fn to_plutus_redeemer_record(&self, redeemer: &MultiEraRedeemer) -> PlutusRedeemerRecord {
+    /// Describes what a redeemer unlocks, by looking up its index in the
+    /// sorted collection that matches its purpose
+    pub fn to_redeemer_target(
+        &self,
+        tx: &MultiEraTx,
+        redeemer: &MultiEraRedeemer,
+    ) -> Option<String> {
+        let idx = redeemer.index() as usize;
+
+        match redeemer.tag() {
+            conway::RedeemerTag::Spend => tx
+                .inputs_sorted_set()
+                .get(idx)
+                .map(|x| format!("{}#{}", x.hash(), x.index())),
+            conway::RedeemerTag::Mint => tx
+                .mints_sorted_set()
+                .get(idx)
+                .map(|x| x.policy().to_string()),
+            conway::RedeemerTag::Cert => tx
+                .certs()
+                .get(idx)
+                .and_then(|x| self.to_certificate_event(x))
+                .and_then(|x| certificate_event_to_target(&x)),
+            conway::RedeemerTag::Reward => tx
+                .withdrawals_sorted_set()
+                .get(idx)
+                .map(|x| self.to_withdrawal_record(*x).reward_account),
+            conway::RedeemerTag::Vote => tx
+                .as_conway()
+                .and_then(|x| x.transaction_body.voting_procedures.as_ref())
+                .and_then(|x| {
+                    let mut voters: Vec<_> = x.keys().collect();
+                    voters.sort_by_key(|x| voter_ledger_order(x));
+                    voters.get(idx).copied()
+                })
+                .map(|x| credential_hex(&voter_to_record_parts(x).1).to_string()),
+            conway::RedeemerTag::Propose => tx
+                .as_conway()
+                .and_then(|x| x.transaction_body.proposal_procedures.as_ref())
+                .and_then(|x| x.get(idx))
+                .map(|x| {
+                    format!(
+                        "{}:{}",
+                        gov_action_to_string(&x.gov_action),
+                        x.reward_account.to_hex()
+                    )
+                }),
+        }
+    }
+
+    pub fn to_plutus_redeemer_record(
+        &self,
+        tx: &MultiEraTx,
+        redeemer: &MultiEraRedeemer,
+    ) -> PlutusRedeemerRecord {
         PlutusRedeemerRecord {
             purpose: match redeemer.tag() {
                 conway::RedeemerTag::Spend => "spend".to_string(),
@@ -422,7 +832,13 @@ impl EventWriter<'_> {
             ex_units_mem: redeemer.ex_units().mem as u32,
             ex_units_steps: redeemer.ex_units().steps,
             input_idx: redeemer.index(),
-            plutus_data: redeemer.data().to_json(),
+            plutus_data: self.to_plutus_data_json(redeemer.data()),
+            target: self.to_redeemer_target(tx, redeemer),
+            // re-encoded, pallas doesn't keep the raw bytes of each redeemer
+            cbor_hex: match self.config.include_witness_cbor {
+                true => Some(hex::encode(redeemer.encode())),
+                false => None,
+            },
         }
     }
 
@@ -433,6 +849,7 @@ impl EventWriter<'_> {
         PlutusWitnessRecord {
             script_hash: script.compute_hash().to_hex(),
             script_hex: script.as_ref().to_hex(),
+            cbor_hex: self.to_reencoded_cbor(script),
         }
     }
 
@@ -443,13 +860,32 @@ impl EventWriter<'_> {
         PlutusWitnessRecord {
             script_hash: script.compute_hash().to_hex(),
             script_hex: script.as_ref().to_hex(),
+            cbor_hex: self.to_reencoded_cbor(script),
         }
     }
 
-    pub fn to_native_witness_record(&self, script: &alonzo::NativeScript) -> NativeWitnessRecord {
+    pub fn to_plutus_v3_witness_record(
+        &self,
+        script: &conway::PlutusScript<3>,
+    ) -> PlutusWitnessRecord {
+        PlutusWitnessRecord {
+            script_hash: script.compute_hash().to_hex(),
+            script_hex: script.as_ref().to_hex(),
+            cbor_hex: self.to_reencoded_cbor(script),
+        }
+    }
+
+    pub fn to_native_witness_record(
+        &self,
+        script: &KeepRaw<'_, alonzo::NativeScript>,
+    ) -> NativeWitnessRecord {
         NativeWitnessRecord {
             policy_id: script.compute_hash().to_hex(),
             script_json: script.to_json(),
+            cbor_hex: match self.config.include_witness_cbor {
+                true => Some(script.raw_cbor().to_hex()),
+                false => None,
+            },
         }
     }
 
@@ -457,11 +893,143 @@ impl EventWriter<'_> {
         VKeyWitnessRecord {
             vkey_hex: witness.vkey.to_hex(),
             signature_hex: witness.signature.to_hex(),
+            cbor_hex: self.to_reencoded_cbor(witness),
         }
     }
 
+    pub fn to_gov_proposal_record(
+        &self,
+        proposal: &conway::ProposalProcedure,
+    ) -> GovProposalRecord {
+        GovProposalRecord {
+            deposit: proposal.deposit,
+            reward_account: proposal.reward_account.to_hex(),
+            action: gov_action_to_string(&proposal.gov_action),
+            anchor: AnchorRecord::from(&proposal.anchor),
+        }
+    }
+
+    pub fn to_gov_vote_records(&self, procedures: &conway::VotingProcedures) -> Vec<GovVoteRecord> {
+        let mut out = vec![];
+
+        for (voter, votes) in procedures.iter() {
+            let (voter_role, voter) = voter_to_record_parts(voter);
+
+            for (action, procedure) in votes.iter() {
+                out.push(GovVoteRecord {
+                    voter_role: voter_role.clone(),
+                    voter: voter.clone(),
+                    action_tx_id: action.transaction_id.to_hex(),
+                    action_index: action.action_index,
+                    vote: match procedure.vote {
+                        conway::Vote::Yes => "yes",
+                        conway::Vote::No => "no",
+                        conway::Vote::Abstain => "abstain",
+                    }
+                    .to_string(),
+                    anchor: procedure.anchor.as_ref().map(AnchorRecord::from),
+                });
+            }
+        }
+
+        out
+    }
+
     pub fn to_certificate_event(&self, cert: &MultiEraCert) -> Option<EventData> {
-        let evt = match cert.as_alonzo()? {
+        match cert {
+            MultiEraCert::AlonzoCompatible(x) => Some(self.to_alonzo_certificate_event(x)),
+            MultiEraCert::Conway(x) => self.to_conway_certificate_event(x),
+            _ => None,
+        }
+    }
+
+    pub fn to_conway_certificate_event(&self, cert: &conway::Certificate) -> Option<EventData> {
+        let evt = match cert {
+            conway::Certificate::Reg(credential, deposit) => {
+                EventData::StakeRegistrationWithDeposit {
+                    credential: credential.into(),
+                    deposit: *deposit,
+                }
+            }
+            conway::Certificate::UnReg(credential, deposit) => {
+                EventData::StakeDeregistrationWithDeposit {
+                    credential: credential.into(),
+                    deposit: *deposit,
+                }
+            }
+            conway::Certificate::VoteDeleg(credential, drep) => EventData::VoteDelegation {
+                credential: credential.into(),
+                drep: drep.into(),
+            },
+            conway::Certificate::StakeVoteDeleg(credential, pool, drep) => {
+                EventData::StakeVoteDelegation {
+                    credential: credential.into(),
+                    pool_hash: pool.to_hex(),
+                    drep: drep.into(),
+                }
+            }
+            conway::Certificate::StakeRegDeleg(credential, pool, deposit) => {
+                EventData::StakeRegistrationDelegation {
+                    credential: credential.into(),
+                    pool_hash: pool.to_hex(),
+                    deposit: *deposit,
+                }
+            }
+            conway::Certificate::VoteRegDeleg(credential, drep, deposit) => {
+                EventData::VoteRegistrationDelegation {
+                    credential: credential.into(),
+                    drep: drep.into(),
+                    deposit: *deposit,
+                }
+            }
+            conway::Certificate::StakeVoteRegDeleg(credential, pool, drep, deposit) => {
+                EventData::StakeVoteRegistrationDelegation {
+                    credential: credential.into(),
+                    pool_hash: pool.to_hex(),
+                    drep: drep.into(),
+                    deposit: *deposit,
+                }
+            }
+            conway::Certificate::AuthCommitteeHot(cold, hot) => {
+                EventData::CommitteeHotAuthorization {
+                    committee_cold_credential: cold.into(),
+                    committee_hot_credential: hot.into(),
+                }
+            }
+            conway::Certificate::ResignCommitteeCold(cold, anchor) => {
+                EventData::CommitteeColdResignation {
+                    committee_cold_credential: cold.into(),
+                    anchor: anchor.as_ref().map(AnchorRecord::from),
+                }
+            }
+            conway::Certificate::RegDRepCert(credential, deposit, anchor) => {
+                EventData::DRepRegistration {
+                    credential: credential.into(),
+                    deposit: *deposit,
+                    anchor: anchor.as_ref().map(AnchorRecord::from),
+                }
+            }
+            conway::Certificate::UnRegDRepCert(credential, deposit) => {
+                EventData::DRepDeregistration {
+                    credential: credential.into(),
+                    deposit: *deposit,
+                }
+            }
+            conway::Certificate::UpdateDRepCert(credential, anchor) => EventData::DRepUpdate {
+                credential: credential.into(),
+                anchor: anchor.as_ref().map(AnchorRecord::from),
+            },
+            x => {
+                let legacy = conway_to_alonzo_certificate(x)?;
+                self.to_alonzo_certificate_event(&legacy)
+            }
+        };
+
+        Some(evt)
+    }
+
+    pub fn to_alonzo_certificate_event(&self, cert: &Certificate) -> EventData {
+        match cert {
             Certificate::StakeRegistration(credential) => EventData::StakeRegistration {
                 credential: credential.into(),
             },
@@ -517,9 +1085,7 @@ impl EventWriter<'_> {
             }
             // TODO: not likely, leaving for later
             Certificate::GenesisKeyDelegation(..) => EventData::GenesisKeyDelegation {},
-        };
-
-        Some(evt)
+        }
     }
 
     pub fn to_collateral_event(&self, collateral: &MultiEraInput) -> EventData {
@@ -529,3 +1095,157 @@ impl EventWriter<'_> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pallas::ledger::traverse::wellknown::GenesisValues;
+
+    use super::*;
+    use crate::framework::MapperOutputPort;
+
+    fn detailed_int(value: alonzo::BigInt) -> String {
+        plutus_data_to_detailed_json(&alonzo::PlutusData::BigInt(value))["int"].to_string()
+    }
+
+    #[test]
+    fn big_bytes_decimals() {
+        assert_eq!(big_bytes_to_decimal(&[], false), "0");
+        assert_eq!(big_bytes_to_decimal(&[0, 0], false), "0");
+        assert_eq!(big_bytes_to_decimal(&[], true), "1");
+        assert_eq!(
+            big_bytes_to_decimal(&[0xff; 8], false),
+            "18446744073709551615"
+        );
+        assert_eq!(
+            big_bytes_to_decimal(&[0xff; 8], true),
+            "18446744073709551616"
+        );
+        assert_eq!(big_bytes_to_decimal(&[0x01, 0xff], true), "512");
+        assert_eq!(big_bytes_to_decimal(&[0x01, 0xfe], true), "511");
+        assert_eq!(
+            big_bytes_to_decimal(&[0xff; 16], false),
+            u128::MAX.to_string()
+        );
+    }
+
+    #[test]
+    fn detailed_plutus_ints() {
+        let int = |x: i128| alonzo::BigInt::Int(x.try_into().unwrap());
+        let big = |x: Vec<u8>| alonzo::BigInt::BigUInt(x.into());
+        let neg = |x: Vec<u8>| alonzo::BigInt::BigNInt(x.into());
+
+        assert_eq!(detailed_int(int(0)), "0");
+        assert_eq!(detailed_int(int(u64::MAX as i128)), "18446744073709551615");
+        assert_eq!(
+            detailed_int(int(-(u64::MAX as i128) - 1)),
+            "-18446744073709551616"
+        );
+
+        assert_eq!(detailed_int(big(vec![])), "0");
+        assert_eq!(
+            detailed_int(big(vec![1, 0, 0, 0, 0, 0, 0, 0, 0])),
+            "18446744073709551616"
+        );
+
+        // a bignint encodes -1 - n, so an empty one is -1
+        assert_eq!(detailed_int(neg(vec![])), "-1");
+        assert_eq!(
+            detailed_int(neg(vec![1, 0, 0, 0, 0, 0, 0, 0, 0])),
+            "-18446744073709551617"
+        );
+        assert_eq!(
+            detailed_int(neg(vec![0xff; 16])),
+            "-340282366920938463463374607431768211456"
+        );
+    }
+
+    #[test]
+    fn detailed_plutus_structure() {
+        let data = alonzo::PlutusData::Constr(alonzo::Constr {
+            tag: 121,
+            any_constructor: None,
+            fields: pallas::codec::utils::MaybeIndefArray::Def(vec![
+                alonzo::PlutusData::BoundedBytes(vec![0xca, 0xfe].into()),
+                alonzo::PlutusData::Array(pallas::codec::utils::MaybeIndefArray::Def(vec![])),
+            ]),
+        });
+
+        assert_eq!(
+            plutus_data_to_detailed_json(&data),
+            json!({
+                "constructor": 0,
+                "fields": [{ "bytes": "cafe" }, { "list": [] }]
+            })
+        );
+    }
+
+    #[test]
+    fn voters_in_ledger_order() {
+        let mut voters = vec![
+            conway::Voter::StakePoolKey(Hash::new([0; 28])),
+            conway::Voter::DRepKey(Hash::new([1; 28])),
+            conway::Voter::DRepKey(Hash::new([0; 28])),
+            conway::Voter::DRepScript(Hash::new([9; 28])),
+            conway::Voter::ConstitutionalCommitteeKey(Hash::new([0; 28])),
+            conway::Voter::ConstitutionalCommitteeScript(Hash::new([9; 28])),
+        ];
+
+        voters.sort_by_key(voter_ledger_order);
+
+        assert_eq!(
+            voters,
+            vec![
+                conway::Voter::ConstitutionalCommitteeScript(Hash::new([9; 28])),
+                conway::Voter::ConstitutionalCommitteeKey(Hash::new([0; 28])),
+                conway::Voter::DRepScript(Hash::new([9; 28])),
+                conway::Voter::DRepKey(Hash::new([0; 28])),
+                conway::Voter::DRepKey(Hash::new([1; 28])),
+                conway::Voter::StakePoolKey(Hash::new([0; 28])),
+            ]
+        );
+    }
+
+    #[test]
+    fn certificate_targets() {
+        let output = MapperOutputPort::default();
+        let config = super::super::Config::default();
+        let genesis = GenesisValues::mainnet();
+        let mut buffer = vec![];
+        let mapper = EventWriter::new(
+            Point::Origin,
+            false,
+            &output,
+            &config,
+            &genesis,
+            &mut buffer,
+        );
+
+        let cert = conway::Certificate::StakeDelegation(
+            conway::StakeCredential::ScriptHash(Hash::new([1; 28])),
+            Hash::new([2; 28]),
+        );
+
+        let target = mapper
+            .to_conway_certificate_event(&cert)
+            .and_then(|x| certificate_event_to_target(&x));
+
+        assert_eq!(
+            target.as_deref(),
+            Some("stake_delegation:01010101010101010101010101010101010101010101010101010101")
+        );
+
+        let cert = conway::Certificate::UpdateDRepCert(
+            conway::StakeCredential::AddrKeyhash(Hash::new([3; 28])),
+            None,
+        );
+
+        let target = mapper
+            .to_conway_certificate_event(&cert)
+            .and_then(|x| certificate_event_to_target(&x));
+
+        assert_eq!(
+            target.as_deref(),
+            Some("drep_update:03030303030303030303030303030303030303030303030303030303")
+        );
+    }
+}
@@ -1,17 +1,25 @@
 //! A mapper that maintains schema-compatibility with Oura v1
 
 mod cip15;
+mod cip20;
 mod cip25;
+mod cip36;
+mod cip68;
 mod crawl;
 mod map;
 mod prelude;
+mod utxos;
+
+use std::collections::{HashMap, HashSet};
 
 use gasket::framework::*;
 use pallas::ledger::traverse::wellknown::GenesisValues;
 use serde::Deserialize;
 
+use crate::framework::legacy_v1::{EventData, EventKind};
 use crate::framework::*;
 pub use prelude::*;
+use utxos::UtxoCache;
 
 #[derive(Stage)]
 #[stage(name = "filter-legacy", unit = "ChainEvent", worker = "Worker")]
@@ -26,33 +34,66 @@ pub struct Stage {
     ops_count: gasket::metrics::Counter,
 }
 
-#[derive(Default)]
-pub struct Worker;
+pub struct Worker {
+    utxos: UtxoCache,
+}
 
 impl From<&Stage> for Worker {
-    fn from(_: &Stage) -> Self {
-        Self
+    fn from(stage: &Stage) -> Self {
+        let max_entries = stage
+            .config
+            .utxo_cache_max_entries
+            .unwrap_or(utxos::DEFAULT_MAX_ENTRIES);
+
+        Self {
+            utxos: UtxoCache::new(max_entries),
+        }
     }
 }
 
-gasket::impl_splitter!(|_worker: Worker, stage: Stage, unit: ChainEvent| => {
+gasket::impl_splitter!(|worker: Worker, stage: Stage, unit: ChainEvent| => {
     let mut buffer = Vec::new();
 
+    let utxos = match stage.config.resolve_inputs {
+        true => Some(&mut worker.utxos),
+        false => None,
+    };
+
     match unit {
-        ChainEvent::Apply(point, Record::CborBlock(cbor)) => {
+        ChainEvent::Apply(point, Record::CborBlock(cbor), _) => {
             let mut writer = EventWriter::new(
                 point.clone(),
+                false,
                 &stage.output,
                 &stage.config,
                 &stage.genesis,
                 &mut buffer,
             );
 
-            writer.crawl_cbor(cbor)?;
+            writer.crawl_cbor(cbor, utxos)?;
+        }
+        ChainEvent::Undo(point, Record::CborBlock(cbor), _) => {
+            // same events as when the block was applied, so that consumers can
+            // revert each of them
+            let mut writer = EventWriter::new(
+                point.clone(),
+                true,
+                &stage.output,
+                &stage.config,
+                &stage.genesis,
+                &mut buffer,
+            );
+
+            writer.crawl_cbor(cbor, utxos)?;
         }
         ChainEvent::Reset(point) => {
+            if let Some(utxos) = utxos {
+                utxos.rollback_to(point);
+            }
+
             let mut writer = EventWriter::new(
                 point.clone(),
+                false,
                 &stage.output,
                 &stage.config,
                 &stage.genesis,
@@ -85,6 +126,70 @@ pub struct Config {
 
     #[serde(default)]
     pub include_block_cbor: bool,
+
+    #[serde(default)]
+    pub include_transaction_cbor: bool,
+
+    #[serde(default)]
+    pub include_output_cbor: bool,
+
+    #[serde(default)]
+    pub include_witness_cbor: bool,
+
+    /// render plutus data in the detailed schema of cardano-cli, with big
+    /// integers as plain numbers
+    #[serde(default)]
+    pub detailed_plutus_data: bool,
+
+    /// attach the spent output to each input, as long as it was produced by a
+    /// block that went through the pipeline
+    #[serde(default)]
+    pub resolve_inputs: bool,
+
+    /// how many outputs the cache of `resolve_inputs` holds before evicting
+    /// the oldest ones
+    #[serde(default)]
+    pub utxo_cache_max_entries: Option<usize>,
+
+    /// kinds of events to emit, all of them when not set
+    #[serde(default)]
+    pub events: Option<HashSet<EventKind>>,
+
+    /// level of detail of each kind of event, overriding the defaults
+    #[serde(default)]
+    pub details: HashMap<EventKind, bool>,
+}
+
+impl Config {
+    pub fn emits(&self, data: &EventData) -> bool {
+        self.emits_kind(EventKind::from(data))
+    }
+
+    /// Whether a kind of event is allowed, checked before building it
+    pub fn emits_kind(&self, kind: EventKind) -> bool {
+        match &self.events {
+            Some(allowed) => allowed.contains(&kind),
+            None => true,
+        }
+    }
+
+    /// Whether any of the kinds is allowed, used to skip whole crawl branches
+    pub fn emits_any(&self, kinds: &[EventKind]) -> bool {
+        kinds.iter().any(|x| self.emits_kind(*x))
+    }
+
+    /// Whether to include the optional, heavier parts of a kind of event
+    pub fn is_detailed(&self, kind: EventKind) -> bool {
+        if let Some(x) = self.details.get(&kind) {
+            return *x;
+        }
+
+        match kind {
+            EventKind::Block | EventKind::BlockEnd => self.include_block_details,
+            EventKind::Transaction | EventKind::TransactionEnd => self.include_transaction_details,
+            _ => true,
+        }
+    }
 }
 
 impl Config {
@@ -6,20 +6,24 @@ use merge::Merge;
 use pallas::ledger::traverse::wellknown::GenesisValues;
 use pallas::network::miniprotocols::Point;
 
+use super::utxos::ResolvedInputs;
 use super::Config;
 
 pub struct EventWriter<'a> {
     context: EventContext,
-    point: Point,
+    pub(crate) point: Point,
+    pub(crate) undo: bool,
     output: &'a MapperOutputPort,
     pub(crate) config: &'a Config,
     pub(crate) genesis: &'a GenesisValues,
+    pub(crate) resolved: Option<&'a ResolvedInputs>,
     buffer: &'a mut Vec<ChainEvent>,
 }
 
 impl<'a> EventWriter<'a> {
     pub fn new(
         point: Point,
+        undo: bool,
         output: &'a MapperOutputPort,
         config: &'a Config,
         genesis: &'a GenesisValues,
@@ -28,21 +32,31 @@ impl<'a> EventWriter<'a> {
         EventWriter {
             context: EventContext::default(),
             point,
+            undo,
             output,
             config,
             genesis,
+            resolved: None,
             buffer,
         }
     }
 
     pub fn append(&mut self, data: EventData) -> Result<(), WorkerError> {
+        if !self.config.emits(&data) {
+            return Ok(());
+        }
+
         let evt = Event {
             context: self.context.clone(),
             data,
             fingerprint: None,
         };
 
-        let msg = ChainEvent::Apply(self.point.clone(), Record::OuraV1Event(evt));
+        let msg = match self.undo {
+            true => ChainEvent::Undo(self.point.clone(), Record::OuraV1Event(evt), None),
+            false => ChainEvent::Apply(self.point.clone(), Record::OuraV1Event(evt), None),
+        };
+
         self.buffer.push(msg);
 
         Ok(())
@@ -61,9 +75,11 @@ impl<'a> EventWriter<'a> {
         EventWriter {
             context: extra_context,
             point: self.point.clone(),
+            undo: self.undo,
             output: self.output,
             config: self.config,
             genesis: self.genesis,
+            resolved: self.resolved,
             buffer: self.buffer,
         }
     }
@@ -0,0 +1,405 @@
+//! A cache of the outputs produced by the blocks that went through the
+//! pipeline, used to resolve the inputs that spend them
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraOutput, MultiEraTx};
+use pallas::network::miniprotocols::Point;
+
+use crate::framework::legacy_v1::TxOutputRecord;
+
+/// how many blocks can be undone, matches the security param of mainnet
+const MAX_JOURNAL_LENGTH: usize = 2160;
+
+/// how many outputs are cached when no bound is configured
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+/// Tx hash and index of an output
+pub type OutputRef = (String, u64);
+
+/// The spent outputs of the inputs of a block that could be resolved
+pub type ResolvedInputs = HashMap<OutputRef, TxOutputRecord>;
+
+/// The outputs a tx refers to, by what it does with them
+struct TxEffects {
+    /// both the inputs and the collateral are resolved, regardless of which
+    /// of them the tx ends up consuming
+    resolves: Vec<OutputRef>,
+    consumes: Vec<OutputRef>,
+    produces: Vec<(OutputRef, TxOutputRecord)>,
+}
+
+impl TxEffects {
+    fn from_tx<F>(tx: &MultiEraTx, map: F) -> Self
+    where
+        F: Fn(&MultiEraOutput) -> TxOutputRecord,
+    {
+        let key = |x: &pallas::ledger::traverse::MultiEraInput| (x.hash().to_string(), x.index());
+
+        Self {
+            resolves: tx
+                .inputs()
+                .iter()
+                .chain(tx.collateral().iter())
+                .map(key)
+                .collect(),
+            consumes: tx.consumes().iter().map(key).collect(),
+            produces: tx
+                .produces()
+                .iter()
+                .map(|(idx, x)| ((tx.hash().to_string(), *idx as u64), map(x)))
+                .collect(),
+        }
+    }
+}
+
+/// Changes of a block to the cache, kept to revert them on rollback
+struct BlockJournal {
+    point: Point,
+    produced: Vec<OutputRef>,
+    consumed: Vec<(OutputRef, CachedOutput)>,
+}
+
+/// An output along with the order in which it entered the cache
+type CachedOutput = (u64, TxOutputRecord);
+
+/// Outputs are evicted oldest first once the cache holds more than
+/// `max_entries` of them. Inputs spending an evicted output are left
+/// unresolved.
+pub struct UtxoCache {
+    outputs: HashMap<OutputRef, CachedOutput>,
+    order: BTreeMap<u64, OutputRef>,
+    next_seq: u64,
+    max_entries: usize,
+    journal: VecDeque<BlockJournal>,
+}
+
+impl Default for UtxoCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl UtxoCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            outputs: HashMap::new(),
+            order: BTreeMap::new(),
+            next_seq: 0,
+            max_entries,
+            journal: VecDeque::new(),
+        }
+    }
+
+    fn insert(&mut self, key: OutputRef, (seq, output): CachedOutput) {
+        if let Some((old, _)) = self.outputs.insert(key.clone(), (seq, output)) {
+            self.order.remove(&old);
+        }
+
+        self.order.insert(seq, key);
+    }
+
+    fn remove(&mut self, key: &OutputRef) -> Option<CachedOutput> {
+        let entry = self.outputs.remove(key)?;
+        self.order.remove(&entry.0);
+        Some(entry)
+    }
+
+    fn evict(&mut self) {
+        while self.outputs.len() > self.max_entries {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+
+            self.outputs.remove(&key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.outputs.clear();
+        self.order.clear();
+        self.journal.clear();
+    }
+
+    /// Applies the block to the cache, returning the outputs spent by its
+    /// inputs. Outputs produced before the pipeline started are not known.
+    pub fn apply<F>(&mut self, point: &Point, block: &MultiEraBlock, map: F) -> ResolvedInputs
+    where
+        F: Fn(&MultiEraOutput) -> TxOutputRecord,
+    {
+        let txs = block.txs();
+        let effects = txs.iter().map(|x| TxEffects::from_tx(x, &map));
+
+        self.apply_txs(point, effects)
+    }
+
+    /// Reverts the block at the point, returning the outputs spent by its
+    /// inputs. These are resolved against the state before the block, which
+    /// is left as is once the block is undone.
+    pub fn apply_undone<F>(
+        &mut self,
+        point: &Point,
+        block: &MultiEraBlock,
+        map: F,
+    ) -> ResolvedInputs
+    where
+        F: Fn(&MultiEraOutput) -> TxOutputRecord,
+    {
+        let txs = block.txs();
+        let effects = txs.iter().map(|x| TxEffects::from_tx(x, &map));
+
+        self.undo_txs(point, effects)
+    }
+
+    fn undo_txs(
+        &mut self,
+        point: &Point,
+        txs: impl IntoIterator<Item = TxEffects>,
+    ) -> ResolvedInputs {
+        self.undo(point);
+        let resolved = self.apply_txs(point, txs);
+        self.undo(point);
+        resolved
+    }
+
+    fn apply_txs(
+        &mut self,
+        point: &Point,
+        txs: impl IntoIterator<Item = TxEffects>,
+    ) -> ResolvedInputs {
+        let mut resolved = ResolvedInputs::new();
+
+        let mut journal = BlockJournal {
+            point: point.clone(),
+            produced: vec![],
+            consumed: vec![],
+        };
+
+        for tx in txs {
+            for key in tx.resolves {
+                if let Some((_, output)) = self.outputs.get(&key) {
+                    resolved.insert(key, output.clone());
+                }
+            }
+
+            for key in tx.consumes {
+                if let Some(entry) = self.remove(&key) {
+                    journal.consumed.push((key, entry));
+                }
+            }
+
+            for (key, output) in tx.produces {
+                self.insert(key.clone(), (self.next_seq, output));
+                self.next_seq += 1;
+                journal.produced.push(key);
+            }
+        }
+
+        self.evict();
+
+        self.journal.push_back(journal);
+
+        if self.journal.len() > MAX_JOURNAL_LENGTH {
+            self.journal.pop_front();
+        }
+
+        resolved
+    }
+
+    fn revert(&mut self, journal: BlockJournal) {
+        for key in journal.produced {
+            self.remove(&key);
+        }
+
+        for (key, entry) in journal.consumed {
+            self.insert(key, entry);
+        }
+
+        self.evict();
+    }
+
+    /// Reverts the block at the point, if it was the last one applied
+    pub fn undo(&mut self, point: &Point) {
+        if self.journal.back().is_some_and(|x| x.point == *point) {
+            let journal = self.journal.pop_back().unwrap();
+            self.revert(journal);
+        }
+    }
+
+    /// Reverts every block after the point. When the point is not one of
+    /// the blocks in the journal, the changes since then can't be reverted
+    /// and the whole cache is dropped.
+    pub fn rollback_to(&mut self, point: &Point) {
+        if !self.journal.iter().any(|x| x.point == *point) {
+            self.clear();
+            return;
+        }
+
+        while self.journal.back().is_some_and(|x| x.point != *point) {
+            let journal = self.journal.pop_back().unwrap();
+            self.revert(journal);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn point(slot: u64, hash: u8) -> Point {
+        Point::Specific(slot, vec![hash; 32])
+    }
+
+    fn output(address: &str) -> TxOutputRecord {
+        TxOutputRecord {
+            address: address.to_string(),
+            amount: 1000000,
+            assets: None,
+            datum_hash: None,
+            inline_datum: None,
+            reference_script: None,
+            cbor_hex: None,
+        }
+    }
+
+    fn utxo(tx: &str, idx: u64) -> OutputRef {
+        (tx.to_string(), idx)
+    }
+
+    /// A tx that spends the given outputs and produces `count` new ones,
+    /// each with its ref as address
+    fn tx(hash: &str, spends: &[OutputRef], count: u64) -> TxEffects {
+        TxEffects {
+            resolves: spends.to_vec(),
+            consumes: spends.to_vec(),
+            produces: (0..count)
+                .map(|idx| (utxo(hash, idx), output(&format!("{hash}#{idx}"))))
+                .collect(),
+        }
+    }
+
+    fn utxos(cache: &UtxoCache) -> BTreeSet<OutputRef> {
+        cache.outputs.keys().cloned().collect()
+    }
+
+    #[test]
+    fn undo_restores_consumed_outputs() {
+        let mut cache = UtxoCache::default();
+
+        cache.apply_txs(&point(1, 1), [tx("a", &[], 2)]);
+
+        let resolved = cache.apply_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+        assert_eq!(resolved.get(&utxo("a", 0)), Some(&output("a#0")));
+        assert_eq!(utxos(&cache), [utxo("a", 1), utxo("b", 0)].into());
+
+        cache.undo(&point(2, 2));
+        assert_eq!(utxos(&cache), [utxo("a", 0), utxo("a", 1)].into());
+        assert_eq!(cache.outputs[&utxo("a", 0)].1, output("a#0"));
+
+        // only the last block can be undone
+        cache.apply_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+        cache.undo(&point(1, 1));
+        assert_eq!(utxos(&cache), [utxo("a", 1), utxo("b", 0)].into());
+    }
+
+    #[test]
+    fn rollback_to_journaled_point() {
+        let mut cache = UtxoCache::default();
+
+        cache.apply_txs(&point(1, 1), [tx("a", &[], 2)]);
+        cache.apply_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+        cache.apply_txs(&point(3, 3), [tx("c", &[utxo("b", 0)], 1)]);
+
+        cache.rollback_to(&point(1, 1));
+
+        assert_eq!(utxos(&cache), [utxo("a", 0), utxo("a", 1)].into());
+        assert_eq!(cache.journal.len(), 1);
+    }
+
+    #[test]
+    fn rollback_to_unknown_point() {
+        let mut cache = UtxoCache::default();
+
+        cache.apply_txs(&point(1, 1), [tx("a", &[], 2)]);
+        cache.apply_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+
+        // the changes since then aren't known, so nothing can be trusted
+        cache.rollback_to(&point(0, 9));
+
+        assert!(cache.outputs.is_empty());
+        assert!(cache.order.is_empty());
+        assert!(cache.journal.is_empty());
+    }
+
+    #[test]
+    fn rollback_to_other_block_of_same_slot() {
+        let mut cache = UtxoCache::default();
+
+        cache.apply_txs(&point(1, 1), [tx("a", &[], 2)]);
+        cache.apply_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+
+        // a block of another fork can't be undone
+        cache.undo(&point(2, 7));
+        assert_eq!(utxos(&cache), [utxo("a", 1), utxo("b", 0)].into());
+
+        cache.rollback_to(&point(2, 7));
+        assert!(cache.outputs.is_empty());
+        assert!(cache.journal.is_empty());
+    }
+
+    #[test]
+    fn evicts_oldest_outputs() {
+        let mut cache = UtxoCache::new(2);
+
+        cache.apply_txs(&point(1, 1), [tx("a", &[], 2)]);
+        cache.apply_txs(&point(2, 2), [tx("b", &[], 1)]);
+        assert_eq!(utxos(&cache), [utxo("a", 1), utxo("b", 0)].into());
+
+        // the evicted output can't be resolved anymore
+        let resolved = cache.apply_txs(&point(3, 3), [tx("c", &[utxo("a", 0), utxo("a", 1)], 1)]);
+        assert_eq!(resolved.len(), 1);
+        assert!(resolved.contains_key(&utxo("a", 1)));
+        assert_eq!(utxos(&cache), [utxo("b", 0), utxo("c", 0)].into());
+
+        // reverting restores what was consumed, but not what was evicted
+        cache.undo(&point(3, 3));
+        assert_eq!(utxos(&cache), [utxo("a", 1), utxo("b", 0)].into());
+
+        // the restored output keeps its age, so it's the next one to go
+        cache.apply_txs(&point(3, 4), [tx("d", &[], 1)]);
+        assert_eq!(utxos(&cache), [utxo("b", 0), utxo("d", 0)].into());
+
+        cache.undo(&point(3, 4));
+        cache.undo(&point(2, 2));
+        assert!(cache.outputs.is_empty());
+        assert!(cache.order.is_empty());
+    }
+
+    #[test]
+    fn undone_block_leaves_cache_as_before() {
+        let chain = || {
+            let mut cache = UtxoCache::default();
+            cache.apply_txs(&point(1, 1), [tx("a", &[], 2)]);
+            cache.apply_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+            cache
+        };
+
+        let mut expected = chain();
+        expected.undo(&point(2, 2));
+
+        let mut cache = chain();
+        let resolved = cache.undo_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+
+        assert_eq!(resolved.get(&utxo("a", 0)), Some(&output("a#0")));
+        assert_eq!(cache.outputs, expected.outputs);
+        assert_eq!(cache.order, expected.order);
+        assert_eq!(cache.journal.len(), expected.journal.len());
+
+        // undoing it again, as a repeated undo event would, changes nothing
+        cache.undo_txs(&point(2, 2), [tx("b", &[utxo("a", 0)], 1)]);
+        assert_eq!(cache.outputs, expected.outputs);
+        assert_eq!(cache.order, expected.order);
+    }
+}
@@ -3,6 +3,7 @@ use serde::Deserialize;
 
 use crate::framework::*;
 
+pub mod fingerprint;
 pub mod into_json;
 pub mod legacy_v1;
 pub mod noop;
@@ -12,6 +13,12 @@ pub mod select;
 pub mod split_block;
 pub mod work_stats;
 
+#[cfg(feature = "template")]
+pub mod template;
+
+#[cfg(feature = "script")]
+pub mod script;
+
 #[cfg(feature = "wasm")]
 pub mod wasm_plugin;
 
@@ -20,12 +27,19 @@ pub enum Bootstrapper {
     Noop(noop::Stage),
     SplitBlock(split_block::Stage),
     IntoJson(into_json::Stage),
+    Fingerprint(fingerprint::Stage),
     LegacyV1(legacy_v1::Stage),
     ParseCbor(parse_cbor::Stage),
     Select(select::Stage),
     RollbackBuffer(rollback_buffer::Stage),
     WorkStats(work_stats::Stage),
 
+    #[cfg(feature = "template")]
+    Template(template::Stage),
+
+    #[cfg(feature = "script")]
+    Script(script::Stage),
+
     #[cfg(feature = "wasm")]
     WasmPlugin(wasm_plugin::Stage),
 }
@@ -36,12 +50,19 @@ impl Bootstrapper {
             Bootstrapper::Noop(p) => &mut p.input,
             Bootstrapper::SplitBlock(p) => &mut p.input,
             Bootstrapper::IntoJson(p) => &mut p.input,
+            Bootstrapper::Fingerprint(p) => &mut p.input,
             Bootstrapper::LegacyV1(p) => &mut p.input,
             Bootstrapper::ParseCbor(p) => &mut p.input,
             Bootstrapper::Select(p) => &mut p.input,
             Bootstrapper::RollbackBuffer(p) => &mut p.input,
             Bootstrapper::WorkStats(p) => &mut p.input,
 
+            #[cfg(feature = "template")]
+            Bootstrapper::Template(p) => &mut p.input,
+
+            #[cfg(feature = "script")]
+            Bootstrapper::Script(p) => &mut p.input,
+
             #[cfg(feature = "wasm")]
             Bootstrapper::WasmPlugin(p) => &mut p.input,
         }
@@ -52,12 +73,19 @@ impl Bootstrapper {
             Bootstrapper::Noop(p) => &mut p.output,
             Bootstrapper::SplitBlock(p) => &mut p.output,
             Bootstrapper::IntoJson(p) => &mut p.output,
+            Bootstrapper::Fingerprint(p) => &mut p.output,
             Bootstrapper::LegacyV1(p) => &mut p.output,
             Bootstrapper::ParseCbor(p) => &mut p.output,
             Bootstrapper::Select(p) => &mut p.output,
             Bootstrapper::RollbackBuffer(p) => &mut p.output,
             Bootstrapper::WorkStats(p) => &mut p.output,
 
+            #[cfg(feature = "template")]
+            Bootstrapper::Template(p) => &mut p.output,
+
+            #[cfg(feature = "script")]
+            Bootstrapper::Script(p) => &mut p.output,
+
             #[cfg(feature = "wasm")]
             Bootstrapper::WasmPlugin(p) => &mut p.output,
         }
@@ -68,30 +96,45 @@ impl Bootstrapper {
             Bootstrapper::Noop(x) => gasket::runtime::spawn_stage(x, policy),
             Bootstrapper::SplitBlock(x) => gasket::runtime::spawn_stage(x, policy),
             Bootstrapper::IntoJson(x) => gasket::runtime::spawn_stage(x, policy),
+            Bootstrapper::Fingerprint(x) => gasket::runtime::spawn_stage(x, policy),
             Bootstrapper::LegacyV1(x) => gasket::runtime::spawn_stage(x, policy),
             Bootstrapper::ParseCbor(x) => gasket::runtime::spawn_stage(x, policy),
             Bootstrapper::Select(x) => gasket::runtime::spawn_stage(x, policy),
             Bootstrapper::RollbackBuffer(x) => gasket::runtime::spawn_stage(x, policy),
             Bootstrapper::WorkStats(x) => gasket::runtime::spawn_stage(x, policy),
 
+            #[cfg(feature = "template")]
+            Bootstrapper::Template(x) => gasket::runtime::spawn_stage(x, policy),
+
+            #[cfg(feature = "script")]
+            Bootstrapper::Script(x) => gasket::runtime::spawn_stage(x, policy),
+
             #[cfg(feature = "wasm")]
             Bootstrapper::WasmPlugin(x) => gasket::runtime::spawn_stage(x, policy),
         }
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Deserialize)]
 #[serde(tag = "type")]
 pub enum Config {
     Noop(noop::Config),
     SplitBlock(split_block::Config),
     IntoJson(into_json::Config),
+    Fingerprint(fingerprint::Config),
     LegacyV1(legacy_v1::Config),
     ParseCbor(parse_cbor::Config),
     Select(select::Config),
     RollbackBuffer(rollback_buffer::Config),
     WorkStats(work_stats::Config),
 
+    #[cfg(feature = "template")]
+    Template(template::Config),
+
+    #[cfg(feature = "script")]
+    Script(script::Config),
+
     #[cfg(feature = "wasm")]
     WasmPlugin(wasm_plugin::Config),
 }
@@ -102,12 +145,19 @@ impl Config {
             Config::Noop(c) => Ok(Bootstrapper::Noop(c.bootstrapper(ctx)?)),
             Config::SplitBlock(c) => Ok(Bootstrapper::SplitBlock(c.bootstrapper(ctx)?)),
             Config::IntoJson(c) => Ok(Bootstrapper::IntoJson(c.bootstrapper(ctx)?)),
+            Config::Fingerprint(c) => Ok(Bootstrapper::Fingerprint(c.bootstrapper(ctx)?)),
             Config::LegacyV1(c) => Ok(Bootstrapper::LegacyV1(c.bootstrapper(ctx)?)),
             Config::ParseCbor(c) => Ok(Bootstrapper::ParseCbor(c.bootstrapper(ctx)?)),
             Config::Select(c) => Ok(Bootstrapper::Select(c.bootstrapper(ctx)?)),
             Config::RollbackBuffer(c) => Ok(Bootstrapper::RollbackBuffer(c.bootstrapper(ctx)?)),
             Config::WorkStats(c) => Ok(Bootstrapper::WorkStats(c.bootstrapper(ctx)?)),
 
+            #[cfg(feature = "template")]
+            Config::Template(c) => Ok(Bootstrapper::Template(c.bootstrapper(ctx)?)),
+
+            #[cfg(feature = "script")]
+            Config::Script(c) => Ok(Bootstrapper::Script(c.bootstrapper(ctx)?)),
+
             #[cfg(feature = "wasm")]
             Config::WasmPlugin(c) => Ok(Bootstrapper::WasmPlugin(c.bootstrapper(ctx)?)),
         }
@@ -9,7 +9,7 @@ use pallas::ledger::traverse as trv;
 use crate::framework::*;
 
 #[derive(Clone, Default)]
-struct NoOpContext;
+pub(crate) struct NoOpContext;
 
 impl interop::LedgerContext for NoOpContext {
     fn get_utxos(&self, _refs: &[interop::TxoRef]) -> Option<interop::UtxoMap> {
@@ -22,7 +22,7 @@ pub struct Stage {
 #[derive(Default)]
 pub struct Worker {
     buffer: chainsync::RollbackBuffer,
-    events: HashMap<Point, Record>,
+    events: HashMap<Point, ChainEvent>,
 }
 
 #[async_trait::async_trait(?Send)]
@@ -41,22 +41,18 @@ impl gasket::framework::Worker<Stage> for Worker {
 
     async fn execute(&mut self, unit: &ChainEvent, stage: &mut Stage) -> Result<(), WorkerError> {
         match unit {
-            ChainEvent::Apply(point, record) => {
-                self.events.insert(point.clone(), record.clone());
+            ChainEvent::Apply(point, ..) => {
+                self.events.insert(point.clone(), unit.clone());
                 self.buffer.roll_forward(point.clone());
 
                 let ready = self.buffer.pop_with_depth(stage.min_depth);
                 for point in ready {
-                    if let Some(record) = self.events.remove(&point) {
-                        stage
-                            .output
-                            .send(ChainEvent::apply(point, record))
-                            .await
-                            .or_panic()?;
+                    if let Some(evt) = self.events.remove(&point) {
+                        stage.output.send(evt.into()).await.or_panic()?;
                     }
                 }
             }
-            ChainEvent::Undo(point, record) => match self.buffer.roll_back(point) {
+            ChainEvent::Undo(point, ..) => match self.buffer.roll_back(point) {
                 chainsync::RollbackEffect::Handled => {
                     info!(?point, "handled rollback within buffer");
                     self.events
@@ -65,11 +61,7 @@ impl gasket::framework::Worker<Stage> for Worker {
                 chainsync::RollbackEffect::OutOfScope => {
                     info!("rollback out of buffer scope, sending event down the pipeline");
                     self.events.clear();
-                    stage
-                        .output
-                        .send(ChainEvent::undo(point.clone(), record.clone()))
-                        .await
-                        .or_panic()?;
+                    stage.output.send(unit.clone().into()).await.or_panic()?;
                 }
             },
             ChainEvent::Reset(point) => {
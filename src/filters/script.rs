@@ -0,0 +1,267 @@
+//! A filter that maps records by evaluating an embedded Rhai script
+
+use gasket::framework::*;
+use pallas::network::miniprotocols::Point;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::framework::*;
+
+fn json_to_dynamic(value: JsonValue) -> Dynamic {
+    match value {
+        JsonValue::Null => Dynamic::UNIT,
+        JsonValue::Bool(x) => x.into(),
+        JsonValue::Number(x) => match (x.as_i64(), x.as_u64()) {
+            (Some(x), _) => x.into(),
+            // integers beyond i64 would lose precision as floats, keep them as text
+            (None, Some(_)) => x.to_string().into(),
+            _ => x.as_f64().map(Dynamic::from).unwrap_or(Dynamic::UNIT),
+        },
+        JsonValue::String(x) => x.into(),
+        JsonValue::Array(x) => Dynamic::from_array(x.into_iter().map(json_to_dynamic).collect()),
+        JsonValue::Object(x) => Dynamic::from_map(
+            x.into_iter()
+                .map(|(k, v)| (k.into(), json_to_dynamic(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn event_to_dynamic(kind: &str, point: &Point, record: &Record) -> Dynamic {
+    let mut map = Map::new();
+
+    map.insert("kind".into(), kind.into());
+
+    let point = match point {
+        Point::Origin => Dynamic::UNIT,
+        Point::Specific(slot, hash) => {
+            let mut point = Map::new();
+            point.insert("slot".into(), (*slot as i64).into());
+            point.insert("hash".into(), hex::encode(hash).into());
+            Dynamic::from_map(point)
+        }
+    };
+
+    map.insert("point".into(), point);
+    map.insert("record".into(), json_to_dynamic(record.clone().into()));
+
+    Dynamic::from_map(map)
+}
+
+fn dynamic_to_records(value: Dynamic) -> Result<Vec<Record>, Error> {
+    let items = match value {
+        x if x.is_unit() => vec![],
+        x if x.is_array() => x.cast::<Array>(),
+        x => vec![x],
+    };
+
+    items
+        .into_iter()
+        .filter(|x| !x.is_unit())
+        .map(|x| {
+            rhai::serde::from_dynamic::<JsonValue>(&x)
+                .map(Record::GenericJson)
+                .map_err(Error::custom)
+        })
+        .collect()
+}
+
+#[derive(Stage)]
+#[stage(name = "filter-script", unit = "ChainEvent", worker = "Worker")]
+pub struct Stage {
+    engine: Engine,
+    ast: AST,
+
+    /// holds the `state` variable, which persists between calls
+    scope: Scope<'static>,
+
+    pub input: FilterInputPort,
+    pub output: FilterOutputPort,
+
+    #[metric]
+    ops_count: gasket::metrics::Counter,
+}
+
+impl Stage {
+    fn map_record(
+        &mut self,
+        kind: &str,
+        point: &Point,
+        record: &Record,
+    ) -> Result<Vec<Record>, Error> {
+        let checkpoint = self.scope.len();
+
+        self.scope
+            .push_dynamic("event", event_to_dynamic(kind, point, record));
+
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut self.scope, &self.ast);
+
+        // drop the event and any variable declared by the script, keep the state
+        self.scope.rewind(checkpoint);
+
+        dynamic_to_records(result.map_err(Error::custom)?)
+    }
+}
+
+#[derive(Default)]
+pub struct Worker;
+
+impl From<&Stage> for Worker {
+    fn from(_: &Stage) -> Self {
+        Self
+    }
+}
+
+gasket::impl_splitter!(|_worker: Worker, stage: Stage, unit: ChainEvent| => {
+    let output = match unit {
+        ChainEvent::Apply(p, r, _) => stage
+            .map_record("apply", p, r)
+            .or_panic()?
+            .into_iter()
+            .map(|x| ChainEvent::Apply(p.clone(), x, None))
+            .collect(),
+        ChainEvent::Undo(p, r, _) => stage
+            .map_record("undo", p, r)
+            .or_panic()?
+            .into_iter()
+            .map(|x| ChainEvent::Undo(p.clone(), x, None))
+            .collect(),
+        ChainEvent::Reset(p) => vec![ChainEvent::Reset(p.clone())],
+    };
+
+    stage.ops_count.inc(1);
+
+    output
+});
+
+#[derive(Deserialize, Default)]
+pub struct Config {
+    /// Path to a Rhai script file
+    pub path: Option<String>,
+
+    /// Inline Rhai script, as an alternative to `path`
+    pub source: Option<String>,
+
+    /// Upper bound on the operations a single evaluation can run
+    pub max_operations: Option<u64>,
+}
+
+impl Config {
+    pub fn bootstrapper(self, ctx: &Context) -> Result<Stage, Error> {
+        let mut engine = Engine::new();
+
+        if let Some(max) = self.max_operations {
+            engine.set_max_operations(max);
+        }
+
+        let ast = match (self.path, self.source) {
+            (Some(path), None) => engine
+                .compile_file(ctx.current_dir.join(path))
+                .map_err(Error::config)?,
+            (None, Some(source)) => engine.compile(source).map_err(Error::config)?,
+            _ => {
+                return Err(Error::config(
+                    "script filter requires either `path` or `source`",
+                ))
+            }
+        };
+
+        let mut scope = Scope::new();
+        scope.push("state", Map::new());
+
+        let stage = Stage {
+            engine,
+            ast,
+            scope,
+            ops_count: Default::default(),
+            input: Default::default(),
+            output: Default::default(),
+        };
+
+        Ok(stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn stage(source: &str) -> Stage {
+        let ctx = Context {
+            chain: ChainConfig::Mainnet,
+            intersect: IntersectConfig::Tip,
+            finalize: None,
+            current_dir: Default::default(),
+            breadcrumbs: Breadcrumbs::new(0),
+        };
+
+        Config {
+            source: Some(source.to_string()),
+            ..Default::default()
+        }
+        .bootstrapper(&ctx)
+        .unwrap()
+    }
+
+    fn map(stage: &mut Stage, kind: &str, record: JsonValue) -> Vec<JsonValue> {
+        let point = Point::Specific(100, vec![0xab, 0xcd]);
+
+        stage
+            .map_record(kind, &point, &Record::GenericJson(record))
+            .unwrap()
+            .into_iter()
+            .map(JsonValue::from)
+            .collect()
+    }
+
+    #[test]
+    fn maps_event_envelope() {
+        let mut stage = stage(
+            r#"
+            #{ kind: event.kind, slot: event.point.slot, fee: event.record.fee }
+            "#,
+        );
+
+        let output = map(&mut stage, "undo", json!({ "fee": 170000 }));
+
+        assert_eq!(
+            output,
+            vec![json!({ "kind": "undo", "slot": 100, "fee": 170000 })]
+        );
+    }
+
+    #[test]
+    fn emits_zero_or_many_records() {
+        let mut stage = stage(
+            r#"
+            if event.record.items == () { return; }
+            event.record.items.map(|x| #{ item: x })
+            "#,
+        );
+
+        assert!(map(&mut stage, "apply", json!({})).is_empty());
+
+        let output = map(&mut stage, "apply", json!({ "items": ["a", "b"] }));
+        assert_eq!(output, vec![json!({ "item": "a" }), json!({ "item": "b" })]);
+    }
+
+    #[test]
+    fn state_persists_between_calls() {
+        let mut stage = stage(
+            r#"
+            let step = 1;
+            state.count = if state.count == () { step } else { state.count + step };
+            state.count
+            "#,
+        );
+
+        assert_eq!(map(&mut stage, "apply", json!({})), vec![json!(1)]);
+        assert_eq!(map(&mut stage, "apply", json!({})), vec![json!(2)]);
+        assert_eq!(map(&mut stage, "apply", json!({})), vec![json!(3)]);
+    }
+}
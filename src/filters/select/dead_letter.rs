@@ -0,0 +1,132 @@
+//! Output for the events that the predicate can't decide on, so that they can
+//! be audited without stopping the pipeline.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use gasket::framework::*;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::framework::*;
+use crate::sinks::common::web::APP_USER_AGENT;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DeadLetterConfig {
+    /// appends each event as a line of JSON
+    File { path: PathBuf },
+
+    /// posts each event as JSON to the url
+    Webhook {
+        url: String,
+
+        /// timeout in milliseconds of the requests
+        timeout: Option<u64>,
+    },
+}
+
+impl DeadLetterConfig {
+    /// Resolves a relative file path against the directory of the daemon
+    pub fn resolve_path(&mut self, current_dir: &Path) {
+        if let Self::File { path } = self {
+            *path = current_dir.join(&*path);
+        }
+    }
+}
+
+pub enum DeadLetter {
+    File(File),
+    Webhook(reqwest::Client, String),
+}
+
+impl DeadLetter {
+    pub fn open(config: &DeadLetterConfig) -> Result<Self, WorkerError> {
+        match config {
+            DeadLetterConfig::File { path } => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .or_panic()?;
+
+                Ok(Self::File(file))
+            }
+            DeadLetterConfig::Webhook { url, timeout } => {
+                let client = reqwest::ClientBuilder::new()
+                    .user_agent(APP_USER_AGENT)
+                    .timeout(Duration::from_millis(timeout.unwrap_or(30000)))
+                    .build()
+                    .or_panic()?;
+
+                Ok(Self::Webhook(client, url.clone()))
+            }
+        }
+    }
+
+    pub async fn send(&mut self, event: &ChainEvent, reason: &str) -> Result<(), WorkerError> {
+        let entry = entry(event, reason);
+
+        match self {
+            Self::File(file) => {
+                let line = format!("{entry}\n");
+                file.write_all(line.as_bytes()).or_retry()?;
+            }
+            Self::Webhook(client, url) => {
+                client
+                    .post(url.as_str())
+                    .json(&entry)
+                    .send()
+                    .await
+                    .and_then(|x| x.error_for_status())
+                    .or_retry()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The JSON of the event, with the reason it was sent to the dead letter
+fn entry(event: &ChainEvent, reason: &str) -> JsonValue {
+    let mut entry = JsonValue::from(event.clone());
+
+    if let Some(x) = entry.as_object_mut() {
+        x.insert("reason".into(), json!(reason));
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_file_path() {
+        let mut config = DeadLetterConfig::File {
+            path: "uncertain.jsonl".into(),
+        };
+
+        config.resolve_path(Path::new("/etc/oura"));
+
+        assert!(matches!(
+            config,
+            DeadLetterConfig::File { path } if path == Path::new("/etc/oura/uncertain.jsonl")
+        ));
+
+        // absolute paths are kept as they are
+        let mut config = DeadLetterConfig::File {
+            path: "/var/log/uncertain.jsonl".into(),
+        };
+
+        config.resolve_path(Path::new("/etc/oura"));
+
+        assert!(matches!(
+            config,
+            DeadLetterConfig::File { path } if path == Path::new("/var/log/uncertain.jsonl")
+        ));
+    }
+}
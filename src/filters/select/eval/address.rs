@@ -22,6 +22,10 @@ pub struct AddressPattern {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub delegation_is_script: Option<bool>,
+
+    /// hash of a credential in either the payment or the delegation part
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<FlexBytes>,
 }
 
 impl PatternOf<&ByronAddress> for AddressPattern {
@@ -36,7 +40,9 @@ impl PatternOf<&ByronAddress> for AddressPattern {
 
         let e = self.delegation_is_script.is_match(false);
 
-        MatchOutcome::fold_all_of([a, b, c, d, e].into_iter())
+        let f = MatchOutcome::if_false(self.credential.is_some());
+
+        MatchOutcome::fold_all_of([a, b, c, d, e, f].into_iter())
     }
 }
 
@@ -58,7 +64,17 @@ impl PatternOf<&ShelleyAddress> for AddressPattern {
             .delegation_is_script
             .is_match(subject.delegation().is_script());
 
-        MatchOutcome::fold_all_of([a, b, c, d, e].into_iter())
+        let f = match &self.credential {
+            Some(x) => {
+                let payment = x.is_match(subject.payment().as_hash().as_ref());
+                let delegation =
+                    x.is_some_match(subject.delegation().as_hash().map(|x| x.as_ref()));
+                payment + delegation
+            }
+            None => MatchOutcome::Positive,
+        };
+
+        MatchOutcome::fold_all_of([a, b, c, d, e, f].into_iter())
     }
 }
 
@@ -68,13 +84,17 @@ impl PatternOf<&StakeAddress> for AddressPattern {
 
         let b = MatchOutcome::if_false(self.payment_part.is_some());
 
-        let c = self.delegation_part.is_match(&subject.to_vec());
+        let hash = subject.payload().as_hash();
+
+        let c = self.delegation_part.is_match(hash.as_ref());
 
         let d = MatchOutcome::if_false(self.payment_is_script.is_some());
 
         let e = self.delegation_is_script.is_match(subject.is_script());
 
-        MatchOutcome::fold_all_of([a, b, c, d, e].into_iter())
+        let f = self.credential.is_match(hash.as_ref());
+
+        MatchOutcome::fold_all_of([a, b, c, d, e, f].into_iter())
     }
 }
 
@@ -103,8 +123,9 @@ impl From<Address> for AddressPattern {
                 byron_address: Some(x.to_vec().into()),
                 ..Default::default()
             },
+            // the stake credential, same as in the delegation part of shelley addresses
             Address::Stake(x) => Self {
-                delegation_part: Some(x.to_vec().into()),
+                delegation_part: Some(x.payload().as_hash().to_vec().into()),
                 ..Default::default()
             },
             Address::Shelley(x) => Self {
@@ -123,8 +144,29 @@ impl From<Address> for AddressPattern {
 impl FromBech32 for AddressPattern {
     fn from_bech32_parts(hrp: &str, content: Vec<u8>) -> Option<Self> {
         match hrp {
-            "addr" | "addr_test" | "stake" => Address::from_bytes(&content).ok().map(From::from),
-            // TODO: add vk prefixes
+            "addr" | "addr_test" | "stake" | "stake_test" => {
+                Address::from_bytes(&content).ok().map(From::from)
+            }
+            "addr_vk" => Some(Self {
+                payment_part: Some(Hasher::<224>::hash(&content).to_vec().into()),
+                ..Default::default()
+            }),
+            "addr_vkh" => Some(Self {
+                payment_part: Some(content.into()),
+                ..Default::default()
+            }),
+            "stake_vk" => Some(Self {
+                delegation_part: Some(Hasher::<224>::hash(&content).to_vec().into()),
+                ..Default::default()
+            }),
+            "stake_vkh" => Some(Self {
+                delegation_part: Some(content.into()),
+                ..Default::default()
+            }),
+            "script" => Some(Self {
+                credential: Some(content.into()),
+                ..Default::default()
+            }),
             _ => None,
         }
     }
@@ -202,4 +244,37 @@ mod tests {
         ));
         assert_eq!(possitives, vec![1, 2, 3]);
     }
+
+    #[test]
+    fn stake_credential_match() {
+        let pattern = |x: &str| Pattern::from(AddressPattern::from_str(x).unwrap());
+
+        // the stake address of the base address in vectors 1 and 3
+        let stake = bech32::encode::<bech32::Bech32>(
+            bech32::Hrp::parse("stake").unwrap(),
+            &hex::decode("e1337b62cfff6403a06a3acbc34f8c46003c69fe79a3628cefa9c47251").unwrap(),
+        )
+        .unwrap();
+
+        let positives = testing::find_positive_test_vectors(pattern(&stake));
+        assert_eq!(positives, vec![1, 3]);
+
+        let stake_vkh = bech32::encode::<bech32::Bech32>(
+            bech32::Hrp::parse("stake_vkh").unwrap(),
+            &hex::decode("337b62cfff6403a06a3acbc34f8c46003c69fe79a3628cefa9c47251").unwrap(),
+        )
+        .unwrap();
+
+        let positives = testing::find_positive_test_vectors(pattern(&stake_vkh));
+        assert_eq!(positives, vec![1, 3]);
+
+        let addr_vkh = bech32::encode::<bech32::Bech32>(
+            bech32::Hrp::parse("addr_vkh").unwrap(),
+            &hex::decode("9493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e").unwrap(),
+        )
+        .unwrap();
+
+        let positives = testing::find_positive_test_vectors(pattern(&addr_vkh));
+        assert_eq!(positives, vec![1, 2, 3]);
+    }
 }
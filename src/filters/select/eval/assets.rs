@@ -1,7 +1,67 @@
+use std::fmt;
+
+use pallas::interop::utxorpc::spec::cardano::Tx;
+use serde::de::Visitor;
+use serde::Deserializer;
+
 use self::serde_ext::FromBech32;
 
 use super::*;
 
+/// An exact asset quantity, signed so that burns are negative. Configs can
+/// write it as a string when it doesn't fit in their integers.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct Quantity(pub i128);
+
+struct QuantityVisitor;
+
+impl Visitor<'_> for QuantityVisitor {
+    type Value = Quantity;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an integer or a string holding one")
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Quantity(v.into()))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Quantity(v.into()))
+    }
+
+    fn visit_i128<E: serde::de::Error>(self, v: i128) -> Result<Self::Value, E> {
+        Ok(Quantity(v))
+    }
+
+    fn visit_u128<E: serde::de::Error>(self, v: u128) -> Result<Self::Value, E> {
+        i128::try_from(v).map(Quantity).map_err(E::custom)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.trim().parse().map(Quantity).map_err(E::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(QuantityVisitor)
+    }
+}
+
+pub type QuantityPattern = NumericPattern<Quantity>;
+
+impl PatternOf<Option<i128>> for QuantityPattern {
+    fn is_match(&self, subject: Option<i128>) -> MatchOutcome {
+        match subject {
+            Some(x) => self.is_match(Quantity(x)),
+            // too big to compare
+            None => MatchOutcome::Uncertain,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct AssetPattern {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -18,6 +78,14 @@ pub struct AssetPattern {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub coin: Option<CoinPattern>,
+
+    /// the exact quantity, negative when minting burns it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<QuantityPattern>,
+
+    /// the quantity burned by a mint, as a positive amount
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burned: Option<QuantityPattern>,
 }
 
 impl FromBech32 for AssetPattern {
@@ -61,7 +129,18 @@ impl PatternOf<(&PolicyId, &Asset)> for AssetPattern {
 
         let e = self.coin.is_match(asset_quantity_to_u64(subject_asset));
 
-        MatchOutcome::fold_all_of([a, b, c, d, e].into_iter())
+        let quantity = asset_quantity_to_i128(subject_asset);
+
+        let f = self.quantity.is_match(quantity);
+
+        let g = match (&self.burned, quantity) {
+            (None, _) => MatchOutcome::Positive,
+            (Some(x), Some(q)) if q < 0 => x.is_match(Quantity(-q)),
+            (Some(_), Some(_)) => MatchOutcome::Negative,
+            (Some(_), None) => MatchOutcome::Uncertain,
+        };
+
+        MatchOutcome::fold_all_of([a, b, c, d, e, f, g].into_iter())
     }
 }
 
@@ -75,6 +154,49 @@ impl PatternOf<&Multiasset> for AssetPattern {
     }
 }
 
+/// Matches the total value sent to the outputs of a tx: lovelace, all the
+/// assets of a policy, or a single asset when `name` is also set
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ValuePattern {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<FlexBytes>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<FlexBytes>,
+
+    pub total: QuantityPattern,
+}
+
+impl ValuePattern {
+    /// Sums the quantities of the outputs, `None` when one of them or the
+    /// total doesn't fit in an `i128`
+    fn total(&self, tx: &Tx) -> Option<i128> {
+        let Some(policy) = &self.policy else {
+            return tx.outputs.iter().try_fold(0i128, |acc, x| {
+                let coin = x
+                    .coin
+                    .as_ref()
+                    .map_or(Some(0), |x| big_int_to_i128(Some(x)))?;
+                acc.checked_add(coin)
+            });
+        };
+
+        tx.outputs
+            .iter()
+            .flat_map(|x| x.assets.iter())
+            .filter(|x| *x.policy_id == **policy)
+            .flat_map(|x| x.assets.iter())
+            .filter(|x| self.name.is_match(x.name.as_ref()) == MatchOutcome::Positive)
+            .try_fold(0i128, |acc, x| acc.checked_add(asset_quantity_to_i128(x)?))
+    }
+}
+
+impl PatternOf<&Tx> for ValuePattern {
+    fn is_match(&self, subject: &Tx) -> MatchOutcome {
+        self.total.is_match(self.total(subject))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,4 +259,93 @@ mod tests {
         ));
         assert_eq!(positives, Vec::<usize>::new());
     }
+
+    fn mint(quantity: big_int::BigInt) -> Multiasset {
+        Multiasset {
+            policy_id: hex::decode("533bb94a8850ee3ccbe483106489399112b74c905342cb1792a797a0")
+                .unwrap()
+                .into(),
+            assets: vec![Asset {
+                name: b"xyz1".to_vec().into(),
+                quantity: Some(asset::Quantity::MintCoin(BigInt {
+                    big_int: Some(quantity),
+                })),
+            }],
+            redeemer: None,
+        }
+    }
+
+    #[test]
+    fn big_quantities() {
+        let pattern: AssetPattern = serde_json::from_str(
+            r#"{ "quantity": { "gte": "18446744073709551616" }, "burned": { "gte": 1 } }"#,
+        )
+        .unwrap();
+
+        // 2^64 + 1 burned, beyond what a u64 holds
+        let subject = mint(big_int::BigInt::BigNInt(
+            [1u8, 0, 0, 0, 0, 0, 0, 0, 0].to_vec().into(),
+        ));
+        let pattern = AssetPattern {
+            quantity: Some(QuantityPattern::Lte(Quantity(-(1 << 64)))),
+            ..pattern
+        };
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Positive);
+
+        let pattern = AssetPattern {
+            burned: Some(QuantityPattern::Gte(Quantity((1 << 64) + 2))),
+            ..pattern
+        };
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Negative);
+
+        // a mint isn't a burn
+        let subject = mint(big_int::BigInt::Int(10));
+        let pattern = AssetPattern {
+            burned: Some(QuantityPattern::Gte(Quantity(0))),
+            ..Default::default()
+        };
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Negative);
+
+        // too big for an i128
+        let subject = mint(big_int::BigInt::BigUInt(vec![1u8; 17].into()));
+        let pattern = AssetPattern {
+            quantity: Some(QuantityPattern::Gte(Quantity(0))),
+            ..Default::default()
+        };
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Uncertain);
+    }
+
+    #[test]
+    fn value_match() {
+        let pattern = |policy: Option<&str>, name: Option<&str>, total| {
+            Pattern::Value(ValuePattern {
+                policy: policy.map(|x| FlexBytes::from_hex(x).unwrap()),
+                name: name.map(|x| x.into()),
+                total,
+            })
+        };
+
+        let policy = "7eae28af2208be856f7a119668ae52a49b73725e326dc16579dcc373";
+
+        let positives = testing::find_positive_test_vectors(pattern(
+            Some(policy),
+            None,
+            QuantityPattern::Exact(Quantity(690000000)),
+        ));
+        assert_eq!(positives, vec![1, 2]);
+
+        let positives = testing::find_positive_test_vectors(pattern(
+            Some(policy),
+            Some("abc1"),
+            QuantityPattern::Exact(Quantity(345000000)),
+        ));
+        assert_eq!(positives, vec![1, 2]);
+
+        let positives = testing::find_positive_test_vectors(pattern(
+            None,
+            None,
+            QuantityPattern::Gte(Quantity(1)),
+        ));
+        assert_eq!(positives, vec![1, 2, 3]);
+    }
 }
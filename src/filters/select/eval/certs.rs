@@ -0,0 +1,213 @@
+use pallas::interop::utxorpc::spec::cardano::{
+    certificate, stake_credential, Certificate, StakeCredential, Withdrawal,
+};
+
+use self::serde_ext::FromBech32;
+
+use super::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WithdrawalPattern {
+    /// the reward account the rewards are withdrawn from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    address: Option<StringOrStruct<AddressPattern>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lovelace: Option<CoinPattern>,
+}
+
+impl PatternOf<&Withdrawal> for WithdrawalPattern {
+    fn is_match(&self, subject: &Withdrawal) -> MatchOutcome {
+        let a = self.address.is_match(subject.reward_account.as_ref());
+
+        let b = self
+            .lovelace
+            .is_match(big_int_to_u64(subject.coin.as_ref()));
+
+        MatchOutcome::fold_all_of([a, b].into_iter())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CertificateKind {
+    StakeRegistration,
+    StakeDeregistration,
+    StakeDelegation,
+    PoolRegistration,
+    PoolRetirement,
+    GenesisKeyDelegation,
+    Mir,
+    Reg,
+    Unreg,
+    VoteDeleg,
+    StakeVoteDeleg,
+    StakeRegDeleg,
+    VoteRegDeleg,
+    StakeVoteRegDeleg,
+    AuthCommitteeHot,
+    ResignCommitteeCold,
+    RegDrep,
+    UnregDrep,
+    UpdateDrep,
+}
+
+impl PatternOf<CertificateKind> for CertificateKind {
+    fn is_match(&self, subject: CertificateKind) -> MatchOutcome {
+        MatchOutcome::if_equal(self, &subject)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CertificatePattern {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    kind: Option<CertificateKind>,
+
+    /// hash of the stake, committee or drep credential of the certificate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    credential: Option<FlexBytes>,
+
+    /// key hash of the pool delegated to, registered or retired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pool: Option<FlexBytes>,
+}
+
+impl FromBech32 for CertificatePattern {
+    fn from_bech32_parts(hrp: &str, content: Vec<u8>) -> Option<Self> {
+        match hrp {
+            "pool" => Some(Self {
+                pool: Some(content.into()),
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub(super) fn credential_hash(credential: Option<&StakeCredential>) -> Option<&[u8]> {
+    match credential?.stake_credential.as_ref()? {
+        stake_credential::StakeCredential::AddrKeyHash(x) => Some(x),
+        stake_credential::StakeCredential::ScriptHash(x) => Some(x),
+    }
+}
+
+/// Breaks a certificate into its kind, credential and pool
+pub(super) fn describe(
+    certificate: &certificate::Certificate,
+) -> (CertificateKind, Option<&StakeCredential>, Option<&[u8]>) {
+    use certificate::Certificate as C;
+    use CertificateKind as K;
+
+    match certificate {
+        C::StakeRegistration(x) => (K::StakeRegistration, Some(x), None),
+        C::StakeDeregistration(x) => (K::StakeDeregistration, Some(x), None),
+        C::StakeDelegation(x) => (
+            K::StakeDelegation,
+            x.stake_credential.as_ref(),
+            Some(&x.pool_keyhash),
+        ),
+        C::PoolRegistration(x) => (K::PoolRegistration, None, Some(&x.operator)),
+        C::PoolRetirement(x) => (K::PoolRetirement, None, Some(&x.pool_keyhash)),
+        C::GenesisKeyDelegation(_) => (K::GenesisKeyDelegation, None, None),
+        C::MirCert(_) => (K::Mir, None, None),
+        C::RegCert(x) => (K::Reg, x.stake_credential.as_ref(), None),
+        C::UnregCert(x) => (K::Unreg, x.stake_credential.as_ref(), None),
+        C::VoteDelegCert(x) => (K::VoteDeleg, x.stake_credential.as_ref(), None),
+        C::StakeVoteDelegCert(x) => (
+            K::StakeVoteDeleg,
+            x.stake_credential.as_ref(),
+            Some(&x.pool_keyhash),
+        ),
+        C::StakeRegDelegCert(x) => (
+            K::StakeRegDeleg,
+            x.stake_credential.as_ref(),
+            Some(&x.pool_keyhash),
+        ),
+        C::VoteRegDelegCert(x) => (K::VoteRegDeleg, x.stake_credential.as_ref(), None),
+        C::StakeVoteRegDelegCert(x) => (
+            K::StakeVoteRegDeleg,
+            x.stake_credential.as_ref(),
+            Some(&x.pool_keyhash),
+        ),
+        C::AuthCommitteeHotCert(x) => (
+            K::AuthCommitteeHot,
+            x.committee_cold_credential.as_ref(),
+            None,
+        ),
+        C::ResignCommitteeColdCert(x) => (
+            K::ResignCommitteeCold,
+            x.committee_cold_credential.as_ref(),
+            None,
+        ),
+        C::RegDrepCert(x) => (K::RegDrep, x.drep_credential.as_ref(), None),
+        C::UnregDrepCert(x) => (K::UnregDrep, x.drep_credential.as_ref(), None),
+        C::UpdateDrepCert(x) => (K::UpdateDrep, x.drep_credential.as_ref(), None),
+    }
+}
+
+impl PatternOf<&Certificate> for CertificatePattern {
+    fn is_match(&self, subject: &Certificate) -> MatchOutcome {
+        let Some(certificate) = subject.certificate.as_ref() else {
+            return MatchOutcome::Uncertain;
+        };
+
+        let (kind, credential, pool) = describe(certificate);
+
+        let a = self.kind.is_match(kind);
+
+        let b = match &self.credential {
+            Some(x) => x.is_some_match(credential_hash(credential)),
+            None => MatchOutcome::Positive,
+        };
+
+        let c = match &self.pool {
+            Some(x) => x.is_some_match(pool),
+            None => MatchOutcome::Positive,
+        };
+
+        MatchOutcome::fold_all_of([a, b, c].into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::interop::utxorpc::spec::cardano::StakeDelegationCert;
+
+    use super::*;
+
+    #[test]
+    fn certificate_match() {
+        let credential = StakeCredential {
+            stake_credential: Some(stake_credential::StakeCredential::AddrKeyHash(
+                vec![1; 28].into(),
+            )),
+        };
+
+        let subject = Certificate {
+            certificate: Some(certificate::Certificate::StakeDelegation(
+                StakeDelegationCert {
+                    stake_credential: Some(credential),
+                    pool_keyhash: vec![2; 28].into(),
+                },
+            )),
+            redeemer: None,
+        };
+
+        let pattern: CertificatePattern =
+            serde_json::from_str(r#"{ "type": "stake_delegation" }"#).unwrap();
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Positive);
+
+        let pattern = CertificatePattern {
+            credential: Some(vec![1; 28].into()),
+            pool: Some(vec![2; 28].into()),
+            ..Default::default()
+        };
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Positive);
+
+        let pattern = CertificatePattern {
+            kind: Some(CertificateKind::PoolRetirement),
+            ..Default::default()
+        };
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Negative);
+    }
+}
@@ -0,0 +1,258 @@
+use pallas::interop::utxorpc::spec::cardano::{certificate, d_rep, Certificate, DRep};
+use pallas::ledger::addresses::Address;
+
+use self::serde_ext::FromBech32;
+
+use super::*;
+
+/// Matches a credential hash wherever it shows up in a tx: the payment and
+/// delegation parts of its addresses, its reward withdrawals, its certificates
+/// and its votes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CredentialPattern {
+    pub hash: FlexBytes,
+}
+
+/// Strips the header byte of a CIP-129 governance credential. The high
+/// nibble is the key type (cc hot, cc cold or drep) and the low one the
+/// credential type (key or script hash). The CIP-105 form is the bare hash.
+fn decode_cip129(content: &[u8], key_type: u8) -> Option<Vec<u8>> {
+    if content.len() == 28 {
+        return Some(content.to_vec());
+    }
+
+    let (header, hash) = content.split_first()?;
+
+    let is_valid = header >> 4 == key_type && matches!(header & 0x0f, 0x02 | 0x03);
+
+    match is_valid && hash.len() == 28 {
+        true => Some(hash.to_vec()),
+        false => None,
+    }
+}
+
+impl FromBech32 for CredentialPattern {
+    fn from_bech32_parts(hrp: &str, content: Vec<u8>) -> Option<Self> {
+        let hash = match hrp {
+            "cc_hot" => decode_cip129(&content, 0x0)?,
+            "cc_cold" => decode_cip129(&content, 0x1)?,
+            "drep" => decode_cip129(&content, 0x2)?,
+            // CIP-105 has its own prefixes for script credentials
+            "drep_script" | "cc_hot_script" | "cc_cold_script" if content.len() == 28 => content,
+            "addr_vkh" | "stake_vkh" | "script" if content.len() == 28 => content,
+            _ => return None,
+        };
+
+        Some(Self { hash: hash.into() })
+    }
+}
+
+impl FromStr for CredentialPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bech32(s)
+    }
+}
+
+fn address_credentials(address: &[u8]) -> Vec<Vec<u8>> {
+    match Address::from_bytes(address) {
+        Ok(Address::Shelley(x)) => [Some(x.payment().as_hash()), x.delegation().as_hash()]
+            .into_iter()
+            .flatten()
+            .map(|x| x.to_vec())
+            .collect(),
+        Ok(Address::Stake(x)) => vec![x.payload().as_hash().to_vec()],
+        _ => vec![],
+    }
+}
+
+fn drep_hash(drep: Option<&DRep>) -> Option<&[u8]> {
+    match drep?.drep.as_ref()? {
+        d_rep::Drep::AddrKeyHash(x) => Some(x),
+        d_rep::Drep::ScriptHash(x) => Some(x),
+        _ => None,
+    }
+}
+
+/// The credentials of a certificate, including the drep and the committee
+/// hot key it delegates or authorizes to
+fn certificate_credentials(subject: &Certificate) -> Vec<&[u8]> {
+    use certificate::Certificate as C;
+
+    let Some(certificate) = subject.certificate.as_ref() else {
+        return vec![];
+    };
+
+    let (_, credential, _) = describe(certificate);
+
+    let extra = match certificate {
+        C::VoteDelegCert(x) => drep_hash(x.drep.as_ref()),
+        C::StakeVoteDelegCert(x) => drep_hash(x.drep.as_ref()),
+        C::VoteRegDelegCert(x) => drep_hash(x.drep.as_ref()),
+        C::StakeVoteRegDelegCert(x) => drep_hash(x.drep.as_ref()),
+        C::AuthCommitteeHotCert(x) => credential_hash(x.committee_hot_credential.as_ref()),
+        _ => None,
+    };
+
+    [credential_hash(credential), extra]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+impl PatternOf<TxSubject<'_>> for CredentialPattern {
+    fn is_match(&self, subject: TxSubject) -> MatchOutcome {
+        let tx = subject.parsed;
+
+        let addresses = iter_tx_addresses(tx)
+            .chain(tx.withdrawals.iter().map(|x| x.reward_account.as_ref()))
+            .flat_map(address_credentials);
+
+        let a = addresses.into_iter().any(|x| x == *self.hash);
+
+        let b = tx
+            .certificates
+            .iter()
+            .flat_map(certificate_credentials)
+            .any(|x| *x == *self.hash);
+
+        if a || b {
+            return MatchOutcome::Positive;
+        }
+
+        // votes are only available in the cbor, but the credential can only
+        // vote in a tx that it witnesses
+        let Some(raw) = subject.raw else {
+            return match may_have_voted(tx, Some(&self.hash)) {
+                true => MatchOutcome::Uncertain,
+                false => MatchOutcome::Negative,
+            };
+        };
+
+        let voters = raw
+            .as_conway()
+            .and_then(|x| x.transaction_body.voting_procedures.as_ref());
+
+        let c = voters
+            .iter()
+            .flat_map(|x| x.iter())
+            .any(|(voter, _)| *describe_voter(voter).1 == *self.hash);
+
+        MatchOutcome::if_true(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cip129() {
+        let hash = vec![7u8; 28];
+
+        let mut content = vec![0x22];
+        content.extend(&hash);
+
+        let drep = bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("drep").unwrap(), &content)
+            .unwrap();
+
+        let pattern = CredentialPattern::from_str(&drep).unwrap();
+        assert_eq!(pattern.hash, hash.clone().into());
+
+        // a drep header isn't valid for a cc hot credential
+        let cc_hot =
+            bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("cc_hot").unwrap(), &content)
+                .unwrap();
+
+        assert!(CredentialPattern::from_str(&cc_hot).is_err());
+    }
+
+    #[test]
+    fn parse_cip105() {
+        let hash = vec![7u8; 28];
+
+        for hrp in ["drep", "drep_script", "cc_hot", "cc_cold", "cc_hot_script"] {
+            let bech32 =
+                bech32::encode::<bech32::Bech32>(bech32::Hrp::parse(hrp).unwrap(), &hash).unwrap();
+
+            let pattern = CredentialPattern::from_str(&bech32).unwrap();
+            assert_eq!(pattern.hash, hash.clone().into());
+
+            // as a shorthand predicate too
+            let pattern = Pattern::from_str(&bech32).unwrap();
+            assert!(matches!(pattern, Pattern::Credential(_)));
+        }
+
+        // neither a hash nor a cip-129 credential
+        let short = bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("drep").unwrap(), &[7; 27])
+            .unwrap();
+
+        assert!(CredentialPattern::from_str(&short).is_err());
+    }
+
+    #[test]
+    fn credential_match() {
+        // the stake credential of the base address in vectors 1 and 3
+        let pattern = CredentialPattern {
+            hash: FlexBytes::from_hex("337b62cfff6403a06a3acbc34f8c46003c69fe79a3628cefa9c47251")
+                .unwrap(),
+        };
+
+        let outcomes: Vec<_> = testing::test_vectors()
+            .iter()
+            .map(|x| pattern.is_match(TxSubject::from(x)))
+            .collect();
+
+        // the rest aren't witnessed by the credential, so it can't have voted
+        assert_eq!(
+            outcomes,
+            vec![
+                MatchOutcome::Negative,
+                MatchOutcome::Positive,
+                MatchOutcome::Negative,
+                MatchOutcome::Positive,
+            ]
+        );
+    }
+
+    #[test]
+    fn payment_tx_is_negative() {
+        use pallas::interop::utxorpc::spec::cardano::{VKeyWitness, WitnessSet};
+
+        let vkey = vec![1u8; 32];
+
+        let pattern = CredentialPattern {
+            hash: Hasher::<224>::hash(&vkey).to_vec().into(),
+        };
+
+        // a payment signed by some other key
+        let mut tx = testing::test_vectors()[0].clone();
+        tx.witnesses = Some(WitnessSet {
+            vkeywitness: vec![VKeyWitness {
+                vkey: vec![2u8; 32].into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Negative
+        );
+
+        // signed by the credential, which could be voting
+        tx.witnesses = Some(WitnessSet {
+            vkeywitness: vec![VKeyWitness {
+                vkey: vkey.into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Uncertain
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use pallas::interop::utxorpc::spec::cardano::{
+    governance_action, GovernanceActionProposal, Script,
+};
+use pallas::ledger::primitives::conway;
+
+use super::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GovActionKind {
+    ParameterChange,
+    HardForkInitiation,
+    TreasuryWithdrawals,
+    NoConfidence,
+    UpdateCommittee,
+    NewConstitution,
+    Info,
+}
+
+impl PatternOf<GovActionKind> for GovActionKind {
+    fn is_match(&self, subject: GovActionKind) -> MatchOutcome {
+        MatchOutcome::if_equal(self, &subject)
+    }
+}
+
+impl From<&governance_action::GovernanceAction> for GovActionKind {
+    fn from(value: &governance_action::GovernanceAction) -> Self {
+        use governance_action::GovernanceAction as A;
+
+        match value {
+            A::ParameterChangeAction(_) => Self::ParameterChange,
+            A::HardForkInitiationAction(_) => Self::HardForkInitiation,
+            A::TreasuryWithdrawalsAction(_) => Self::TreasuryWithdrawals,
+            A::NoConfidenceAction(_) => Self::NoConfidence,
+            A::UpdateCommitteeAction(_) => Self::UpdateCommittee,
+            A::NewConstitutionAction(_) => Self::NewConstitution,
+            A::InfoAction(_) => Self::Info,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ProposalPattern {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    action: Option<GovActionKind>,
+
+    /// the reward account that gets the deposit back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reward_account: Option<StringOrStruct<AddressPattern>>,
+}
+
+impl PatternOf<&GovernanceActionProposal> for ProposalPattern {
+    fn is_match(&self, subject: &GovernanceActionProposal) -> MatchOutcome {
+        let action = subject
+            .gov_action
+            .as_ref()
+            .and_then(|x| x.governance_action.as_ref())
+            .map(GovActionKind::from);
+
+        let a = is_known_match(&self.action, action);
+
+        let b = self
+            .reward_account
+            .is_match(subject.reward_account.as_ref());
+
+        MatchOutcome::fold_all_of([a, b].into_iter())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoterRole {
+    Committee,
+    Drep,
+    Pool,
+}
+
+impl PatternOf<VoterRole> for VoterRole {
+    fn is_match(&self, subject: VoterRole) -> MatchOutcome {
+        MatchOutcome::if_equal(self, &subject)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteChoice {
+    Yes,
+    No,
+    Abstain,
+}
+
+impl PatternOf<VoteChoice> for VoteChoice {
+    fn is_match(&self, subject: VoteChoice) -> MatchOutcome {
+        MatchOutcome::if_equal(self, &subject)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct VotePattern {
+    /// key or script hash of the voter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    voter: Option<FlexBytes>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<VoterRole>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vote: Option<VoteChoice>,
+
+    /// hash of the tx that submitted the voted governance action
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    action_tx: Option<FlexBytes>,
+}
+
+pub(super) fn describe_voter(voter: &conway::Voter) -> (VoterRole, &[u8]) {
+    match voter {
+        conway::Voter::ConstitutionalCommitteeScript(x) => (VoterRole::Committee, x.as_ref()),
+        conway::Voter::ConstitutionalCommitteeKey(x) => (VoterRole::Committee, x.as_ref()),
+        conway::Voter::DRepScript(x) => (VoterRole::Drep, x.as_ref()),
+        conway::Voter::DRepKey(x) => (VoterRole::Drep, x.as_ref()),
+        conway::Voter::StakePoolKey(x) => (VoterRole::Pool, x.as_ref()),
+    }
+}
+
+fn describe_vote(vote: &conway::Vote) -> VoteChoice {
+    match vote {
+        conway::Vote::Yes => VoteChoice::Yes,
+        conway::Vote::No => VoteChoice::No,
+        conway::Vote::Abstain => VoteChoice::Abstain,
+    }
+}
+
+impl
+    PatternOf<(
+        &conway::Voter,
+        &conway::GovActionId,
+        &conway::VotingProcedure,
+    )> for VotePattern
+{
+    fn is_match(
+        &self,
+        subject: (
+            &conway::Voter,
+            &conway::GovActionId,
+            &conway::VotingProcedure,
+        ),
+    ) -> MatchOutcome {
+        let (voter, action, procedure) = subject;
+
+        let (role, voter) = describe_voter(voter);
+
+        let a = self.voter.is_match(voter);
+
+        let b = self.role.is_match(role);
+
+        let c = self.vote.is_match(describe_vote(&procedure.vote));
+
+        let d = self.action_tx.is_match(action.transaction_id.as_ref());
+
+        MatchOutcome::fold_all_of([a, b, c, d].into_iter())
+    }
+}
+
+/// Tells if a voter, by its key or script hash, could have voted in a u5c tx,
+/// or if anyone could have when no voter is given. The votes themselves aren't
+/// mapped, but each of them must be witnessed by its voter, either with a
+/// signature or with a script, which might be a reference one.
+pub(super) fn may_have_voted(tx: &ParsedTx, voter: Option<&[u8]>) -> bool {
+    let witnesses = tx.witnesses.as_ref();
+
+    let is_hash = |x: &[u8]| voter.is_none_or(|voter| x == voter);
+
+    let signed = witnesses
+        .iter()
+        .flat_map(|x| x.vkeywitness.iter())
+        .any(|x| is_hash(Hasher::<224>::hash(&x.vkey).as_ref()));
+
+    // the hash of native scripts isn't known
+    let is_voter = |x: &Script| u5c_script_hash(x).is_none_or(|x| is_hash(x.as_ref()));
+
+    let script = witnesses.iter().flat_map(|x| x.script.iter()).any(is_voter);
+
+    let referenced = tx.reference_inputs.iter().any(|x| match &x.as_output {
+        Some(output) => output.script.as_ref().is_some_and(is_voter),
+        None => true,
+    });
+
+    signed || script || referenced
+}
+
+impl PatternOf<TxSubject<'_>> for VotePattern {
+    fn is_match(&self, subject: TxSubject) -> MatchOutcome {
+        // u5c doesn't map the voting procedures, they're only available in the
+        // cbor. Without it, only a voter that didn't witness the tx is known
+        // not to have voted.
+        let Some(raw) = subject.raw else {
+            return match may_have_voted(subject.parsed, self.voter.as_deref()) {
+                true => MatchOutcome::Uncertain,
+                false => MatchOutcome::Negative,
+            };
+        };
+
+        // txs of eras before conway can't have votes
+        let votes = raw
+            .as_conway()
+            .and_then(|x| x.transaction_body.voting_procedures.as_ref());
+
+        let Some(votes) = votes else {
+            return MatchOutcome::Negative;
+        };
+
+        let votes = votes.iter().flat_map(|(voter, actions)| {
+            actions
+                .iter()
+                .map(move |(action, procedure)| (voter, action, procedure))
+        });
+
+        self.is_any_match(votes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::interop::utxorpc::spec::cardano::{GovernanceAction, VKeyWitness, WitnessSet};
+
+    use pallas::codec::minicbor;
+
+    use super::*;
+
+    #[test]
+    fn proposal_match() {
+        let subject = GovernanceActionProposal {
+            gov_action: Some(GovernanceAction {
+                governance_action: Some(governance_action::GovernanceAction::InfoAction(6)),
+            }),
+            ..Default::default()
+        };
+
+        let pattern: ProposalPattern = serde_json::from_str(r#"{ "action": "info" }"#).unwrap();
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Positive);
+
+        let pattern = ProposalPattern {
+            action: Some(GovActionKind::NoConfidence),
+            ..Default::default()
+        };
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Negative);
+    }
+
+    #[test]
+    fn votes_need_cbor() {
+        let tx = ParsedTx {
+            witnesses: Some(WitnessSet {
+                vkeywitness: vec![VKeyWitness::default()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let pattern = VotePattern {
+            vote: Some(VoteChoice::Yes),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Uncertain
+        );
+
+        // nobody witnessed the tx, so nobody voted in it
+        let tx = ParsedTx::default();
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Negative
+        );
+    }
+
+    /// A conway tx with no inputs nor outputs, with a yes vote of a drep when
+    /// `vote` is set
+    fn conway_tx_cbor(vote: bool) -> Vec<u8> {
+        let mut e = minicbor::Encoder::new(vec![]);
+
+        e.array(4).unwrap();
+        e.map(if vote { 4 } else { 3 }).unwrap();
+        e.u8(0).unwrap().array(0).unwrap();
+        e.u8(1).unwrap().array(0).unwrap();
+        e.u8(2).unwrap().u64(170000).unwrap();
+
+        if vote {
+            e.u8(19).unwrap().map(1).unwrap();
+            e.array(2).unwrap().u8(2).unwrap().bytes(&[1; 28]).unwrap();
+            e.map(1).unwrap();
+            e.array(2).unwrap().bytes(&[2; 32]).unwrap().u32(0).unwrap();
+            e.array(2).unwrap().u8(1).unwrap().null().unwrap();
+        }
+
+        e.map(0).unwrap();
+        e.bool(true).unwrap();
+        e.null().unwrap();
+
+        e.into_writer()
+    }
+
+    #[test]
+    fn votes_from_cbor() {
+        let mapper = Mapper::default();
+
+        let eval = |cbor: &[u8], pattern: VotePattern| {
+            let record = Record::CborTx(cbor.to_vec());
+            super::super::eval(&mapper, &record, &Predicate::from(Pattern::Vote(pattern)))
+        };
+
+        let voted = conway_tx_cbor(true);
+
+        let pattern = VotePattern {
+            voter: Some(vec![1; 28].into()),
+            role: Some(VoterRole::Drep),
+            vote: Some(VoteChoice::Yes),
+            action_tx: Some(vec![2; 32].into()),
+        };
+
+        assert_eq!(eval(&voted, pattern), MatchOutcome::Positive);
+
+        let pattern = VotePattern {
+            vote: Some(VoteChoice::No),
+            ..Default::default()
+        };
+
+        assert_eq!(eval(&voted, pattern), MatchOutcome::Negative);
+
+        let pattern = VotePattern {
+            role: Some(VoterRole::Pool),
+            ..Default::default()
+        };
+
+        assert_eq!(eval(&voted, pattern), MatchOutcome::Negative);
+
+        // without voting procedures, the answer is certain as well
+        let unvoted = conway_tx_cbor(false);
+        assert_eq!(
+            eval(&unvoted, VotePattern::default()),
+            MatchOutcome::Negative
+        );
+    }
+
+    #[test]
+    fn voter_must_witness_tx() {
+        let vkey = vec![1u8; 32];
+        let voter = Hasher::<224>::hash(&vkey);
+
+        let pattern = VotePattern {
+            voter: Some(voter.to_vec().into()),
+            ..Default::default()
+        };
+
+        let tx = ParsedTx::default();
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Negative
+        );
+
+        let tx = ParsedTx {
+            witnesses: Some(WitnessSet {
+                vkeywitness: vec![VKeyWitness {
+                    vkey: vkey.into(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Uncertain
+        );
+    }
+}
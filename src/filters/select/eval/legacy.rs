@@ -0,0 +1,262 @@
+//! Maps legacy v1 events into the u5c shape understood by the tx patterns
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use pallas::interop::utxorpc::spec::cardano::{
+    asset, big_int, metadatum, Asset, AuxData, BigInt, Datum, Metadata, Metadatum, MetadatumArray,
+    MetadatumMap, MetadatumPair, Multiasset, TxInput, TxOutput,
+};
+use pallas::ledger::addresses::Address;
+use serde_json::Value as JsonValue;
+
+use super::{BlockSubject, TxSubject};
+use crate::framework::legacy_v1::*;
+use crate::framework::ParsedTx;
+
+fn hex_bytes(value: &str) -> Vec<u8> {
+    hex::decode(value).unwrap_or_default()
+}
+
+fn address_bytes(value: &str) -> Vec<u8> {
+    Address::from_str(value)
+        .map(|x| x.to_vec())
+        .unwrap_or_default()
+}
+
+fn u64_to_big_int(value: u64) -> BigInt {
+    let big_int = match i64::try_from(value) {
+        Ok(x) => big_int::BigInt::Int(x),
+        Err(_) => big_int::BigInt::BigUInt(value.to_be_bytes().to_vec().into()),
+    };
+
+    BigInt {
+        big_int: Some(big_int),
+    }
+}
+
+fn map_assets<'a>(
+    assets: impl Iterator<Item = (&'a str, &'a str, asset::Quantity)>,
+) -> Vec<Multiasset> {
+    let mut policies = BTreeMap::<&str, Vec<Asset>>::new();
+
+    for (policy, name, quantity) in assets {
+        policies.entry(policy).or_default().push(Asset {
+            name: hex_bytes(name).into(),
+            quantity: Some(quantity),
+        });
+    }
+
+    policies
+        .into_iter()
+        .map(|(policy, assets)| Multiasset {
+            policy_id: hex_bytes(policy).into(),
+            assets,
+            redeemer: None,
+        })
+        .collect()
+}
+
+fn map_output_assets(address: &str, assets: &[OutputAssetRecord]) -> TxOutput {
+    let assets = assets.iter().map(|x| {
+        let quantity = asset::Quantity::OutputCoin(u64_to_big_int(x.amount));
+        (x.policy.as_str(), x.asset.as_str(), quantity)
+    });
+
+    TxOutput {
+        address: address_bytes(address).into(),
+        assets: map_assets(assets),
+        ..Default::default()
+    }
+}
+
+fn map_output(record: &TxOutputRecord) -> TxOutput {
+    let datum = record.datum_hash.as_ref().map(|x| Datum {
+        hash: hex_bytes(x).into(),
+        ..Default::default()
+    });
+
+    TxOutput {
+        coin: Some(u64_to_big_int(record.amount)),
+        datum,
+        ..map_output_assets(
+            &record.address,
+            record.assets.as_deref().unwrap_or_default(),
+        )
+    }
+}
+
+fn map_input(record: &TxInputRecord) -> TxInput {
+    TxInput {
+        tx_hash: hex_bytes(&record.tx_id).into(),
+        output_index: record.index as u32,
+        as_output: record.spent_output.as_ref().map(map_output),
+        ..Default::default()
+    }
+}
+
+fn map_mint(records: &[MintRecord]) -> Vec<Multiasset> {
+    let assets = records.iter().map(|x| {
+        let quantity = asset::Quantity::MintCoin(BigInt {
+            big_int: Some(big_int::BigInt::Int(x.quantity)),
+        });
+
+        (x.policy.as_str(), x.asset.as_str(), quantity)
+    });
+
+    map_assets(assets)
+}
+
+/// Rebuilds a metadatum from its JSON rendition. The JSON form is lossy: keys
+/// and bytes come back as text, which is what text patterns match against.
+fn json_to_metadatum(value: &JsonValue) -> Option<Metadatum> {
+    let metadatum = match value {
+        JsonValue::Number(x) => metadatum::Metadatum::Int(x.as_i64()?),
+        JsonValue::String(x) => metadatum::Metadatum::Text(x.clone()),
+        JsonValue::Array(x) => metadatum::Metadatum::Array(MetadatumArray {
+            items: x.iter().filter_map(json_to_metadatum).collect(),
+        }),
+        JsonValue::Object(x) => metadatum::Metadatum::Map(MetadatumMap {
+            pairs: x
+                .iter()
+                .map(|(k, v)| MetadatumPair {
+                    key: json_to_metadatum(&JsonValue::String(k.clone())),
+                    value: json_to_metadatum(v),
+                })
+                .collect(),
+        }),
+        _ => return None,
+    };
+
+    Some(Metadatum {
+        metadatum: Some(metadatum),
+    })
+}
+
+fn map_metadatum(content: &MetadatumRendition) -> Option<Metadatum> {
+    let metadatum = match content {
+        MetadatumRendition::MapJson(x) | MetadatumRendition::ArrayJson(x) => {
+            return json_to_metadatum(x)
+        }
+        MetadatumRendition::IntScalar(x) => metadatum::Metadatum::Int(i64::try_from(*x).ok()?),
+        MetadatumRendition::TextScalar(x) => metadatum::Metadatum::Text(x.clone()),
+        MetadatumRendition::BytesHex(x) => metadatum::Metadatum::Bytes(hex_bytes(x).into()),
+    };
+
+    Some(Metadatum {
+        metadatum: Some(metadatum),
+    })
+}
+
+fn map_metadata(records: &[MetadataRecord]) -> Option<AuxData> {
+    let metadata = records
+        .iter()
+        .filter_map(|x| {
+            Some(Metadata {
+                label: x.label.parse().ok()?,
+                value: map_metadatum(&x.content),
+            })
+        })
+        .collect();
+
+    Some(AuxData {
+        metadata,
+        ..Default::default()
+    })
+}
+
+fn map_transaction(record: &TransactionRecord) -> ParsedTx {
+    ParsedTx {
+        hash: hex_bytes(&record.hash).into(),
+        fee: Some(u64_to_big_int(record.fee)),
+        inputs: record.inputs.iter().flatten().map(map_input).collect(),
+        outputs: record.outputs.iter().flatten().map(map_output).collect(),
+        reference_inputs: record
+            .reference_inputs
+            .iter()
+            .flatten()
+            .map(map_input)
+            .collect(),
+        mint: map_mint(record.mint.as_deref().unwrap_or_default()),
+        auxiliary: record.metadata.as_deref().and_then(map_metadata),
+        ..Default::default()
+    }
+}
+
+/// Maps a legacy event into the transactions it describes.
+///
+/// Events scoped to a part of a transaction (an input, an output, a mint, a
+/// metadata entry) become a partial transaction holding only that part. Block
+/// events yield their transactions when the mapper included block details.
+/// Events with no transaction data yield nothing.
+pub fn event_to_txs(event: &Event) -> Vec<ParsedTx> {
+    let tx = match &event.data {
+        EventData::Block(x) | EventData::BlockEnd(x) => {
+            return x
+                .transactions
+                .iter()
+                .flatten()
+                .map(map_transaction)
+                .collect()
+        }
+        EventData::Transaction(x) | EventData::TransactionEnd(x) => map_transaction(x),
+        EventData::TxInput(x) => ParsedTx {
+            inputs: vec![map_input(x)],
+            ..Default::default()
+        },
+        EventData::TxOutput(x) => ParsedTx {
+            outputs: vec![map_output(x)],
+            ..Default::default()
+        },
+        EventData::OutputAsset(x) => {
+            let address = event.context.output_address.as_deref().unwrap_or_default();
+
+            ParsedTx {
+                outputs: vec![map_output_assets(address, std::slice::from_ref(x))],
+                ..Default::default()
+            }
+        }
+        EventData::Mint(x) => ParsedTx {
+            mint: map_mint(std::slice::from_ref(x)),
+            ..Default::default()
+        },
+        EventData::Metadata(x) => ParsedTx {
+            auxiliary: map_metadata(std::slice::from_ref(x)),
+            ..Default::default()
+        },
+        _ => return vec![],
+    };
+
+    vec![tx]
+}
+
+fn map_era(era: &Era) -> Option<u8> {
+    match era {
+        Era::Byron => Some(0),
+        Era::Shelley => Some(1),
+        Era::Allegra => Some(2),
+        Era::Mary => Some(3),
+        Era::Alonzo => Some(4),
+        Era::Babbage => Some(5),
+        Era::Conway => Some(6),
+        Era::Undefined | Era::Unknown => None,
+    }
+}
+
+/// Describes the block of a legacy event. Block events carry the full header,
+/// every other event only knows the slot and hash from its context.
+pub fn event_to_block<'a>(event: &Event, txs: &'a [TxSubject<'a>]) -> BlockSubject<'a> {
+    match &event.data {
+        EventData::Block(x) | EventData::BlockEnd(x) => BlockSubject {
+            hash: Some(hex_bytes(&x.hash)),
+            slot: Some(x.slot),
+            era: map_era(&x.era),
+            issuer_vkey: Some(hex_bytes(&x.issuer_vkey)),
+            txs: x.transactions.as_ref().map(|_| txs),
+        },
+        _ => BlockSubject {
+            hash: event.context.block_hash.as_deref().map(hex_bytes),
+            slot: event.context.slot,
+            ..Default::default()
+        },
+    }
+}
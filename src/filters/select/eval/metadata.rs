@@ -1,22 +1,216 @@
+use pallas::interop::utxorpc::spec::cardano::metadatum::Metadatum as M;
+use regex::Regex;
+
 use super::*;
 
+/// Applies a leaf pattern to every scalar found in a metadatum tree, recursing
+/// through the items of arrays and the keys and values of maps.
+fn is_deep_match(subject: &Metadatum, leaf: &dyn Fn(&M) -> MatchOutcome) -> MatchOutcome {
+    match subject.metadatum.as_ref() {
+        Some(M::Array(array)) => {
+            MatchOutcome::fold_any_of(array.items.iter().map(|x| is_deep_match(x, leaf)))
+        }
+        Some(M::Map(map)) => {
+            let pairs = map
+                .pairs
+                .iter()
+                .flat_map(|p| p.key.iter().chain(p.value.iter()));
+
+            MatchOutcome::fold_any_of(pairs.map(|x| is_deep_match(x, leaf)))
+        }
+        Some(x) => leaf(x),
+        None => MatchOutcome::Negative,
+    }
+}
+
+impl PatternOf<&Metadatum> for NumericPattern<i64> {
+    fn is_match(&self, subject: &Metadatum) -> MatchOutcome {
+        is_deep_match(subject, &|x| match x {
+            M::Int(x) => self.is_match(*x),
+            _ => MatchOutcome::Negative,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BytesPattern {
+    Exact(FlexBytes),
+    Prefix(FlexBytes),
+}
+
+impl PatternOf<&[u8]> for BytesPattern {
+    fn is_match(&self, subject: &[u8]) -> MatchOutcome {
+        match self {
+            BytesPattern::Exact(x) => MatchOutcome::if_equal(x.deref(), subject),
+            BytesPattern::Prefix(x) => MatchOutcome::if_true(subject.starts_with(x)),
+        }
+    }
+}
+
+impl PatternOf<&Metadatum> for BytesPattern {
+    fn is_match(&self, subject: &Metadatum) -> MatchOutcome {
+        is_deep_match(subject, &|x| match x {
+            M::Bytes(x) => self.is_match(x.as_ref()),
+            _ => MatchOutcome::Negative,
+        })
+    }
+}
+
+/// A step into a metadatum: the key of a map entry, or the index of an array
+/// item when numeric.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum MetadatumKey {
+    Int(i64),
+    Text(String),
+}
+
+impl FromStr for MetadatumKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            anyhow::bail!("empty metadatum key");
+        }
+
+        match s.parse() {
+            Ok(x) => Ok(Self::Int(x)),
+            Err(_) => Ok(Self::Text(s.to_owned())),
+        }
+    }
+}
+
+impl PatternOf<&Metadatum> for MetadatumKey {
+    fn is_match(&self, subject: &Metadatum) -> MatchOutcome {
+        match (self, subject.metadatum.as_ref()) {
+            (MetadatumKey::Int(a), Some(M::Int(b))) => MatchOutcome::if_equal(a, b),
+            // numeric segments of a path can't tell apart text keys made of
+            // digits, such as the version keys of some standards
+            (MetadatumKey::Int(a), Some(M::Text(b))) => MatchOutcome::if_true(*b == a.to_string()),
+            (MetadatumKey::Text(a), Some(M::Text(b))) => MatchOutcome::if_equal(a, b),
+            // keys such as policy ids are often encoded as bytes
+            (MetadatumKey::Text(a), Some(M::Bytes(b))) => {
+                MatchOutcome::if_true(a.eq_ignore_ascii_case(&hex::encode(b)))
+            }
+            _ => MatchOutcome::Negative,
+        }
+    }
+}
+
+impl MetadatumKey {
+    /// The children of `subject` reached through this key
+    fn step<'a>(&self, subject: &'a Metadatum) -> Vec<&'a Metadatum> {
+        match (self, subject.metadatum.as_ref()) {
+            (_, Some(M::Map(map))) => map
+                .pairs
+                .iter()
+                .filter(|p| {
+                    p.key
+                        .as_ref()
+                        .is_some_and(|k| self.is_match(k) == MatchOutcome::Positive)
+                })
+                .filter_map(|p| p.value.as_ref())
+                .collect(),
+            (MetadatumKey::Int(idx), Some(M::Array(array))) => usize::try_from(*idx)
+                .ok()
+                .and_then(|idx| array.items.get(idx))
+                .into_iter()
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MapPattern {
+    /// keys to follow from the root of the metadatum
+    #[serde(default)]
+    path: Vec<MetadatumKey>,
+
+    /// pattern for the value found at the end of the path, any value matches
+    /// if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value: Option<Box<MetadatumPattern>>,
+}
+
+impl PatternOf<&Metadatum> for MapPattern {
+    fn is_match(&self, subject: &Metadatum) -> MatchOutcome {
+        if !matches!(subject.metadatum, Some(M::Map(_))) {
+            return MatchOutcome::Negative;
+        }
+
+        let found = self.path.iter().fold(vec![subject], |found, key| {
+            found.into_iter().flat_map(|x| key.step(x)).collect()
+        });
+
+        match &self.value {
+            Some(x) => x.is_any_match(found.into_iter()),
+            None => MatchOutcome::if_true(!found.is_empty()),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum MetadatumPattern {
     Text(TextPattern),
     Int(NumericPattern<i64>),
-    // TODO: bytes, array, map
+    Bytes(BytesPattern),
+    /// an array with any item matching the inner pattern
+    Array(Box<MetadatumPattern>),
+    Map(MapPattern),
 }
 
 impl PatternOf<&Metadatum> for MetadatumPattern {
     fn is_match(&self, subject: &Metadatum) -> MatchOutcome {
         match self {
             MetadatumPattern::Text(x) => x.is_match(subject),
-            MetadatumPattern::Int(_) => todo!(),
+            MetadatumPattern::Int(x) => x.is_match(subject),
+            MetadatumPattern::Bytes(x) => x.is_match(subject),
+            MetadatumPattern::Array(x) => match subject.metadatum.as_ref() {
+                Some(M::Array(array)) => x.is_any_match(array.items.iter()),
+                _ => MatchOutcome::Negative,
+            },
+            MetadatumPattern::Map(x) => x.is_match(subject),
         }
     }
 }
 
+impl FromStr for MetadatumPattern {
+    type Err = anyhow::Error;
+
+    /// Parses the `<op><value>` suffix of the `#label` shorthand
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let prefix_regex = |x: &str| Regex::new(&format!("^{}", regex::escape(x)));
+
+        let pattern = if let Some(x) = s.strip_prefix(">=") {
+            Self::Int(NumericPattern::Gte(x.parse()?))
+        } else if let Some(x) = s.strip_prefix("<=") {
+            Self::Int(NumericPattern::Lte(x.parse()?))
+        } else if let Some(x) = s.strip_prefix("^=") {
+            match x.strip_prefix("0x") {
+                Some(hex) => Self::Bytes(BytesPattern::Prefix(FlexBytes::from_hex(hex)?)),
+                None => Self::Text(TextPattern::Regex(prefix_regex(x)?)),
+            }
+        } else if let Some(x) = s.strip_prefix('~') {
+            Self::Text(TextPattern::Regex(Regex::new(x)?))
+        } else if let Some(x) = s.strip_prefix('=') {
+            if let Some(hex) = x.strip_prefix("0x") {
+                Self::Bytes(BytesPattern::Exact(FlexBytes::from_hex(hex)?))
+            } else if let Ok(int) = x.parse() {
+                Self::Int(NumericPattern::Exact(int))
+            } else {
+                Self::Text(TextPattern::Exact(x.to_owned()))
+            }
+        } else {
+            anyhow::bail!("unknown metadatum operator (expected =, ~, ^=, >= or <=)");
+        };
+
+        Ok(pattern)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct MetadataPattern {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -26,26 +220,41 @@ pub struct MetadataPattern {
     value: Option<MetadatumPattern>,
 }
 
-use regex::Regex;
-
 impl FromStr for MetadataPattern {
     type Err = anyhow::Error;
 
+    /// Parses the shorthand `#<label>[.<key>...][<op><value>]`, eg:
+    /// `#674.msg~(?i)hello` or `#721.<policy>.<name>.name=Foo`
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"#(\d+)").unwrap();
+        let Some(s) = s.strip_prefix('#') else {
+            anyhow::bail!("can't parse string as metadata pattern (expected #<u64>)");
+        };
 
-        if let Some(caps) = re.captures(s) {
-            if caps.len() == 2 {
-                let label = caps[1].parse()?;
+        let (path, value) = match s.find(['=', '~', '^', '<', '>']) {
+            Some(idx) => (&s[..idx], Some(MetadatumPattern::from_str(&s[idx..])?)),
+            None => (s, None),
+        };
 
-                return Ok(Self {
-                    label: Some(label),
-                    ..Default::default()
-                });
-            }
-        }
+        let mut path = path.split('.');
+
+        let label = path.next().unwrap_or_default().parse()?;
+
+        let path = path
+            .map(MetadatumKey::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let value = match (path.is_empty(), value) {
+            (true, value) => value,
+            (false, value) => Some(MetadatumPattern::Map(MapPattern {
+                path,
+                value: value.map(Box::new),
+            })),
+        };
 
-        anyhow::bail!("can't parse string as metadata pattern (expected #<u64>)");
+        Ok(Self {
+            label: Some(label),
+            value,
+        })
     }
 }
 
@@ -183,4 +392,179 @@ mod tests {
             MatchOutcome::Negative
         );
     }
+
+    fn text(x: &str) -> Metadatum {
+        Metadatum {
+            metadatum: M::Text(x.into()).into(),
+        }
+    }
+
+    fn int(x: i64) -> Metadatum {
+        Metadatum {
+            metadatum: M::Int(x).into(),
+        }
+    }
+
+    fn array(items: Vec<Metadatum>) -> Metadatum {
+        use pallas::interop::utxorpc::spec::cardano::MetadatumArray;
+
+        Metadatum {
+            metadatum: M::Array(MetadatumArray { items }).into(),
+        }
+    }
+
+    fn map(pairs: Vec<(Metadatum, Metadatum)>) -> Metadatum {
+        use pallas::interop::utxorpc::spec::cardano::{MetadatumMap, MetadatumPair};
+
+        let pairs = pairs
+            .into_iter()
+            .map(|(k, v)| MetadatumPair {
+                key: Some(k),
+                value: Some(v),
+            })
+            .collect();
+
+        Metadatum {
+            metadatum: M::Map(MetadatumMap { pairs }).into(),
+        }
+    }
+
+    /// a CIP-20 message
+    fn cip20() -> Metadatum {
+        map(vec![(
+            text("msg"),
+            array(vec![text("Invoice-No: 1234"), int(42)]),
+        )])
+    }
+
+    #[test]
+    fn int_value_match() {
+        let pattern: MetadatumPattern =
+            serde_json::from_str(r#"{ "int": { "gte": 40 } }"#).unwrap();
+        assert_eq!(pattern.is_match(&cip20()), MatchOutcome::Positive);
+
+        let pattern = MetadatumPattern::Int(NumericPattern::Between(0, 10));
+        assert_eq!(pattern.is_match(&cip20()), MatchOutcome::Negative);
+        assert_eq!(pattern.is_match(&int(5)), MatchOutcome::Positive);
+    }
+
+    #[test]
+    fn bytes_value_match() {
+        let subject = Metadatum {
+            metadatum: M::Bytes(vec![0xca, 0xfe, 0x01].into()).into(),
+        };
+
+        let pattern: MetadatumPattern =
+            serde_json::from_str(r#"{ "bytes": { "prefix": "cafe" } }"#).unwrap();
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Positive);
+
+        let pattern = MetadatumPattern::Bytes(BytesPattern::Exact(vec![0xca, 0xfe].into()));
+        assert_eq!(pattern.is_match(&subject), MatchOutcome::Negative);
+    }
+
+    #[test]
+    fn array_value_match() {
+        let items = array(vec![text("a"), text("b")]);
+
+        let pattern = MetadatumPattern::Array(Box::new(MetadatumPattern::Text(
+            TextPattern::Exact("b".into()),
+        )));
+        assert_eq!(pattern.is_match(&items), MatchOutcome::Positive);
+
+        // the array pattern doesn't look into nested structures
+        assert_eq!(pattern.is_match(&cip20()), MatchOutcome::Negative);
+    }
+
+    #[test]
+    fn map_path_match() {
+        let pattern: MetadatumPattern = serde_json::from_str(
+            r#"{ "map": { "path": ["msg", 0], "value": { "text": { "regex": "^Invoice" } } } }"#,
+        )
+        .unwrap();
+        assert_eq!(pattern.is_match(&cip20()), MatchOutcome::Positive);
+
+        let pattern = MetadatumPattern::Map(MapPattern {
+            path: vec![MetadatumKey::Text("msg".into()), MetadatumKey::Int(1)],
+            value: Some(Box::new(MetadatumPattern::Int(NumericPattern::Exact(42)))),
+        });
+        assert_eq!(pattern.is_match(&cip20()), MatchOutcome::Positive);
+
+        let pattern = MetadatumPattern::Map(MapPattern {
+            path: vec![MetadatumKey::Text("other".into())],
+            value: None,
+        });
+        assert_eq!(pattern.is_match(&cip20()), MatchOutcome::Negative);
+    }
+
+    #[test]
+    fn numeric_text_key_match() {
+        let subject = map(vec![
+            (text("1"), text("one")),
+            (int(2), text("two")),
+            (text("02"), text("zero two")),
+        ]);
+
+        let pattern = |key: &str, value: &str| {
+            MetadatumPattern::Map(MapPattern {
+                path: vec![MetadatumKey::from_str(key).unwrap()],
+                value: Some(Box::new(MetadatumPattern::Text(TextPattern::Exact(
+                    value.into(),
+                )))),
+            })
+        };
+
+        assert_eq!(
+            pattern("1", "one").is_match(&subject),
+            MatchOutcome::Positive
+        );
+        assert_eq!(
+            pattern("2", "two").is_match(&subject),
+            MatchOutcome::Positive
+        );
+
+        // the text key must be the canonical form of the number
+        assert_eq!(
+            pattern("2", "zero two").is_match(&subject),
+            MatchOutcome::Negative
+        );
+
+        let parsed = MetadataPattern::from_str("#721.1=one").unwrap();
+        assert_eq!(
+            parsed.value.unwrap().is_match(&subject),
+            MatchOutcome::Positive
+        );
+    }
+
+    #[test]
+    fn shorthand_parse() {
+        let parsed = MetadataPattern::from_str("#674.msg.0~^Invoice").unwrap();
+        assert_eq!(parsed.label, Some(674));
+        assert_eq!(
+            parsed.value.unwrap().is_match(&cip20()),
+            MatchOutcome::Positive
+        );
+
+        let parsed = MetadataPattern::from_str("#674.msg.1>=42").unwrap();
+        assert_eq!(
+            parsed.value.unwrap().is_match(&cip20()),
+            MatchOutcome::Positive
+        );
+
+        let parsed = MetadataPattern::from_str("#674.msg").unwrap();
+        assert_eq!(
+            parsed.value.unwrap().is_match(&cip20()),
+            MatchOutcome::Positive
+        );
+
+        let parsed = MetadataPattern::from_str("#721=0xcafe").unwrap();
+        assert_eq!(
+            parsed.value,
+            Some(MetadatumPattern::Bytes(BytesPattern::Exact(
+                vec![0xca, 0xfe].into()
+            )))
+        );
+
+        assert!(MetadataPattern::from_str("#674.msg>hello").is_err());
+        assert!(MetadataPattern::from_str("674").is_err());
+    }
 }
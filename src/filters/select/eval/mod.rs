@@ -1,29 +1,48 @@
 use std::{ops::Deref, str::FromStr};
 
+use pallas::crypto::hash::Hasher;
 use pallas::interop::utxorpc::spec::cardano::{
     asset, big_int, Asset, AuxData, BigInt, Metadata, Metadatum, Multiasset, TxInput, TxOutput,
 };
+use pallas::interop::utxorpc::{self as interop};
+use pallas::ledger::traverse as trv;
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
+use crate::filters::parse_cbor::NoOpContext;
 use crate::framework::*;
 
 mod address;
 mod assets;
 mod bytes;
+mod certs;
 mod cip14;
+mod credential;
+mod governance;
+mod legacy;
 mod metadata;
+pub mod query;
+mod scripts;
 mod serde_ext;
+mod watchlist;
 
 #[cfg(test)]
-mod testing;
+pub(crate) mod testing;
 
 pub use address::*;
 pub use assets::*;
 pub use bytes::*;
+pub use certs::*;
 pub use cip14::*;
+pub use credential::*;
+pub use governance::*;
 pub use metadata::*;
+pub use scripts::*;
+
+/// Maps cbor records into their parsed form to evaluate them
+pub(crate) type Mapper = interop::Mapper<NoOpContext>;
 pub use serde_ext::*;
+pub use watchlist::*;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MatchOutcome {
@@ -172,6 +191,12 @@ impl PatternOf<u64> for u64 {
     }
 }
 
+impl PatternOf<u32> for u32 {
+    fn is_match(&self, subject: u32) -> MatchOutcome {
+        MatchOutcome::if_equal(self, &subject)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum NumericPattern<I: Ord + Eq> {
@@ -183,13 +208,13 @@ pub enum NumericPattern<I: Ord + Eq> {
 
 pub type CoinPattern = NumericPattern<u64>;
 
-impl PatternOf<u64> for CoinPattern {
-    fn is_match(&self, subject: u64) -> MatchOutcome {
+impl<I: Ord + Eq> PatternOf<I> for NumericPattern<I> {
+    fn is_match(&self, subject: I) -> MatchOutcome {
         match self {
-            CoinPattern::Exact(x) => MatchOutcome::if_true(subject == *x),
-            CoinPattern::Gte(x) => MatchOutcome::if_true(subject >= *x),
-            CoinPattern::Lte(x) => MatchOutcome::if_true(subject <= *x),
-            CoinPattern::Between(a, b) => MatchOutcome::if_true(subject >= *a && subject <= *b),
+            NumericPattern::Exact(x) => MatchOutcome::if_true(subject == *x),
+            NumericPattern::Gte(x) => MatchOutcome::if_true(subject >= *x),
+            NumericPattern::Lte(x) => MatchOutcome::if_true(subject <= *x),
+            NumericPattern::Between(a, b) => MatchOutcome::if_true(subject >= *a && subject <= *b),
         }
     }
 }
@@ -217,6 +242,32 @@ fn asset_quantity_to_u64(asset: &Asset) -> u64 {
     }
 }
 
+/// Exact conversion of a u5c `BigInt` into an `i128`, `None` when it doesn't
+/// fit. As in CBOR, the bytes of a negative big int hold `n` for `-1 - n`.
+fn big_int_to_i128(value: Option<&BigInt>) -> Option<i128> {
+    let fold = |bytes: &[u8]| {
+        bytes.iter().try_fold(0i128, |acc, b| {
+            acc.checked_mul(256)?.checked_add(*b as i128)
+        })
+    };
+
+    match value?.big_int.as_ref()? {
+        big_int::BigInt::Int(x) => Some(*x as i128),
+        big_int::BigInt::BigUInt(bytes) => fold(bytes),
+        big_int::BigInt::BigNInt(bytes) => fold(bytes).map(|x| -1 - x),
+    }
+}
+
+/// Extracts the quantity of a u5c `Asset` as an `i128`, negative for burns
+fn asset_quantity_to_i128(asset: &Asset) -> Option<i128> {
+    match asset.quantity.as_ref() {
+        Some(asset::Quantity::OutputCoin(x)) | Some(asset::Quantity::MintCoin(x)) => {
+            big_int_to_i128(Some(x))
+        }
+        None => None,
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum TextPattern {
@@ -304,11 +355,6 @@ impl PatternOf<&[u8]> for DatumPattern {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct ScriptPattern {
-    hash: Option<Vec<u8>>,
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct OutputPattern {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -347,8 +393,14 @@ impl PatternOf<&TxOutput> for OutputPattern {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct InputPattern {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tx_hash: Option<FlexBytes>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output_index: Option<u32>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     address: Option<StringOrStruct<AddressPattern>>,
 
@@ -360,35 +412,51 @@ pub struct InputPattern {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     datum: Option<StringOrStruct<DatumPattern>>,
-    // u5c redeemer structure is not suitable, is lacks a datum hash (and it also contains a
-    // redundant purpose flag) redeemer: Option<DatumPattern>,
+}
+
+impl InputPattern {
+    /// Whether the pattern looks at the spent output, which needs to be resolved
+    fn needs_output(&self) -> bool {
+        self.address.is_some()
+            || !self.assets.is_empty()
+            || self.lovelace.is_some()
+            || self.datum.is_some()
+    }
 }
 
 impl PatternOf<&TxInput> for InputPattern {
     fn is_match(&self, subject: &TxInput) -> MatchOutcome {
+        let a = self.tx_hash.is_match(subject.tx_hash.as_ref());
+
+        let b = self.output_index.is_match(subject.output_index);
+
+        if !self.needs_output() {
+            return MatchOutcome::fold_all_of([a, b].into_iter());
+        }
+
         let as_output = match subject.as_output.as_ref() {
             Some(x) => x,
-            None => return MatchOutcome::Uncertain,
+            None => return MatchOutcome::fold_all_of([a, b, MatchOutcome::Uncertain].into_iter()),
         };
 
-        let a = self.address.is_match(as_output.address.as_ref());
+        let c = self.address.is_match(as_output.address.as_ref());
 
-        let b = self
+        let d = self
             .lovelace
             .is_match(big_int_to_u64(as_output.coin.as_ref()));
 
-        let c = self
+        let e = self
             .assets
             .iter()
             .map(|x| x.is_any_match(as_output.assets.iter()));
 
-        let c = MatchOutcome::fold_all_of(c);
+        let e = MatchOutcome::fold_all_of(e);
 
-        let d = self
+        let f = self
             .datum
             .is_some_match(as_output.datum.as_ref().map(|x| x.hash.as_ref()));
 
-        MatchOutcome::fold_all_of([a, b, c, d].into_iter())
+        MatchOutcome::fold_all_of([a, b, c, d, e, f].into_iter())
     }
 }
 
@@ -410,6 +478,20 @@ impl PatternOf<&Multiasset> for MintPattern {
     }
 }
 
+/// A tx under evaluation. The decoded CBOR is available when the record came
+/// in raw form, it provides the data that the u5c structs lack.
+#[derive(Clone, Copy, Debug)]
+pub struct TxSubject<'a> {
+    pub parsed: &'a ParsedTx,
+    pub raw: Option<&'a trv::MultiEraTx<'a>>,
+}
+
+impl<'a> From<&'a ParsedTx> for TxSubject<'a> {
+    fn from(parsed: &'a ParsedTx) -> Self {
+        Self { parsed, raw: None }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct TxPattern {
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -423,12 +505,36 @@ pub struct TxPattern {
 
     #[serde(skip_serializing_if = "Vec::is_empty")]
     metadata: Vec<MetadataPattern>,
-    // the u5c struct is not suitable, it lacks hash for the scripts
-    // scripts: Vec<ScriptPattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    reference_inputs: Vec<InputPattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    collateral: Vec<InputPattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    scripts: Vec<ScriptPattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    redeemers: Vec<RedeemerPattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    withdrawals: Vec<WithdrawalPattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    certificates: Vec<CertificatePattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    proposals: Vec<ProposalPattern>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    votes: Vec<VotePattern>,
 }
 
-impl PatternOf<&ParsedTx> for TxPattern {
-    fn is_match(&self, tx: &ParsedTx) -> MatchOutcome {
+impl PatternOf<TxSubject<'_>> for TxPattern {
+    fn is_match(&self, subject: TxSubject) -> MatchOutcome {
+        let tx = subject.parsed;
+
         let a = self.inputs.iter().map(|x| x.is_any_match(tx.inputs.iter()));
 
         let a = MatchOutcome::fold_all_of(a);
@@ -451,27 +557,209 @@ impl PatternOf<&ParsedTx> for TxPattern {
 
         let d = MatchOutcome::fold_all_of(d);
 
-        MatchOutcome::fold_all_of([a, b, c, d].into_iter())
+        let e = self
+            .reference_inputs
+            .iter()
+            .map(|x| x.is_any_match(tx.reference_inputs.iter()));
+
+        let e = MatchOutcome::fold_all_of(e);
+
+        let f = self
+            .collateral
+            .iter()
+            .map(|x| x.is_any_match(iter_tx_collateral(tx)));
+
+        let f = MatchOutcome::fold_all_of(f);
+
+        let g = self.scripts.iter().map(|x| x.is_match(subject));
+
+        let g = MatchOutcome::fold_all_of(g);
+
+        let h = self.redeemers.iter().map(|x| x.is_match(subject));
+
+        let h = MatchOutcome::fold_all_of(h);
+
+        let i = self
+            .withdrawals
+            .iter()
+            .map(|x| x.is_any_match(tx.withdrawals.iter()));
+
+        let i = MatchOutcome::fold_all_of(i);
+
+        let j = self
+            .certificates
+            .iter()
+            .map(|x| x.is_any_match(tx.certificates.iter()));
+
+        let j = MatchOutcome::fold_all_of(j);
+
+        let k = self
+            .proposals
+            .iter()
+            .map(|x| x.is_any_match(tx.proposals.iter()));
+
+        let k = MatchOutcome::fold_all_of(k);
+
+        let l = self.votes.iter().map(|x| x.is_match(subject));
+
+        let l = MatchOutcome::fold_all_of(l);
+
+        MatchOutcome::fold_all_of([a, b, c, d, e, f, g, h, i, j, k, l].into_iter())
     }
 }
 
 pub type SlotPattern = NumericPattern<u64>;
 
-pub type EraPattern = NumericPattern<u8>;
+/// names of the ledger eras, in the order of their ordinals
+const ERA_NAMES: [&str; 7] = [
+    "byron", "shelley", "allegra", "mary", "alonzo", "babbage", "conway",
+];
+
+/// A ledger era, either by its ordinal (starting with Byron as `0`) or by its
+/// name
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(try_from = "EraDef", into = "u8")]
+pub struct Era(u8);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EraDef {
+    Ordinal(u8),
+    Name(String),
+}
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+impl TryFrom<EraDef> for Era {
+    type Error = String;
+
+    fn try_from(value: EraDef) -> Result<Self, Self::Error> {
+        match value {
+            EraDef::Ordinal(x) => Ok(Era(x)),
+            EraDef::Name(x) => ERA_NAMES
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(&x))
+                .map(|x| Era(x as u8))
+                .ok_or_else(|| format!("unknown era `{x}`, expected one of {ERA_NAMES:?}")),
+        }
+    }
+}
+
+impl From<Era> for u8 {
+    fn from(value: Era) -> Self {
+        value.0
+    }
+}
+
+pub type EraPattern = NumericPattern<Era>;
+
+/// The block data available to block patterns. Depending on the record being
+/// evaluated, some of it might not be known.
+#[derive(Debug, Default)]
+pub struct BlockSubject<'a> {
+    pub hash: Option<Vec<u8>>,
+    pub slot: Option<u64>,
+
+    /// ordinal of the ledger era, starting with Byron as `0`
+    pub era: Option<u8>,
+
+    pub issuer_vkey: Option<Vec<u8>>,
+
+    /// all the txs of the block, when available
+    pub txs: Option<&'a [TxSubject<'a>]>,
+}
+
+impl<'a> BlockSubject<'a> {
+    fn from_parsed(block: &ParsedBlock, txs: &'a [TxSubject<'a>]) -> Self {
+        Self {
+            hash: block.header.as_ref().map(|x| x.hash.to_vec()),
+            slot: block.header.as_ref().map(|x| x.slot),
+            // u5c blocks don't carry the era nor the issuer
+            era: None,
+            issuer_vkey: None,
+            txs: Some(txs),
+        }
+    }
+
+    fn from_cbor(block: &trv::MultiEraBlock, txs: &'a [TxSubject<'a>]) -> Self {
+        Self {
+            hash: Some(block.hash().to_vec()),
+            slot: Some(block.slot()),
+            era: Some(block.era() as u8),
+            issuer_vkey: block.header().issuer_vkey().map(|x| x.to_vec()),
+            txs: Some(txs),
+        }
+    }
+}
+
+/// Evaluates a pattern against a value that might be missing from the subject
+fn is_known_match<S, P>(pattern: &Option<P>, subject: Option<S>) -> MatchOutcome
+where
+    P: PatternOf<S>,
+{
+    match (pattern, subject) {
+        (None, _) => MatchOutcome::Positive,
+        (Some(_), None) => MatchOutcome::Uncertain,
+        (Some(pattern), Some(subject)) => pattern.is_match(subject),
+    }
+}
+
+/// Matches a block issuer either by its verification key or by its pool id
+/// (the blake2b-224 hash of the key).
+fn is_issuer_match(issuer: &FlexBytes, vkey: &[u8]) -> MatchOutcome {
+    let pool_id = Hasher::<224>::hash(vkey);
+
+    MatchOutcome::if_true(issuer.deref() == vkey || issuer.deref() == pool_id.as_ref())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct BlockPattern {
-    hash: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<FlexBytes>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     slot: Option<SlotPattern>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     era: Option<EraPattern>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    issuer: Option<FlexBytes>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     txs: Vec<TxPattern>,
 }
 
+impl PatternOf<&BlockSubject<'_>> for BlockPattern {
+    fn is_match(&self, subject: &BlockSubject) -> MatchOutcome {
+        let a = is_known_match(&self.hash, subject.hash.as_deref());
+
+        let b = is_known_match(&self.slot, subject.slot);
+
+        let c = is_known_match(&self.era, subject.era.map(Era));
+
+        let d = match (&self.issuer, &subject.issuer_vkey) {
+            (None, _) => MatchOutcome::Positive,
+            (Some(_), None) => MatchOutcome::Uncertain,
+            (Some(issuer), Some(vkey)) => is_issuer_match(issuer, vkey),
+        };
+
+        let e = match subject.txs {
+            _ if self.txs.is_empty() => MatchOutcome::Positive,
+            Some(txs) => {
+                let e = self.txs.iter().map(|x| x.is_any_match(txs.iter().copied()));
+                MatchOutcome::fold_all_of(e)
+            }
+            None => MatchOutcome::Uncertain,
+        };
+
+        MatchOutcome::fold_all_of([a, b, c, d, e].into_iter())
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Pattern {
     Block(BlockPattern),
-    Tx(TxPattern),
+    Tx(Box<TxPattern>),
     Address(StringOrStruct<AddressPattern>),
     Asset(StringOrStruct<AssetPattern>),
     Input(InputPattern),
@@ -479,6 +767,20 @@ pub enum Pattern {
     Mint(MintPattern),
     Metadata(StringOrStruct<MetadataPattern>),
     Datum(StringOrStruct<DatumPattern>),
+
+    #[serde(rename = "reference_input")]
+    ReferenceInput(InputPattern),
+
+    Collateral(InputPattern),
+    Script(StringOrStruct<ScriptPattern>),
+    Redeemer(RedeemerPattern),
+    Withdrawal(WithdrawalPattern),
+    Certificate(CertificatePattern),
+    Proposal(ProposalPattern),
+    Vote(VotePattern),
+    Watchlist(WatchlistPattern),
+    Credential(StringOrStruct<CredentialPattern>),
+    Value(ValuePattern),
 }
 
 impl From<AssetPattern> for Pattern {
@@ -505,6 +807,18 @@ impl From<MetadataPattern> for Pattern {
     }
 }
 
+impl From<CredentialPattern> for Pattern {
+    fn from(value: CredentialPattern) -> Self {
+        Pattern::Credential(StringOrStruct(value))
+    }
+}
+
+impl From<ScriptPattern> for Pattern {
+    fn from(value: ScriptPattern) -> Self {
+        Pattern::Script(StringOrStruct(value))
+    }
+}
+
 impl FromBech32 for Pattern {
     fn from_bech32_parts(hrp: &str, content: Vec<u8>) -> Option<Self> {
         match hrp {
@@ -512,7 +826,20 @@ impl FromBech32 for Pattern {
             "addr" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
             "addr_test" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
             "stake" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
+            "stake_test" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
+            "addr_vk" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
+            "addr_vkh" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
+            "stake_vk" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
+            "stake_vkh" => AddressPattern::from_bech32_parts(hrp, content).map(From::from),
+            "pool" => CertificatePattern::from_bech32_parts(hrp, content).map(Pattern::Certificate),
+            "drep" => CredentialPattern::from_bech32_parts(hrp, content).map(From::from),
+            "cc_hot" => CredentialPattern::from_bech32_parts(hrp, content).map(From::from),
+            "cc_cold" => CredentialPattern::from_bech32_parts(hrp, content).map(From::from),
+            "drep_script" | "cc_hot_script" | "cc_cold_script" => {
+                CredentialPattern::from_bech32_parts(hrp, content).map(From::from)
+            }
             "datum" => DatumPattern::from_bech32_parts(hrp, content).map(From::from),
+            "script" => ScriptPattern::from_bech32_parts(hrp, content).map(From::from),
             _ => None,
         }
     }
@@ -564,22 +891,40 @@ fn iter_tx_datums(tx: &ParsedTx) -> impl Iterator<Item = &[u8]> {
     a
 }
 
-impl PatternOf<&ParsedTx> for Pattern {
-    fn is_match(&self, subject: &ParsedTx) -> MatchOutcome {
+fn iter_tx_collateral(tx: &ParsedTx) -> impl Iterator<Item = &TxInput> {
+    tx.collateral.iter().flat_map(|x| x.collateral.iter())
+}
+
+impl PatternOf<TxSubject<'_>> for Pattern {
+    fn is_match(&self, subject: TxSubject) -> MatchOutcome {
+        let tx = subject.parsed;
+
         match self {
             Pattern::Block(_) => MatchOutcome::Negative,
             Pattern::Tx(x) => x.is_match(subject),
-            Pattern::Address(x) => x.is_any_match(iter_tx_addresses(subject)),
-            Pattern::Asset(x) => x.is_any_match(iter_tx_assets(subject)),
-            Pattern::Input(x) => x.is_any_match(subject.inputs.iter()),
-            Pattern::Output(x) => x.is_any_match(subject.outputs.iter()),
-            Pattern::Mint(x) => x.is_any_match(subject.mint.iter()),
-            Pattern::Metadata(x) => x.is_any_match(subject.auxiliary.iter()),
-            Pattern::Datum(x) => x.is_any_match(iter_tx_datums(subject)),
+            Pattern::Address(x) => x.is_any_match(iter_tx_addresses(tx)),
+            Pattern::Asset(x) => x.is_any_match(iter_tx_assets(tx)),
+            Pattern::Input(x) => x.is_any_match(tx.inputs.iter()),
+            Pattern::Output(x) => x.is_any_match(tx.outputs.iter()),
+            Pattern::Mint(x) => x.is_any_match(tx.mint.iter()),
+            Pattern::Metadata(x) => x.is_any_match(tx.auxiliary.iter()),
+            Pattern::Datum(x) => x.is_any_match(iter_tx_datums(tx)),
+            Pattern::ReferenceInput(x) => x.is_any_match(tx.reference_inputs.iter()),
+            Pattern::Collateral(x) => x.is_any_match(iter_tx_collateral(tx)),
+            Pattern::Script(x) => x.is_match(subject),
+            Pattern::Redeemer(x) => x.is_match(subject),
+            Pattern::Withdrawal(x) => x.is_any_match(tx.withdrawals.iter()),
+            Pattern::Certificate(x) => x.is_any_match(tx.certificates.iter()),
+            Pattern::Proposal(x) => x.is_any_match(tx.proposals.iter()),
+            Pattern::Vote(x) => x.is_match(subject),
+            Pattern::Watchlist(x) => x.is_match(subject),
+            Pattern::Credential(x) => x.is_match(subject),
+            Pattern::Value(x) => x.is_match(tx),
         }
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Predicate {
@@ -609,6 +954,17 @@ impl Predicate {
     pub fn not(p: Self) -> Self {
         Predicate::Not(Box::new(StringOrStruct(p)))
     }
+
+    /// True when the predicate only looks at block patterns, meaning that it
+    /// can select a block regardless of its transactions.
+    pub fn is_block_level(&self) -> bool {
+        match self {
+            Predicate::Match(x) => matches!(x.deref(), Pattern::Block(_)),
+            Predicate::Not(x) => x.is_block_level(),
+            Predicate::AnyOf(x) => x.iter().all(|x| x.is_block_level()),
+            Predicate::AllOf(x) => x.iter().all(|x| x.is_block_level()),
+        }
+    }
 }
 
 impl From<Pattern> for Predicate {
@@ -621,42 +977,236 @@ impl FromStr for Predicate {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let inner = Pattern::from_str(s)?;
-        Ok(inner.into())
+        let err = match query::parse(s) {
+            Ok(x) => return Ok(x),
+            Err(err) => err,
+        };
+
+        // a bare metadata shorthand can hold regexes with spaces or parens
+        // that don't read as a query
+        if s.trim_start().starts_with('#') {
+            if let Ok(x) = Pattern::from_str(s.trim()) {
+                return Ok(x.into());
+            }
+        }
+
+        // rendered through miette so that the config error points at the span
+        Err(anyhow::anyhow!("{:?}", miette::Report::new(err)))
     }
 }
 
-fn eval_tx(tx: &ParsedTx, predicate: &Predicate) -> MatchOutcome {
+fn eval_tx(tx: TxSubject, block: Option<&BlockSubject>, predicate: &Predicate) -> MatchOutcome {
     match predicate {
-        Predicate::Not(x) => !eval_tx(tx, x),
+        Predicate::Not(x) => !eval_tx(tx, block, x),
         Predicate::AnyOf(x) => {
-            let o = x.iter().map(|x| eval_tx(tx, x));
+            let o = x.iter().map(|x| eval_tx(tx, block, x));
             MatchOutcome::fold_any_of(o)
         }
         Predicate::AllOf(x) => {
-            let o = x.iter().map(|x| eval_tx(tx, x));
+            let o = x.iter().map(|x| eval_tx(tx, block, x));
             MatchOutcome::fold_all_of(o)
         }
-        Predicate::Match(x) => x.is_match(tx),
+        Predicate::Match(x) => match (x.deref(), block) {
+            (Pattern::Block(x), Some(block)) => x.is_match(block),
+            _ => x.is_match(tx),
+        },
     }
 }
 
-fn eval_block(block: &ParsedBlock, predicate: &Predicate) -> MatchOutcome {
-    let outcomes = block
+fn eval_block_level(block: &BlockSubject, predicate: &Predicate) -> MatchOutcome {
+    match predicate {
+        Predicate::Not(x) => !eval_block_level(block, x),
+        Predicate::AnyOf(x) => {
+            let o = x.iter().map(|x| eval_block_level(block, x));
+            MatchOutcome::fold_any_of(o)
+        }
+        Predicate::AllOf(x) => {
+            let o = x.iter().map(|x| eval_block_level(block, x));
+            MatchOutcome::fold_all_of(o)
+        }
+        Predicate::Match(x) => match x.deref() {
+            Pattern::Block(x) => x.is_match(block),
+            _ => MatchOutcome::Negative,
+        },
+    }
+}
+
+/// Evaluates the predicate against txs that belong to the given block. Block
+/// patterns are evaluated against the block itself, so a predicate made only
+/// of them selects the block as a whole. The rest of the predicate matches if
+/// any of the txs does.
+fn eval_block(block: &BlockSubject, txs: &[TxSubject], predicate: &Predicate) -> MatchOutcome {
+    if predicate.is_block_level() {
+        return eval_block_level(block, predicate);
+    }
+
+    let eval_txs = |predicates: &[&Predicate]| {
+        let o = txs.iter().map(|tx| {
+            let o = predicates.iter().map(|x| eval_tx(*tx, Some(block), x));
+            MatchOutcome::fold_all_of(o)
+        });
+
+        MatchOutcome::fold_any_of(o)
+    };
+
+    match predicate {
+        Predicate::AnyOf(x) => {
+            let o = x.iter().map(|x| eval_block(block, txs, x));
+            MatchOutcome::fold_any_of(o)
+        }
+        Predicate::AllOf(x) => {
+            // block patterns hold for the block regardless of the tx, only the
+            // rest of them have to match the same tx
+            let (on_block, on_txs): (Vec<&Predicate>, Vec<&Predicate>) = x
+                .iter()
+                .map(|x| x.deref())
+                .partition(|x| x.is_block_level());
+
+            let a = on_block.iter().map(|x| eval_block_level(block, x));
+            let a = MatchOutcome::fold_all_of(a);
+
+            MatchOutcome::fold_all_of([a, eval_txs(&on_txs)].into_iter())
+        }
+        _ => eval_txs(&[predicate]),
+    }
+}
+
+fn eval_legacy_event(event: &legacy_v1::Event, predicate: &Predicate) -> MatchOutcome {
+    // rollbacks carry no data to match against, but the stages downstream
+    // need them to undo what was selected before
+    if let legacy_v1::EventData::RollBack { .. } = event.data {
+        return MatchOutcome::Positive;
+    }
+
+    let txs = legacy::event_to_txs(event);
+    let txs: Vec<_> = txs.iter().map(TxSubject::from).collect();
+    let block = legacy::event_to_block(event, &txs);
+
+    eval_block(&block, &txs, predicate)
+}
+
+fn parsed_block_txs(block: &ParsedBlock) -> Vec<TxSubject<'_>> {
+    block
         .body
         .iter()
-        .flat_map(|b| b.tx.iter())
-        .map(|tx| eval_tx(tx, predicate));
+        .flat_map(|x| x.tx.iter())
+        .map(TxSubject::from)
+        .collect()
+}
+
+/// Evaluates each tx of a block record on its own, in the context of the
+/// block. Returns `None` for records that aren't blocks or can't be decoded.
+pub(crate) fn eval_block_txs(
+    mapper: &Mapper,
+    record: &Record,
+    predicate: &Predicate,
+) -> Option<Vec<MatchOutcome>> {
+    let eval_all = |block: &BlockSubject, txs: &[TxSubject]| {
+        txs.iter()
+            .map(|tx| eval_tx(*tx, Some(block), predicate))
+            .collect()
+    };
+
+    match record {
+        Record::ParsedBlock(x) => {
+            let txs = parsed_block_txs(x);
+            Some(eval_all(&BlockSubject::from_parsed(x, &txs), &txs))
+        }
+        Record::CborBlock(x) => {
+            let block = trv::MultiEraBlock::decode(x).ok()?;
+            let raw = block.txs();
+            let parsed: Vec<_> = raw.iter().map(|x| mapper.map_tx(x)).collect();
+            let txs = cbor_block_txs(&raw, &parsed);
+            Some(eval_all(&BlockSubject::from_cbor(&block, &txs), &txs))
+        }
+        _ => None,
+    }
+}
+
+fn cbor_block_txs<'a>(
+    raw: &'a [trv::MultiEraTx<'a>],
+    parsed: &'a [ParsedTx],
+) -> Vec<TxSubject<'a>> {
+    raw.iter()
+        .zip(parsed)
+        .map(|(raw, parsed)| TxSubject {
+            parsed,
+            raw: Some(raw),
+        })
+        .collect()
+}
 
-    MatchOutcome::fold_any_of(outcomes)
+fn collect_patterns<'a>(predicate: &'a Predicate, out: &mut Vec<&'a Pattern>) {
+    match predicate {
+        Predicate::Match(x) => out.push(x.deref()),
+        Predicate::Not(x) => collect_patterns(x, out),
+        Predicate::AnyOf(x) | Predicate::AllOf(x) => {
+            x.iter().for_each(|x| collect_patterns(x, out));
+        }
+    }
 }
 
-pub fn eval(record: &Record, predicate: &Predicate) -> MatchOutcome {
+/// The patterns of the predicate that can't be evaluated against the record,
+/// to explain why its outcome was uncertain. Empty when the record itself
+/// can't be evaluated.
+pub(crate) fn uncertain_patterns<'a>(
+    mapper: &Mapper,
+    record: &Record,
+    predicate: &'a Predicate,
+) -> Vec<&'a Pattern> {
+    // a predicate without patterns is only uncertain for records that can't
+    // be evaluated at all
+    if eval(mapper, record, &Predicate::any_of(vec![])) == MatchOutcome::Uncertain {
+        return vec![];
+    }
+
+    let mut patterns = vec![];
+    collect_patterns(predicate, &mut patterns);
+
+    patterns
+        .into_iter()
+        .filter(|x| eval(mapper, record, &Predicate::from((*x).clone())) == MatchOutcome::Uncertain)
+        .collect()
+}
+
+pub(crate) fn eval(mapper: &Mapper, record: &Record, predicate: &Predicate) -> MatchOutcome {
     match record {
-        Record::ParsedTx(x) => eval_tx(x, predicate),
-        Record::ParsedBlock(x) => eval_block(x, predicate),
-        _ => {
-            warn!("The select filter is valid only with ParsedTx & ParsedBlock records");
+        Record::ParsedTx(x) => eval_tx(x.into(), None, predicate),
+        Record::ParsedBlock(x) => {
+            let txs = parsed_block_txs(x);
+            eval_block(&BlockSubject::from_parsed(x, &txs), &txs, predicate)
+        }
+        Record::CborTx(x) => match trv::MultiEraTx::decode(x) {
+            Ok(raw) => {
+                let parsed = mapper.map_tx(&raw);
+
+                let tx = TxSubject {
+                    parsed: &parsed,
+                    raw: Some(&raw),
+                };
+
+                eval_tx(tx, None, predicate)
+            }
+            Err(err) => {
+                warn!(%err, "can't decode tx cbor for the select filter");
+                MatchOutcome::Uncertain
+            }
+        },
+        Record::CborBlock(x) => match trv::MultiEraBlock::decode(x) {
+            Ok(block) => {
+                let raw = block.txs();
+                let parsed: Vec<_> = raw.iter().map(|x| mapper.map_tx(x)).collect();
+                let txs = cbor_block_txs(&raw, &parsed);
+                eval_block(&BlockSubject::from_cbor(&block, &txs), &txs, predicate)
+            }
+            Err(err) => {
+                warn!(%err, "can't decode block cbor for the select filter");
+                MatchOutcome::Uncertain
+            }
+        },
+        Record::OuraV1Event(x) => eval_legacy_event(x, predicate),
+        Record::GenericJson(_) => {
+            warn!("The select filter can't evaluate GenericJson records");
             MatchOutcome::Uncertain
         }
     }
@@ -669,7 +1219,7 @@ mod tests {
 
     #[test]
     fn empty_tx_pattern() {
-        let pattern = Pattern::Tx(TxPattern::default());
+        let pattern = Pattern::Tx(Box::default());
 
         let positives = testing::find_positive_test_vectors(pattern);
         assert_eq!(positives, vec![0, 1, 2, 3]);
@@ -732,6 +1282,207 @@ mod tests {
         assert_eq!(pattern.is_match(&invalid_utf8[..]), MatchOutcome::Uncertain);
     }
 
+    fn eval(record: &Record, predicate: &Predicate) -> MatchOutcome {
+        super::eval(&Mapper::default(), record, predicate)
+    }
+
+    fn legacy_event(data: legacy_v1::EventData) -> Record {
+        Record::OuraV1Event(legacy_v1::Event {
+            context: Default::default(),
+            data,
+            fingerprint: None,
+        })
+    }
+
+    #[test]
+    fn eval_legacy_events() {
+        let address = "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x";
+        let predicate = Predicate::from_str(address).unwrap();
+
+        let output = legacy_event(legacy_v1::EventData::TxOutput(legacy_v1::TxOutputRecord {
+            address: address.to_string(),
+            amount: 2000000,
+            assets: None,
+            datum_hash: None,
+            inline_datum: None,
+            reference_script: None,
+            cbor_hex: None,
+        }));
+
+        assert_eq!(eval(&output, &predicate), MatchOutcome::Positive);
+
+        let mint = legacy_event(legacy_v1::EventData::Mint(legacy_v1::MintRecord {
+            policy: "533bb94a8850ee3ccbe483106489399112b74c905342cb1792a797a0".to_string(),
+            asset: hex::encode("xyz1"),
+            quantity: 1,
+        }));
+
+        assert_eq!(eval(&mint, &predicate), MatchOutcome::Negative);
+
+        let predicate = Predicate::from(Pattern::Mint(MintPattern {
+            assets: vec![StringOrStruct(AssetPattern {
+                policy: Some(
+                    hex::decode("533bb94a8850ee3ccbe483106489399112b74c905342cb1792a797a0")
+                        .unwrap()
+                        .into(),
+                ),
+                ..Default::default()
+            })],
+        }));
+
+        assert_eq!(eval(&mint, &predicate), MatchOutcome::Positive);
+
+        let rollback = legacy_event(legacy_v1::EventData::RollBack {
+            block_slot: 0,
+            block_hash: String::new(),
+        });
+
+        // rollbacks pass through regardless of the predicate
+        assert_eq!(eval(&rollback, &predicate), MatchOutcome::Positive);
+
+        let predicate = Predicate::from_str(address).unwrap();
+        assert_eq!(eval(&rollback, &predicate), MatchOutcome::Positive);
+    }
+
+    #[test]
+    fn eval_invalid_cbor_is_uncertain() {
+        let predicate = Predicate::from_str("#127").unwrap();
+
+        let tx = Record::CborTx(vec![0xff, 0x00]);
+        assert_eq!(eval(&tx, &predicate), MatchOutcome::Uncertain);
+
+        let block = Record::CborBlock(vec![0xff, 0x00]);
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Uncertain);
+    }
+
+    #[test]
+    fn eval_block_patterns() {
+        use pallas::interop::utxorpc::spec::cardano::{BlockBody, BlockHeader};
+
+        let block = Record::ParsedBlock(ParsedBlock {
+            header: Some(BlockHeader {
+                slot: 1000,
+                ..Default::default()
+            }),
+            body: Some(BlockBody {
+                tx: testing::test_vectors(),
+            }),
+            ..Default::default()
+        });
+
+        let by_slot = |slot| {
+            Predicate::from(Pattern::Block(BlockPattern {
+                slot: Some(slot),
+                ..Default::default()
+            }))
+        };
+
+        let predicate = by_slot(SlotPattern::Between(900, 1100));
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Positive);
+
+        let predicate = by_slot(SlotPattern::Gte(1100));
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Negative);
+
+        // a block-level match doesn't depend on the txs
+        let empty = Record::ParsedBlock(ParsedBlock {
+            header: Some(BlockHeader {
+                slot: 1000,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let predicate = by_slot(SlotPattern::Exact(1000));
+        assert_eq!(eval(&empty, &predicate), MatchOutcome::Positive);
+
+        // mixed with tx patterns, the block matches through one of its txs
+        let predicate = Predicate::all_of(vec![
+            by_slot(SlotPattern::Exact(1000)),
+            Predicate::from_str("#9980").unwrap(),
+        ]);
+
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Positive);
+        assert_eq!(eval(&empty, &predicate), MatchOutcome::Negative);
+
+        // block patterns match the block even when it has no txs
+        let predicate = Predicate::any_of(vec![
+            by_slot(SlotPattern::Exact(1000)),
+            Predicate::from_str("#9980").unwrap(),
+        ]);
+
+        assert_eq!(eval(&empty, &predicate), MatchOutcome::Positive);
+
+        // u5c blocks don't carry the era nor the issuer, so neither the
+        // pattern nor its negation can be told apart
+        let by_era = Predicate::from(Pattern::Block(BlockPattern {
+            era: Some(EraPattern::Gte(Era(6))),
+            ..Default::default()
+        }));
+
+        assert_eq!(eval(&block, &by_era), MatchOutcome::Uncertain);
+
+        let predicate = Predicate::not(by_era);
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Uncertain);
+
+        let by_issuer = Predicate::from(Pattern::Block(BlockPattern {
+            issuer: Some(FlexBytes::from(vec![0xab; 28])),
+            ..Default::default()
+        }));
+
+        let predicate = Predicate::not(by_issuer);
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Uncertain);
+    }
+
+    #[test]
+    fn eval_legacy_block_patterns() {
+        let vkey = "24dd5f1e85d0a1db7e2d3ffb3ab42af6b8b3d1cdc69e8f1cda2a0d10b6fcfc81";
+        let pool_id = hex::encode(Hasher::<224>::hash(&hex::decode(vkey).unwrap()));
+
+        let block = legacy_event(legacy_v1::EventData::Block(legacy_v1::BlockRecord {
+            era: legacy_v1::Era::Babbage,
+            epoch: None,
+            epoch_slot: None,
+            body_size: 0,
+            issuer_vkey: vkey.to_string(),
+            vrf_vkey: String::new(),
+            tx_count: 0,
+            slot: 1000,
+            hash: "abcd".to_string(),
+            number: 1,
+            previous_hash: String::new(),
+            cbor_hex: None,
+            transactions: None,
+        }));
+
+        let predicate = serde_json::from_str::<StringOrStruct<Predicate>>(&format!(
+            r#"{{ "match": {{ "block": {{ "era": {{ "gte": 5 }}, "issuer": "{pool_id}" }} }} }}"#
+        ))
+        .unwrap();
+
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Positive);
+
+        let predicate = serde_json::from_str::<StringOrStruct<Predicate>>(
+            r#"{ "not": { "match": { "block": { "era": { "exact": 5 } } } } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Negative);
+
+        // eras can be given by name as well
+        let predicate = serde_json::from_str::<StringOrStruct<Predicate>>(
+            r#"{ "match": { "block": { "era": { "between": ["alonzo", "Babbage"] } } } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(eval(&block, &predicate), MatchOutcome::Positive);
+
+        let predicate = serde_json::from_str::<StringOrStruct<Predicate>>(
+            r#"{ "match": { "block": { "era": { "exact": "goguen" } } } }"#,
+        );
+
+        assert!(predicate.is_err());
+    }
+
     #[test]
     fn deser_predicate() {
         serde_json::from_str::<StringOrStruct<Predicate>>("\"addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x\"").unwrap();
@@ -0,0 +1,480 @@
+//! A compact textual syntax for predicates, eg:
+//!
+//! `addr(addr1...) and not asset(asset1...) or metadata(#674 ~ "invoice")`
+//!
+//! Each `kind(arg)` call is a pattern, where `kind` is any of the pattern
+//! names of the struct syntax and `arg` is either the JSON of the pattern or
+//! its string shorthand. Bare shorthands (bech32, `#label`) are accepted as
+//! well. `not` binds tighter than `and`, which binds tighter than `or`.
+
+use std::fmt::{self, Display};
+
+use miette::{Diagnostic, SourceSpan};
+use thiserror::Error;
+
+use super::*;
+
+#[derive(Error, Diagnostic, Debug)]
+#[error("invalid predicate: {reason}")]
+#[diagnostic(code(oura::select::query))]
+pub struct QueryError {
+    #[source_code]
+    src: String,
+
+    #[label("{reason}")]
+    span: SourceSpan,
+
+    reason: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Open,
+    Close,
+    Word(&'a str),
+    Call {
+        name: &'a str,
+        name_span: SourceSpan,
+        arg: &'a str,
+        arg_span: SourceSpan,
+    },
+}
+
+type Spanned<'a> = (Token<'a>, SourceSpan);
+
+/// characters of the operators of the shorthands, which can be surrounded by
+/// spaces within a call
+const SHORTHAND_OPERATORS: [char; 5] = ['=', '~', '^', '<', '>'];
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Spanned<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, span: impl Into<SourceSpan>, reason: impl Into<String>) -> QueryError {
+        QueryError {
+            src: self.src.to_owned(),
+            span: span.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Offset of the closing paren matching the open one at `start`, skipping
+    /// over quoted strings
+    fn find_close(&self, start: usize) -> Result<usize, QueryError> {
+        let mut depth = 0;
+        let mut quoted = false;
+        let mut escaped = false;
+
+        for (idx, c) in self.src[start..].char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                '(' if !quoted => depth += 1,
+                ')' if !quoted => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return Ok(start + idx);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Err(self.error((start, 1), "unclosed parenthesis"))
+    }
+
+    fn tokenize(&mut self) -> Result<(), QueryError> {
+        let src = self.src;
+        let mut idx = 0;
+
+        while let Some(c) = src[idx..].chars().next() {
+            if c.is_whitespace() {
+                idx += c.len_utf8();
+                continue;
+            }
+
+            if c == '(' || c == ')' {
+                let token = if c == '(' { Token::Open } else { Token::Close };
+                self.tokens.push((token, (idx, 1).into()));
+                idx += 1;
+                continue;
+            }
+
+            let len = src[idx..]
+                .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                .unwrap_or(src.len() - idx);
+
+            let word = &src[idx..idx + len];
+            let end = idx + len;
+
+            let is_ident = word.chars().all(|c| c.is_ascii_alphabetic() || c == '_');
+
+            if is_ident && src[end..].starts_with('(') {
+                let close = self.find_close(end)?;
+
+                let token = Token::Call {
+                    name: word,
+                    name_span: (idx, len).into(),
+                    arg: &src[end + 1..close],
+                    arg_span: (end + 1, close - end - 1).into(),
+                };
+
+                self.tokens.push((token, (idx, close + 1 - idx).into()));
+                idx = close + 1;
+            } else {
+                self.tokens.push((Token::Word(word), (idx, len).into()));
+                idx = end;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turns the argument of a call into the JSON value of its pattern. JSON
+    /// objects are taken as they are, anything else is a string shorthand
+    /// whose words and quoted strings are joined together, so that
+    /// `#674 ~ "invoice"` reads as `#674~invoice`. Unquoted words are only
+    /// joined around an operator, anything else has to be quoted.
+    fn shorthand_to_json(
+        &self,
+        arg: &str,
+        arg_span: SourceSpan,
+    ) -> Result<serde_json::Value, QueryError> {
+        let trimmed = arg.trim();
+
+        if trimmed.starts_with('{') {
+            return serde_json::from_str(trimmed).map_err(|e| self.error(arg_span, e.to_string()));
+        }
+
+        let mut out = String::new();
+        let mut idx = 0;
+
+        // the last unquoted word, while only whitespace follows it
+        let mut last_word: Option<&str> = None;
+
+        while let Some(c) = arg[idx..].chars().next() {
+            let rest = &arg[idx..];
+
+            if c.is_whitespace() {
+                idx += c.len_utf8();
+            } else if c == '"' {
+                let mut de = serde_json::Deserializer::from_str(rest).into_iter::<String>();
+
+                let quoted = de
+                    .next()
+                    .ok_or_else(|| self.error(arg_span, "unclosed string"))?
+                    .map_err(|e| self.error(arg_span, e.to_string()))?;
+
+                out.push_str(&quoted);
+                idx += de.byte_offset();
+                last_word = None;
+            } else {
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || c == '"')
+                    .unwrap_or(rest.len());
+
+                let word = &rest[..len];
+
+                let is_joined = last_word.is_none_or(|x| {
+                    x.ends_with(SHORTHAND_OPERATORS) || word.starts_with(SHORTHAND_OPERATORS)
+                });
+
+                if !is_joined {
+                    return Err(self.error(
+                        (arg_span.offset() + idx, len),
+                        format!("unexpected `{word}`, quote arguments that contain spaces"),
+                    ));
+                }
+
+                out.push_str(word);
+                idx += len;
+                last_word = Some(word);
+            }
+        }
+
+        if out.is_empty() {
+            return Err(self.error(arg_span, "missing pattern argument"));
+        }
+
+        Ok(serde_json::Value::String(out))
+    }
+
+    fn peek(&self) -> Option<&Spanned<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some((Token::Word(x), _)) if *x == keyword => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_chain(
+        &mut self,
+        keyword: &str,
+        next: fn(&mut Self) -> Result<Predicate, QueryError>,
+        join: fn(Vec<Predicate>) -> Predicate,
+    ) -> Result<Predicate, QueryError> {
+        let mut items = vec![next(self)?];
+
+        while self.eat_keyword(keyword) {
+            items.push(next(self)?);
+        }
+
+        match items.len() {
+            1 => Ok(items.remove(0)),
+            _ => Ok(join(items)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Predicate, QueryError> {
+        self.parse_chain("or", Self::parse_and, Predicate::any_of)
+    }
+
+    fn parse_and(&mut self) -> Result<Predicate, QueryError> {
+        self.parse_chain("and", Self::parse_unary, Predicate::all_of)
+    }
+
+    fn parse_unary(&mut self) -> Result<Predicate, QueryError> {
+        if self.eat_keyword("not") {
+            return Ok(Predicate::not(self.parse_unary()?));
+        }
+
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Predicate, QueryError> {
+        let Some((token, span)) = self.tokens.get(self.pos) else {
+            return Err(self.error((self.src.len(), 0), "expected a pattern"));
+        };
+
+        let span = *span;
+        self.pos += 1;
+
+        match token {
+            Token::Open => {
+                let inner = self.parse_or()?;
+
+                match self.peek() {
+                    Some((Token::Close, _)) => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(self.error(span, "unclosed parenthesis")),
+                }
+            }
+            Token::Close => Err(self.error(span, "unexpected closing parenthesis")),
+            Token::Word(x) if matches!(*x, "and" | "or" | "not") => {
+                Err(self.error(span, format!("expected a pattern, found `{x}`")))
+            }
+            Token::Word(x) => Pattern::from_str(x)
+                .map(Predicate::from)
+                .map_err(|e| self.error(span, e.to_string())),
+            Token::Call {
+                name,
+                name_span,
+                arg,
+                arg_span,
+            } => {
+                let value = self.shorthand_to_json(arg, *arg_span)?;
+
+                let name = match *name {
+                    "addr" => "address",
+                    x => x,
+                };
+
+                let pattern = serde_json::json!({ name: value });
+
+                match serde_json::from_value::<Pattern>(pattern) {
+                    Ok(x) => Ok(x.into()),
+                    Err(e) if e.to_string().starts_with("unknown variant") => {
+                        Err(self.error(*name_span, e.to_string()))
+                    }
+                    Err(e) => Err(self.error(*arg_span, e.to_string())),
+                }
+            }
+        }
+    }
+}
+
+/// Parses a predicate from its textual syntax
+pub fn parse(src: &str) -> Result<Predicate, QueryError> {
+    let mut parser = Parser {
+        src,
+        tokens: vec![],
+        pos: 0,
+    };
+
+    parser.tokenize()?;
+
+    let predicate = parser.parse_or()?;
+
+    match parser.peek() {
+        None => Ok(predicate),
+        Some((Token::Close, span)) => Err(parser.error(*span, "unexpected closing parenthesis")),
+        Some((_, span)) => Err(parser.error(*span, "expected `and`, `or` or the end")),
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = serde_json::to_value(self).map_err(|_| fmt::Error)?;
+
+        let Some((name, arg)) = value.as_object().and_then(|x| x.iter().next()) else {
+            return Err(fmt::Error);
+        };
+
+        write!(f, "{name}({arg})")
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, items: &[StringOrStruct<Predicate>], sep| {
+            for (idx, item) in items.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, " {sep} ")?;
+                }
+
+                match item.deref() {
+                    Predicate::Match(x) => write!(f, "{}", x.deref())?,
+                    Predicate::Not(_) => write!(f, "{}", item.deref())?,
+                    x => write!(f, "({x})")?,
+                }
+            }
+
+            Ok(())
+        };
+
+        match self {
+            Predicate::Match(x) => write!(f, "{}", x.deref()),
+            Predicate::Not(x) => match x.deref().deref() {
+                Predicate::AnyOf(_) | Predicate::AllOf(_) => {
+                    write!(f, "not ({})", x.deref().deref())
+                }
+                x => write!(f, "not {x}"),
+            },
+            Predicate::AnyOf(x) => join(f, x, "or"),
+            Predicate::AllOf(x) => join(f, x, "and"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "addr1w8phkx6acpnf78fuvxn0mkew3l0fd058hzquvz7w36x4gtcyjy7wx";
+    const ASSET: &str = "asset17jd78wukhtrnmjh3fngzasxm8rck0l2r4hhyyt";
+
+    fn pattern(s: &str) -> Predicate {
+        Pattern::from_str(s).unwrap().into()
+    }
+
+    #[test]
+    fn precedence() {
+        let query =
+            format!(r#"addr({ADDRESS}) and not asset({ASSET}) or metadata(#674 ~ "invoice")"#);
+
+        let expected = Predicate::any_of(vec![
+            Predicate::all_of(vec![pattern(ADDRESS), Predicate::not(pattern(ASSET))]),
+            pattern("#674~invoice"),
+        ]);
+
+        assert_eq!(parse(&query).unwrap(), expected);
+    }
+
+    #[test]
+    fn groups_and_bare_patterns() {
+        let query = format!("#127 and ({ADDRESS} or not ({ASSET}))");
+
+        let expected = Predicate::all_of(vec![
+            pattern("#127"),
+            Predicate::any_of(vec![pattern(ADDRESS), Predicate::not(pattern(ASSET))]),
+        ]);
+
+        assert_eq!(parse(&query).unwrap(), expected);
+    }
+
+    #[test]
+    fn json_arguments() {
+        let query = r#"output({ "lovelace": { "gte": 1000000 } }) and metadata({ "label": 9980 })"#;
+
+        let output: Pattern =
+            serde_json::from_str(r#"{ "output": { "lovelace": { "gte": 1000000 } } }"#).unwrap();
+
+        let expected = Predicate::all_of(vec![output.into(), pattern("#9980")]);
+
+        assert_eq!(parse(query).unwrap(), expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        let predicate = Predicate::any_of(vec![
+            Predicate::all_of(vec![
+                pattern(ADDRESS),
+                Predicate::not(Predicate::any_of(vec![pattern(ASSET), pattern("#127")])),
+            ]),
+            pattern("#674.msg~(?i)invoice"),
+            Predicate::not(pattern("#721")),
+        ]);
+
+        let text = predicate.to_string();
+        assert_eq!(parse(&text).unwrap(), predicate);
+    }
+
+    #[test]
+    fn error_spans() {
+        let err = parse("#127 and foo(1)").unwrap_err();
+        assert_eq!(err.span, (9, 3).into());
+
+        let err = parse("#127 and (#9980 or #1").unwrap_err();
+        assert_eq!(err.span, (9, 1).into());
+
+        let err = parse("#127 or").unwrap_err();
+        assert_eq!(err.span, (7, 0).into());
+
+        let err = parse("#127 #9980").unwrap_err();
+        assert_eq!(err.span, (5, 5).into());
+
+        let err = parse(r#"output({ "lovelace": 1 })"#).unwrap_err();
+        assert_eq!(err.span, (7, 17).into());
+    }
+
+    #[test]
+    fn adjacent_words_in_call() {
+        let query = "metadata(#674 ~ hello world)";
+        let err = parse(query).unwrap_err();
+        assert_eq!(err.span, (22, 5).into());
+
+        let query = r#"metadata(#674 ~ "hello world")"#;
+        assert_eq!(parse(query).unwrap(), pattern("#674~hello world"));
+
+        let query = "metadata(#674 ~ (?i)invoice)";
+        assert_eq!(parse(query).unwrap(), pattern("#674~(?i)invoice"));
+    }
+
+    #[test]
+    fn bare_metadata_shorthand() {
+        for text in ["#674~(?i)invoice", "#674.msg~^hello world$", "#674~(a|b) c"] {
+            let predicate = Predicate::from_str(text).unwrap();
+            assert_eq!(predicate, pattern(text));
+        }
+
+        // a query starting with a label is still a query
+        let predicate = Predicate::from_str("#127 or #9980").unwrap();
+        assert_eq!(
+            predicate,
+            Predicate::any_of(vec![pattern("#127"), pattern("#9980")])
+        );
+    }
+}
@@ -0,0 +1,401 @@
+use pallas::codec::minicbor;
+use pallas::codec::utils::KeyValuePairs;
+use pallas::crypto::hash::{Hash, Hasher};
+use pallas::interop::utxorpc::spec::cardano::{script, Redeemer, Script};
+use pallas::ledger::primitives::conway;
+use pallas::ledger::traverse::{ComputeHash, OriginalHash};
+
+use self::serde_ext::FromBech32;
+
+use super::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScriptPattern {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<FlexBytes>,
+}
+
+impl FromBech32 for ScriptPattern {
+    fn from_bech32_parts(hrp: &str, content: Vec<u8>) -> Option<Self> {
+        match hrp {
+            "script" => Some(Self {
+                hash: Some(content.into()),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ScriptPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bech32(s)
+    }
+}
+
+/// Hash of a script as mapped into u5c. Native scripts lose their original
+/// cbor in the mapping, so their hash can't be computed.
+pub(super) fn u5c_script_hash(subject: &Script) -> Option<Hash<28>> {
+    match subject.script.as_ref()? {
+        script::Script::PlutusV1(x) => Some(Hasher::<224>::hash_tagged(x, 1)),
+        script::Script::PlutusV2(x) => Some(Hasher::<224>::hash_tagged(x, 2)),
+        script::Script::PlutusV3(x) => Some(Hasher::<224>::hash_tagged(x, 3)),
+        _ => None,
+    }
+}
+
+fn script_ref_hash(subject: &conway::ScriptRef) -> Hash<28> {
+    match subject {
+        conway::ScriptRef::NativeScript(x) => x.original_hash(),
+        conway::ScriptRef::PlutusV1Script(x) => x.compute_hash(),
+        conway::ScriptRef::PlutusV2Script(x) => x.compute_hash(),
+        conway::ScriptRef::PlutusV3Script(x) => x.compute_hash(),
+    }
+}
+
+/// Hashes of the scripts in the witness set and the reference scripts of the
+/// outputs, taken from the cbor
+fn raw_script_hashes(tx: &trv::MultiEraTx) -> Vec<Option<Hash<28>>> {
+    let a = tx.native_scripts().iter().map(|x| x.original_hash());
+    let b = tx.plutus_v1_scripts().iter().map(|x| x.compute_hash());
+    let c = tx.plutus_v2_scripts().iter().map(|x| x.compute_hash());
+    let d = tx.plutus_v3_scripts().iter().map(|x| x.compute_hash());
+
+    let e = tx
+        .outputs()
+        .iter()
+        .filter_map(|x| x.script_ref())
+        .map(|x| script_ref_hash(&x))
+        .collect::<Vec<_>>();
+
+    a.chain(b).chain(c).chain(d).chain(e).map(Some).collect()
+}
+
+/// Same as [raw_script_hashes], but from the u5c structs
+fn u5c_script_hashes(tx: &ParsedTx) -> Vec<Option<Hash<28>>> {
+    let a = tx.witnesses.iter().flat_map(|x| x.script.iter());
+    let b = tx.outputs.iter().flat_map(|x| x.script.iter());
+
+    a.chain(b).map(u5c_script_hash).collect()
+}
+
+impl PatternOf<TxSubject<'_>> for ScriptPattern {
+    fn is_match(&self, subject: TxSubject) -> MatchOutcome {
+        let mut hashes = match subject.raw {
+            Some(raw) => raw_script_hashes(raw),
+            None => u5c_script_hashes(subject.parsed),
+        };
+
+        // reference scripts of the reference inputs, only known once resolved
+        let resolved = subject
+            .parsed
+            .reference_inputs
+            .iter()
+            .flat_map(|x| x.as_output.as_ref())
+            .flat_map(|x| x.script.as_ref());
+
+        hashes.extend(resolved.map(u5c_script_hash));
+
+        let outcomes = hashes.iter().map(|x| match x {
+            Some(x) => self.hash.is_match(x.as_ref()),
+            None if self.hash.is_none() => MatchOutcome::Positive,
+            None => MatchOutcome::Uncertain,
+        });
+
+        MatchOutcome::fold_any_of(outcomes)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RedeemerPurpose {
+    Spend,
+    Mint,
+    Cert,
+    Reward,
+    Vote,
+    Propose,
+}
+
+impl PatternOf<RedeemerPurpose> for RedeemerPurpose {
+    fn is_match(&self, subject: RedeemerPurpose) -> MatchOutcome {
+        MatchOutcome::if_equal(self, &subject)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RedeemerPattern {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    purpose: Option<RedeemerPurpose>,
+
+    /// the hash of the redeemer data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<StringOrStruct<DatumPattern>>,
+}
+
+struct RedeemerSubject {
+    purpose: Option<RedeemerPurpose>,
+    data_hash: Option<Hash<32>>,
+}
+
+impl From<trv::MultiEraRedeemer<'_>> for RedeemerSubject {
+    fn from(value: trv::MultiEraRedeemer) -> Self {
+        let purpose = match value.tag() {
+            conway::RedeemerTag::Spend => RedeemerPurpose::Spend,
+            conway::RedeemerTag::Mint => RedeemerPurpose::Mint,
+            conway::RedeemerTag::Cert => RedeemerPurpose::Cert,
+            conway::RedeemerTag::Reward => RedeemerPurpose::Reward,
+            conway::RedeemerTag::Vote => RedeemerPurpose::Vote,
+            conway::RedeemerTag::Propose => RedeemerPurpose::Propose,
+        };
+
+        // re-encoded, only used when the original cbor can't be found
+        Self {
+            purpose: Some(purpose),
+            data_hash: Some(value.data().compute_hash()),
+        }
+    }
+}
+
+impl From<&Redeemer> for RedeemerSubject {
+    fn from(value: &Redeemer) -> Self {
+        let purpose = match value.purpose {
+            1 => Some(RedeemerPurpose::Spend),
+            2 => Some(RedeemerPurpose::Mint),
+            3 => Some(RedeemerPurpose::Cert),
+            4 => Some(RedeemerPurpose::Reward),
+            5 => Some(RedeemerPurpose::Vote),
+            6 => Some(RedeemerPurpose::Propose),
+            _ => None,
+        };
+
+        // the u5c payload is a re-shaped tree of the data, it can't be hashed
+        Self {
+            purpose,
+            data_hash: None,
+        }
+    }
+}
+
+/// The cbor of an item, as found in the source
+#[derive(Clone)]
+struct RawItem<'b>(&'b [u8]);
+
+impl<'b, C> minicbor::Decode<'b, C> for RawItem<'b> {
+    fn decode(d: &mut minicbor::Decoder<'b>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
+        let start = d.position();
+        d.skip()?;
+
+        Ok(RawItem(&d.input()[start..d.position()]))
+    }
+}
+
+/// Hashes of the redeemer data as encoded in the witness set, in the order of
+/// the redeemers. The data isn't kept raw once decoded, and re-encoding it
+/// doesn't always give back the original bytes.
+fn redeemer_data_hashes(witness_set: &[u8]) -> Option<Vec<Hash<32>>> {
+    let witness_set: KeyValuePairs<u64, RawItem> = minicbor::decode(witness_set).ok()?;
+
+    let Some((_, redeemers)) = witness_set.iter().find(|(k, _)| *k == 5) else {
+        return Some(vec![]);
+    };
+
+    // either a list of `[tag, index, data, ex_units]` or, since conway, a map
+    // of `[tag, index]` to `[data, ex_units]`
+    let data: Vec<RawItem> = match minicbor::decode::<Vec<Vec<RawItem>>>(redeemers.0) {
+        Ok(x) => x
+            .into_iter()
+            .map(|x| x.into_iter().nth(2))
+            .collect::<Option<_>>()?,
+        Err(_) => minicbor::decode::<KeyValuePairs<RawItem, Vec<RawItem>>>(redeemers.0)
+            .ok()?
+            .iter()
+            .map(|(_, x)| x.first().cloned())
+            .collect::<Option<_>>()?,
+    };
+
+    Some(data.iter().map(|x| Hasher::<256>::hash(x.0)).collect())
+}
+
+fn raw_redeemers(tx: &trv::MultiEraTx) -> Vec<RedeemerSubject> {
+    let witness_set = match tx {
+        trv::MultiEraTx::AlonzoCompatible(x, _) => Some(x.transaction_witness_set.raw_cbor()),
+        trv::MultiEraTx::Babbage(x) => Some(x.transaction_witness_set.raw_cbor()),
+        trv::MultiEraTx::Conway(x) => Some(x.transaction_witness_set.raw_cbor()),
+        _ => None,
+    };
+
+    let redeemers = tx.redeemers();
+
+    let hashes = witness_set
+        .and_then(redeemer_data_hashes)
+        .filter(|x| x.len() == redeemers.len());
+
+    redeemers
+        .into_iter()
+        .enumerate()
+        .map(|(idx, x)| {
+            let mut subject = RedeemerSubject::from(x);
+
+            if let Some(hashes) = &hashes {
+                subject.data_hash = Some(hashes[idx]);
+            }
+
+            subject
+        })
+        .collect()
+}
+
+/// Redeemers attached by the u5c mapping to the items they unlock
+fn u5c_redeemers(tx: &ParsedTx) -> impl Iterator<Item = &Redeemer> {
+    let a = tx.inputs.iter().flat_map(|x| x.redeemer.as_ref());
+    let b = tx.mint.iter().flat_map(|x| x.redeemer.as_ref());
+    let c = tx.withdrawals.iter().flat_map(|x| x.redeemer.as_ref());
+    let d = tx.certificates.iter().flat_map(|x| x.redeemer.as_ref());
+
+    a.chain(b).chain(c).chain(d)
+}
+
+impl PatternOf<&RedeemerSubject> for RedeemerPattern {
+    fn is_match(&self, subject: &RedeemerSubject) -> MatchOutcome {
+        let a = is_known_match(&self.purpose, subject.purpose);
+
+        let b = is_known_match(&self.data, subject.data_hash.as_ref().map(|x| x.as_ref()));
+
+        MatchOutcome::fold_all_of([a, b].into_iter())
+    }
+}
+
+impl PatternOf<TxSubject<'_>> for RedeemerPattern {
+    fn is_match(&self, subject: TxSubject) -> MatchOutcome {
+        let redeemers: Vec<RedeemerSubject> = match subject.raw {
+            Some(raw) => raw_redeemers(raw),
+            None => u5c_redeemers(subject.parsed).map(From::from).collect(),
+        };
+
+        self.is_any_match(redeemers.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::interop::utxorpc::spec::cardano::WitnessSet;
+
+    use super::*;
+
+    #[test]
+    fn script_match() {
+        let script = vec![0x4d, 0x01, 0x00, 0x00];
+        let hash = Hasher::<224>::hash_tagged(&script, 2);
+
+        let tx = ParsedTx {
+            witnesses: Some(WitnessSet {
+                script: vec![Script {
+                    script: Some(script::Script::PlutusV2(script.into())),
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let pattern = ScriptPattern {
+            hash: Some(hash.to_vec().into()),
+        };
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Positive
+        );
+
+        let pattern = ScriptPattern {
+            hash: Some(vec![0; 28].into()),
+        };
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Negative
+        );
+
+        let empty = ParsedTx::default();
+        assert_eq!(
+            ScriptPattern::default().is_match((&empty).into()),
+            MatchOutcome::Negative
+        );
+
+        assert_eq!(pattern.is_match((&empty).into()), MatchOutcome::Negative);
+    }
+
+    #[test]
+    fn redeemer_match() {
+        let tx = ParsedTx {
+            mint: vec![Multiasset {
+                redeemer: Some(Redeemer {
+                    purpose: 2,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let pattern = RedeemerPattern {
+            purpose: Some(RedeemerPurpose::Mint),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Positive
+        );
+
+        let pattern = RedeemerPattern {
+            purpose: Some(RedeemerPurpose::Spend),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Negative
+        );
+
+        // the data hash needs the cbor of the tx
+        let pattern = RedeemerPattern {
+            data: Some(StringOrStruct(
+                DatumPattern::from_str("datum1kthqfw4769ejpkx3h8le45yxaph5fmzdnur2s4").unwrap(),
+            )),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&tx)),
+            MatchOutcome::Uncertain
+        );
+
+        // a tx without redeemers can't match, whatever the pattern
+        let empty = ParsedTx::default();
+        assert_eq!(
+            pattern.is_match(TxSubject::from(&empty)),
+            MatchOutcome::Negative
+        );
+    }
+
+    #[test]
+    fn redeemer_data_original_hash() {
+        // the data is `1` encoded in two bytes, which re-encodes into one
+        let data = [0x18, 0x01];
+        let expected = Hasher::<256>::hash(&data);
+
+        // { 5: [[0, 0, data, [0, 0]]] }
+        let list = hex::decode("a105818400001801820000").unwrap();
+        assert_eq!(redeemer_data_hashes(&list), Some(vec![expected]));
+
+        // { 5: { [0, 0]: [data, [0, 0]] } }
+        let map = hex::decode("a105a1820000821801820000").unwrap();
+        assert_eq!(redeemer_data_hashes(&map), Some(vec![expected]));
+
+        // { 0: [] }
+        let none = hex::decode("a10080").unwrap();
+        assert_eq!(redeemer_data_hashes(&none), Some(vec![]));
+    }
+}
@@ -132,9 +132,11 @@ pub fn find_positive_test_vectors(predicate: impl Into<Predicate>) -> Vec<usize>
     subjects
         .into_iter()
         .enumerate()
-        .filter_map(|(idx, subject)| match eval_tx(&subject, &predicate) {
-            MatchOutcome::Positive => Some(idx),
-            _ => None,
-        })
+        .filter_map(
+            |(idx, subject)| match eval_tx((&subject).into(), None, &predicate) {
+                MatchOutcome::Positive => Some(idx),
+                _ => None,
+            },
+        )
         .collect()
 }
@@ -0,0 +1,445 @@
+use std::collections::HashSet;
+
+use pallas::crypto::hash::Hasher;
+use pallas::ledger::addresses::Address;
+
+use super::*;
+
+/// A bloom filter over byte strings. Trades a small rate of false positives
+/// for a fraction of the memory of the hash set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+    items: usize,
+}
+
+impl BloomFilter {
+    fn new(items: usize, false_positive_rate: f64) -> Self {
+        let n = items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+
+        let bits = (-n * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as usize;
+        let hashes = ((bits as f64 / n) * ln2).round().clamp(1.0, 16.0) as u32;
+
+        Self {
+            bits: vec![0; bits.div_ceil(64)],
+            hashes,
+            items,
+        }
+    }
+
+    /// Bit positions of an item, by double hashing a stable digest so that a
+    /// serialized filter stays valid across builds
+    fn positions(&self, item: &[u8]) -> impl Iterator<Item = usize> {
+        let digest = Hasher::<256>::hash(item);
+        let h1 = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(digest[8..16].try_into().unwrap());
+        let len = (self.bits.len() * 64) as u64;
+
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    fn insert(&mut self, item: &[u8]) {
+        for pos in self.positions(item).collect::<Vec<_>>() {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+    }
+
+    fn contains(&self, item: &[u8]) -> bool {
+        self.positions(item)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MemberSet {
+    Exact(HashSet<Vec<u8>>),
+    Bloom(BloomFilter),
+}
+
+impl Default for MemberSet {
+    fn default() -> Self {
+        Self::Exact(HashSet::new())
+    }
+}
+
+impl MemberSet {
+    fn contains(&self, item: &[u8]) -> bool {
+        match self {
+            MemberSet::Exact(x) => x.contains(item),
+            MemberSet::Bloom(x) => x.contains(item),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            MemberSet::Exact(x) => x.len(),
+            MemberSet::Bloom(x) => x.items,
+        }
+    }
+
+    fn build(items: Vec<Vec<u8>>, false_positive_rate: Option<f64>) -> Self {
+        match false_positive_rate {
+            Some(rate) => {
+                let mut bloom = BloomFilter::new(items.len(), rate);
+                items.iter().for_each(|x| bloom.insert(x));
+                MemberSet::Bloom(bloom)
+            }
+            None => MemberSet::Exact(items.into_iter().collect()),
+        }
+    }
+}
+
+/// The serialized form of a member set: either its entries or a bloom filter
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum MemberDef {
+    List(Vec<String>),
+    Bloom {
+        bloom: FlexBytes,
+        hashes: u32,
+        items: usize,
+    },
+}
+
+impl Default for MemberDef {
+    fn default() -> Self {
+        Self::List(vec![])
+    }
+}
+
+impl MemberDef {
+    fn is_empty(&self) -> bool {
+        matches!(self, MemberDef::List(x) if x.is_empty())
+    }
+
+    fn into_set(
+        self,
+        parse: fn(&str) -> anyhow::Result<Vec<u8>>,
+        false_positive_rate: Option<f64>,
+    ) -> anyhow::Result<MemberSet> {
+        match self {
+            MemberDef::List(entries) => {
+                let items = entries
+                    .iter()
+                    .map(|x| parse(x).map_err(|e| anyhow::anyhow!("{x}: {e}")))
+                    .collect::<Result<_, _>>()?;
+
+                Ok(MemberSet::build(items, false_positive_rate))
+            }
+            MemberDef::Bloom {
+                bloom,
+                hashes,
+                items,
+            } => {
+                if bloom.is_empty() || bloom.len() % 8 != 0 {
+                    anyhow::bail!("bloom filter must be a non-empty multiple of 8 bytes");
+                }
+
+                // without hashes every item would match
+                if !(1..=16).contains(&hashes) {
+                    anyhow::bail!("bloom filter hashes must be between 1 and 16");
+                }
+
+                let bits = bloom
+                    .chunks(8)
+                    .map(|x| x.try_into().map(u64::from_le_bytes))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(MemberSet::Bloom(BloomFilter {
+                    bits,
+                    hashes,
+                    items,
+                }))
+            }
+        }
+    }
+}
+
+impl From<MemberSet> for MemberDef {
+    fn from(value: MemberSet) -> Self {
+        match value {
+            MemberSet::Exact(x) => MemberDef::List(x.iter().map(hex::encode).collect()),
+            MemberSet::Bloom(x) => MemberDef::Bloom {
+                bloom: x
+                    .bits
+                    .iter()
+                    .flat_map(|x| x.to_le_bytes())
+                    .collect::<Vec<_>>()
+                    .into(),
+                hashes: x.hashes,
+                items: x.items,
+            },
+        }
+    }
+}
+
+fn parse_hash(s: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = hex::decode(s)?;
+
+    if bytes.len() != 28 {
+        anyhow::bail!("expected a 28 bytes hash");
+    }
+
+    Ok(bytes)
+}
+
+fn parse_address(s: &str) -> anyhow::Result<Vec<u8>> {
+    match hex::decode(s) {
+        Ok(x) => Ok(x),
+        Err(_) => Ok(Address::from_bech32(s)?.to_vec()),
+    }
+}
+
+fn parse_payment_part(s: &str) -> anyhow::Result<Vec<u8>> {
+    if let Ok(x) = parse_hash(s) {
+        return Ok(x);
+    }
+
+    match Address::from_bech32(s)? {
+        Address::Shelley(x) => Ok(x.payment().as_hash().to_vec()),
+        _ => anyhow::bail!("address has no payment part"),
+    }
+}
+
+fn delegation_hash(address: &Address) -> Option<Vec<u8>> {
+    match address {
+        Address::Shelley(x) => x.delegation().as_hash().map(|x| x.to_vec()),
+        Address::Stake(x) => Some(x.payload().as_hash().to_vec()),
+        Address::Byron(_) => None,
+    }
+}
+
+fn parse_delegation_part(s: &str) -> anyhow::Result<Vec<u8>> {
+    if let Ok(x) = parse_hash(s) {
+        return Ok(x);
+    }
+
+    delegation_hash(&Address::from_bech32(s)?)
+        .ok_or_else(|| anyhow::anyhow!("address has no delegation part"))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct WatchlistDef {
+    #[serde(default, skip_serializing_if = "MemberDef::is_empty")]
+    addresses: MemberDef,
+
+    #[serde(default, skip_serializing_if = "MemberDef::is_empty")]
+    payment_parts: MemberDef,
+
+    #[serde(default, skip_serializing_if = "MemberDef::is_empty")]
+    delegation_parts: MemberDef,
+
+    #[serde(default, skip_serializing_if = "MemberDef::is_empty")]
+    policies: MemberDef,
+
+    /// builds bloom filters with this rate of false positives instead of
+    /// hash sets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    false_positive_rate: Option<f64>,
+}
+
+/// Matches txs touching any member of large sets of addresses, credentials
+/// or policies, through a lookup per address or asset of the tx.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(try_from = "WatchlistDef", into = "WatchlistDef")]
+pub struct WatchlistPattern {
+    addresses: MemberSet,
+    payment_parts: MemberSet,
+    delegation_parts: MemberSet,
+    policies: MemberSet,
+}
+
+impl TryFrom<WatchlistDef> for WatchlistPattern {
+    type Error = anyhow::Error;
+
+    fn try_from(value: WatchlistDef) -> Result<Self, Self::Error> {
+        let rate = value.false_positive_rate;
+
+        if rate.is_some_and(|x| !(x > 0.0 && x < 1.0)) {
+            anyhow::bail!("false positive rate must be between 0 and 1");
+        }
+
+        Ok(Self {
+            addresses: value.addresses.into_set(parse_address, rate)?,
+            payment_parts: value.payment_parts.into_set(parse_payment_part, rate)?,
+            delegation_parts: value
+                .delegation_parts
+                .into_set(parse_delegation_part, rate)?,
+            policies: value.policies.into_set(parse_hash, rate)?,
+        })
+    }
+}
+
+impl From<WatchlistPattern> for WatchlistDef {
+    fn from(value: WatchlistPattern) -> Self {
+        Self {
+            addresses: value.addresses.into(),
+            payment_parts: value.payment_parts.into(),
+            delegation_parts: value.delegation_parts.into(),
+            policies: value.policies.into(),
+            false_positive_rate: None,
+        }
+    }
+}
+
+impl WatchlistPattern {
+    /// Number of members across all the sets
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+            + self.payment_parts.len()
+            + self.delegation_parts.len()
+            + self.policies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an address or stake address in bech32 to the exact sets. Returns
+    /// false if the entry isn't one of those or the sets are bloom filters.
+    pub fn try_insert(&mut self, entry: &str) -> bool {
+        let Ok(address) = Address::from_bech32(entry) else {
+            return false;
+        };
+
+        let (set, item) = match &address {
+            Address::Stake(_) => (&mut self.delegation_parts, delegation_hash(&address)),
+            _ => (&mut self.addresses, Some(address.to_vec())),
+        };
+
+        match (set, item) {
+            (MemberSet::Exact(set), Some(item)) => {
+                set.insert(item);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_address_match(&self, address: &[u8]) -> bool {
+        if self.addresses.contains(address) {
+            return true;
+        }
+
+        if self.payment_parts.len() == 0 && self.delegation_parts.len() == 0 {
+            return false;
+        }
+
+        let Ok(address) = Address::from_bytes(address) else {
+            return false;
+        };
+
+        let payment = match &address {
+            Address::Shelley(x) => self.payment_parts.contains(x.payment().as_hash().as_ref()),
+            _ => false,
+        };
+
+        let delegation =
+            delegation_hash(&address).is_some_and(|x| self.delegation_parts.contains(&x));
+
+        payment || delegation
+    }
+}
+
+impl PatternOf<TxSubject<'_>> for WatchlistPattern {
+    fn is_match(&self, subject: TxSubject) -> MatchOutcome {
+        let tx = subject.parsed;
+
+        let a = iter_tx_addresses(tx).any(|x| self.is_address_match(x));
+
+        let b = iter_tx_assets(tx).any(|x| self.policies.contains(&x.policy_id));
+
+        MatchOutcome::if_true(a || b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x";
+    const PAYMENT: &str = "9493315cd92eb5d8c4304e67b7e16ae36d61d34502694657811a2c8e";
+
+    fn watchlist(json: &str) -> Pattern {
+        Pattern::Watchlist(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn address_match() {
+        let pattern = watchlist(&format!(r#"{{ "addresses": ["{ADDRESS}"] }}"#));
+        let positives = testing::find_positive_test_vectors(pattern);
+        assert_eq!(positives, vec![1, 3]);
+
+        // the payment part is shared with an enterprise address of vector 2
+        let pattern = watchlist(&format!(r#"{{ "payment_parts": ["{PAYMENT}"] }}"#));
+        let positives = testing::find_positive_test_vectors(pattern);
+        assert_eq!(positives, vec![1, 2, 3]);
+
+        let pattern = watchlist(&format!(r#"{{ "delegation_parts": ["{ADDRESS}"] }}"#));
+        let positives = testing::find_positive_test_vectors(pattern);
+        assert_eq!(positives, vec![1, 3]);
+    }
+
+    #[test]
+    fn bloom_match() {
+        let members: Vec<_> = (0u8..200).map(|x| hex::encode([x; 28])).collect();
+
+        let def = WatchlistDef {
+            payment_parts: MemberDef::List(
+                members.iter().cloned().chain([PAYMENT.into()]).collect(),
+            ),
+            false_positive_rate: Some(0.001),
+            ..Default::default()
+        };
+
+        let pattern = WatchlistPattern::try_from(def).unwrap();
+        assert!(matches!(pattern.payment_parts, MemberSet::Bloom(_)));
+        assert_eq!(pattern.len(), 201);
+
+        for member in members.iter() {
+            assert!(pattern
+                .payment_parts
+                .contains(&hex::decode(member).unwrap()));
+        }
+
+        let positives = testing::find_positive_test_vectors(Pattern::Watchlist(pattern.clone()));
+        assert_eq!(positives, vec![1, 2, 3]);
+
+        // the filter survives a round trip through its serialized form
+        let json = serde_json::to_string(&pattern).unwrap();
+        let restored: WatchlistPattern = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, pattern);
+    }
+
+    #[test]
+    fn invalid_bloom() {
+        let parse = |bloom: &str, hashes: u32| {
+            serde_json::from_str::<WatchlistPattern>(&format!(
+                r#"{{ "policies": {{ "bloom": "{bloom}", "hashes": {hashes}, "items": 1 }} }}"#
+            ))
+        };
+
+        assert!(parse("0000000000000000", 3).is_ok());
+
+        assert!(parse("0000000000000000", 0).is_err());
+        assert!(parse("0000000000000000", 17).is_err());
+        assert!(parse("", 3).is_err());
+        assert!(parse("000000", 3).is_err());
+    }
+
+    #[test]
+    fn insert_entries() {
+        let mut pattern = WatchlistPattern::default();
+
+        assert!(pattern.try_insert(ADDRESS));
+        assert!(pattern.try_insert("stake178phkx6acpnf78fuvxn0mkew3l0fd058hzquvz7w36x4gtcccycj5"));
+        assert!(!pattern.try_insert("asset17jd78wukhtrnmjh3fngzasxm8rck0l2r4hhyyt"));
+        assert!(!pattern.try_insert("#127"));
+
+        assert_eq!(pattern.len(), 2);
+    }
+}
@@ -1,81 +1,428 @@
 use gasket::framework::*;
+use pallas::ledger::traverse as trv;
 use serde::Deserialize;
-use tracing::info;
+use tokio::select;
+use tracing::{info, warn};
 
 use crate::framework::*;
 
 use self::eval::{MatchOutcome, Predicate, StringOrStruct};
 
+pub mod dead_letter;
 pub mod eval;
+pub mod source;
+
+use self::dead_letter::{DeadLetter, DeadLetterConfig};
+use self::source::PredicateSource;
+
+/// Keeps only the txs of a block flagged in `keep`, either rewriting the block
+/// or splitting the txs out as individual records.
+fn prune_block(
+    mapper: &eval::Mapper,
+    record: &Record,
+    keep: &[bool],
+    mode: BlockMode,
+) -> Result<Vec<Record>, WorkerError> {
+    let is_kept = |idx: usize| keep.get(idx).copied().unwrap_or_default();
+
+    let mut block = match record {
+        Record::CborBlock(cbor) if mode == BlockMode::Split => {
+            let block = trv::MultiEraBlock::decode(cbor).or_panic()?;
+
+            let out = block
+                .txs()
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| is_kept(*idx))
+                .map(|(_, tx)| Record::CborTx(tx.encode()))
+                .collect();
+
+            return Ok(out);
+        }
+        // the cbor of a block can't be pruned without invalidating its body
+        // hashes, so the pruned block is emitted in its parsed form
+        Record::CborBlock(cbor) => {
+            let block = trv::MultiEraBlock::decode(cbor).or_panic()?;
+            mapper.map_block(&block)
+        }
+        Record::ParsedBlock(block) => block.clone(),
+        x => return Ok(vec![x.clone()]),
+    };
+
+    let txs: Vec<_> = block
+        .body
+        .as_mut()
+        .map(|x| std::mem::take(&mut x.tx))
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| is_kept(*idx))
+        .map(|(_, tx)| tx)
+        .collect();
+
+    match mode {
+        BlockMode::Split => Ok(txs.into_iter().map(Record::ParsedTx).collect()),
+        _ => {
+            if let Some(body) = block.body.as_mut() {
+                body.tx = txs;
+            }
+
+            Ok(vec![Record::ParsedBlock(block)])
+        }
+    }
+}
+
+/// The records to emit for an event, and whether the predicate couldn't decide
+/// on some of it
+#[derive(Default)]
+pub struct Selection {
+    records: Vec<Record>,
+    uncertain: bool,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum Unit {
+    Event(ChainEvent),
+    Reload,
+}
 
 #[derive(Stage)]
-#[stage(name = "select", unit = "ChainEvent", worker = "Worker")]
+#[stage(name = "select", unit = "Unit", worker = "Worker")]
 pub struct Stage {
+    /// the predicate set in the config, combined with the loaded one
+    base: Option<Predicate>,
     predicate: Predicate,
     skip_uncertain: bool,
+    block_mode: BlockMode,
+    source: Option<PredicateSource>,
+    dead_letter: Option<DeadLetterConfig>,
+    mapper: eval::Mapper,
 
     pub input: FilterInputPort,
     pub output: FilterOutputPort,
 
+    reload: Option<gasket::messaging::TimerPort>,
+
     #[metric]
     ops_count: gasket::metrics::Counter,
+
+    #[metric]
+    reload_count: gasket::metrics::Counter,
+
+    #[metric]
+    predicate_size: gasket::metrics::Gauge,
+
+    #[metric]
+    uncertain_count: gasket::metrics::Counter,
 }
 
-#[derive(Default)]
-pub struct Worker;
+impl Stage {
+    fn is_selected(&self, outcome: MatchOutcome) -> Result<bool, WorkerError> {
+        match outcome {
+            MatchOutcome::Positive => Ok(true),
+            MatchOutcome::Negative => Ok(false),
+            MatchOutcome::Uncertain if self.skip_uncertain => Ok(false),
+            MatchOutcome::Uncertain if self.dead_letter.is_some() => Ok(false),
+            MatchOutcome::Uncertain => Err(WorkerError::Panic),
+        }
+    }
+
+    fn select(&self, record: &Record) -> Result<Selection, WorkerError> {
+        let tx_outcomes = match self.block_mode {
+            BlockMode::Whole => None,
+            // block patterns alone select the whole block, there's nothing to prune
+            _ if self.predicate.is_block_level() => None,
+            _ => eval::eval_block_txs(&self.mapper, record, &self.predicate),
+        };
+
+        // a block without txs has nothing to prune, but block patterns can
+        // still select it as a whole
+        let tx_outcomes = match tx_outcomes {
+            Some(x) if x.is_empty() && self.block_mode == BlockMode::Prune => None,
+            x => x,
+        };
+
+        let Some(tx_outcomes) = tx_outcomes else {
+            let is_match = eval::eval(&self.mapper, record, &self.predicate);
+
+            let records = match self.is_selected(is_match)? {
+                true => vec![record.clone()],
+                false => vec![],
+            };
+
+            return Ok(Selection {
+                records,
+                uncertain: is_match == MatchOutcome::Uncertain,
+            });
+        };
+
+        let uncertain = tx_outcomes.contains(&MatchOutcome::Uncertain);
+
+        let keep = tx_outcomes
+            .into_iter()
+            .map(|x| self.is_selected(x))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !keep.contains(&true) {
+            return Ok(Selection {
+                records: vec![],
+                uncertain,
+            });
+        }
+
+        let records = prune_block(&self.mapper, record, &keep, self.block_mode)?;
+
+        Ok(Selection { records, uncertain })
+    }
+
+    /// Explains an uncertain outcome by the patterns that couldn't be
+    /// evaluated against the record
+    fn uncertain_reason(&self, record: &Record) -> String {
+        let patterns = eval::uncertain_patterns(&self.mapper, record, &self.predicate);
+
+        if patterns.is_empty() {
+            return "the record can't be evaluated".into();
+        }
+
+        let patterns: Vec<_> = patterns.iter().map(|x| format!("`{x}`")).collect();
+
+        format!("can't evaluate {}", patterns.join(", "))
+    }
+
+    /// Swaps in a freshly loaded predicate. Events are processed one at a
+    /// time, so each of them is evaluated against either the old or the new
+    /// predicate as a whole.
+    fn swap_predicate(&mut self, loaded: Predicate, size: usize) {
+        self.predicate = match self.base.clone() {
+            Some(base) => Predicate::all_of(vec![base, loaded]),
+            None => loaded,
+        };
+
+        self.predicate_size.set(size as i64);
+        self.reload_count.inc(1);
+    }
+}
+
+pub struct Worker {
+    client: reqwest::Client,
+    loaded: bool,
+    modified: Option<std::time::SystemTime>,
+    dead_letter: Option<DeadLetter>,
+}
+
+impl Worker {
+    async fn reload(&mut self, stage: &mut Stage) -> Result<(), WorkerError> {
+        let Some(source) = &stage.source else {
+            return Ok(());
+        };
+
+        let modified = source.modified().await;
+
+        if self.loaded && source.watch && modified == self.modified {
+            return Ok(());
+        }
+
+        match source.load(&self.client).await {
+            Ok((predicate, size)) => {
+                info!(size, "predicate loaded from source");
+                stage.swap_predicate(predicate, size);
+                self.modified = modified;
+                self.loaded = true;
+            }
+            // the first load must succeed, later ones keep the current predicate
+            Err(err) if self.loaded => warn!(%err, "can't reload predicate from source"),
+            Err(err) => {
+                tracing::error!(%err, "can't load predicate from source");
+                return Err(WorkerError::Panic);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts the uncertain event and sends it to the dead letter, if set
+    async fn dead_letter(
+        &mut self,
+        event: &ChainEvent,
+        record: &Record,
+        stage: &Stage,
+    ) -> Result<(), WorkerError> {
+        stage.uncertain_count.inc(1);
+
+        let Some(dead_letter) = self.dead_letter.as_mut() else {
+            return Ok(());
+        };
+
+        let reason = stage.uncertain_reason(record);
+        warn!(%reason, "uncertain event sent to the dead letter");
+
+        dead_letter.send(event, &reason).await
+    }
+}
 
 #[async_trait::async_trait(?Send)]
 impl gasket::framework::Worker<Stage> for Worker {
-    async fn bootstrap(_: &Stage) -> Result<Self, WorkerError> {
-        Ok(Default::default())
+    async fn bootstrap(stage: &Stage) -> Result<Self, WorkerError> {
+        let client = match &stage.source {
+            Some(x) => x.build_client().or_panic()?,
+            None => reqwest::Client::new(),
+        };
+
+        let dead_letter = stage
+            .dead_letter
+            .as_ref()
+            .map(DeadLetter::open)
+            .transpose()?;
+
+        Ok(Self {
+            client,
+            loaded: stage.source.is_none(),
+            modified: None,
+            dead_letter,
+        })
     }
 
-    async fn schedule(
-        &mut self,
-        stage: &mut Stage,
-    ) -> Result<WorkSchedule<ChainEvent>, WorkerError> {
-        let msg = stage.input.recv().await.or_panic()?;
+    async fn schedule(&mut self, stage: &mut Stage) -> Result<WorkSchedule<Unit>, WorkerError> {
+        if !self.loaded {
+            return Ok(WorkSchedule::Unit(Unit::Reload));
+        }
 
-        Ok(WorkSchedule::Unit(msg.payload))
+        let Some(reload) = stage.reload.as_mut() else {
+            let msg = stage.input.recv().await.or_panic()?;
+            return Ok(WorkSchedule::Unit(Unit::Event(msg.payload)));
+        };
+
+        select! {
+            msg = stage.input.recv() => {
+                let msg = msg.or_panic()?;
+                Ok(WorkSchedule::Unit(Unit::Event(msg.payload)))
+            }
+            msg = reload.recv() => {
+                msg.or_panic()?;
+                Ok(WorkSchedule::Unit(Unit::Reload))
+            }
+        }
     }
 
-    async fn execute(&mut self, unit: &ChainEvent, stage: &mut Stage) -> Result<(), WorkerError> {
-        let is_match = match unit {
-            ChainEvent::Apply(_, r) => eval::eval(r, &stage.predicate),
-            ChainEvent::Undo(_, r) => eval::eval(r, &stage.predicate),
-            ChainEvent::Reset(_) => MatchOutcome::Positive,
+    async fn execute(&mut self, unit: &Unit, stage: &mut Stage) -> Result<(), WorkerError> {
+        let unit = match unit {
+            Unit::Event(x) => x,
+            Unit::Reload => return self.reload(stage).await,
         };
 
-        match is_match {
-            MatchOutcome::Positive => stage.output.send(unit.clone().into()).await.or_panic()?,
-            MatchOutcome::Negative => (),
-            MatchOutcome::Uncertain => {
-                if !stage.skip_uncertain {
-                    return Err(WorkerError::Panic);
+        let selection = match unit {
+            ChainEvent::Apply(_, r, _) | ChainEvent::Undo(_, r, _) => {
+                let selection = stage.select(r)?;
+
+                if selection.uncertain {
+                    self.dead_letter(unit, r, stage).await?;
                 }
+
+                selection
             }
+            ChainEvent::Reset(_) => Selection::default(),
+        };
+
+        let selected = match unit {
+            ChainEvent::Apply(p, ..) => selection
+                .records
+                .into_iter()
+                .map(|x| ChainEvent::Apply(p.clone(), x, None))
+                .collect(),
+            ChainEvent::Undo(p, ..) => selection
+                .records
+                .into_iter()
+                .map(|x| ChainEvent::Undo(p.clone(), x, None))
+                .collect(),
+            ChainEvent::Reset(_) => vec![unit.clone()],
         };
 
+        for event in selected {
+            stage.output.send(event.into()).await.or_panic()?;
+        }
+
         stage.ops_count.inc(1);
 
         Ok(())
     }
 }
 
+/// What to emit when some of the txs of a block match the predicate
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockMode {
+    /// emit the whole block
+    #[default]
+    Whole,
+
+    /// emit the block keeping only the matching txs
+    Prune,
+
+    /// emit each matching tx as its own record
+    Split,
+}
+
 #[derive(Deserialize)]
 pub struct Config {
-    pub predicate: StringOrStruct<Predicate>,
+    pub predicate: Option<StringOrStruct<Predicate>>,
+
+    #[serde(default)]
     pub skip_uncertain: bool,
+
+    #[serde(default)]
+    pub block_mode: BlockMode,
+
+    /// an external predicate, combined with `predicate` when both are set
+    pub source: Option<PredicateSource>,
+
+    /// where to send the events the predicate can't decide on, instead of
+    /// stopping the pipeline
+    pub dead_letter: Option<DeadLetterConfig>,
 }
 
 impl Config {
-    pub fn bootstrapper(self, _ctx: &Context) -> Result<Stage, Error> {
+    pub fn bootstrapper(mut self, ctx: &Context) -> Result<Stage, Error> {
         info!(predicate = ?self.predicate, "selection filter predicate");
 
+        let base = self.predicate.map(StringOrStruct::unwrap);
+
+        if let Some(source) = &mut self.source {
+            source.validate()?;
+            source.resolve_path(&ctx.current_dir);
+        }
+
+        if let Some(dead_letter) = &mut self.dead_letter {
+            dead_letter.resolve_path(&ctx.current_dir);
+        }
+
+        // until the source is loaded, nothing is selected
+        let predicate = match (&base, &self.source) {
+            (_, Some(_)) => Predicate::any_of(vec![]),
+            (Some(x), None) => x.clone(),
+            (None, None) => {
+                return Err(Error::config("select filter needs a predicate or a source"))
+            }
+        };
+
+        let reload = self
+            .source
+            .as_ref()
+            .and_then(|x| x.check_interval())
+            .map(gasket::messaging::TimerPort::from_secs);
+
         let stage = Stage {
-            predicate: self.predicate.unwrap(),
+            base,
+            predicate,
             skip_uncertain: self.skip_uncertain,
+            block_mode: self.block_mode,
+            source: self.source,
+            dead_letter: self.dead_letter,
+            mapper: Default::default(),
+            reload,
             ops_count: Default::default(),
+            reload_count: Default::default(),
+            predicate_size: Default::default(),
+            uncertain_count: Default::default(),
             input: Default::default(),
             output: Default::default(),
         };
@@ -83,3 +430,143 @@ impl Config {
         Ok(stage)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use pallas::interop::utxorpc::spec::cardano::{BlockBody, VKeyWitness, WitnessSet};
+
+    use super::*;
+
+    fn stage(predicate: Predicate, block_mode: BlockMode) -> Stage {
+        Stage {
+            base: None,
+            predicate,
+            skip_uncertain: false,
+            block_mode,
+            source: None,
+            dead_letter: None,
+            mapper: Default::default(),
+            reload: None,
+            ops_count: Default::default(),
+            reload_count: Default::default(),
+            predicate_size: Default::default(),
+            uncertain_count: Default::default(),
+            input: Default::default(),
+            output: Default::default(),
+        }
+    }
+
+    fn select(predicate: &str, block_mode: BlockMode) -> Vec<Record> {
+        let stage = stage(Predicate::from_str(predicate).unwrap(), block_mode);
+
+        let block = Record::ParsedBlock(ParsedBlock {
+            body: Some(BlockBody {
+                tx: eval::testing::test_vectors(),
+            }),
+            ..Default::default()
+        });
+
+        stage.select(&block).unwrap().records
+    }
+
+    fn block_txs(record: &Record) -> &[ParsedTx] {
+        match record {
+            Record::ParsedBlock(x) => &x.body.as_ref().unwrap().tx,
+            _ => panic!("expected a parsed block"),
+        }
+    }
+
+    #[test]
+    fn whole_block_mode() {
+        let output = select("#127", BlockMode::Whole);
+        assert_eq!(output.len(), 1);
+        assert_eq!(block_txs(&output[0]).len(), 4);
+
+        assert!(select("#404", BlockMode::Whole).is_empty());
+    }
+
+    #[test]
+    fn prune_block_mode() {
+        let output = select("#127", BlockMode::Prune);
+        assert_eq!(output.len(), 1);
+
+        let expected = eval::testing::test_vectors();
+        assert_eq!(block_txs(&output[0]), &expected[1..3]);
+
+        assert!(select("#404", BlockMode::Prune).is_empty());
+    }
+
+    #[test]
+    fn split_block_mode() {
+        let output = select("#9980", BlockMode::Split);
+
+        let expected = eval::testing::test_vectors();
+        assert!(
+            matches!(&output[..], [Record::ParsedTx(a), Record::ParsedTx(b)] if a == &expected[1] && b == &expected[3])
+        );
+    }
+
+    #[test]
+    fn swap_loaded_predicate() {
+        let mut stage = stage(Predicate::any_of(vec![]), BlockMode::Whole);
+        stage.base = Some(Predicate::from_str("#127").unwrap());
+
+        let txs = eval::testing::test_vectors();
+        let record = Record::ParsedTx(txs[1].clone());
+
+        // nothing is selected until a predicate is loaded
+        assert!(stage.select(&record).unwrap().records.is_empty());
+
+        let (loaded, size) = source::parse("#9980", source::SourceFormat::Set).unwrap();
+        stage.swap_predicate(loaded, size);
+        assert_eq!(stage.select(&record).unwrap().records.len(), 1);
+
+        // the config predicate still applies on top of the loaded one
+        let record = Record::ParsedTx(txs[3].clone());
+        assert!(stage.select(&record).unwrap().records.is_empty());
+    }
+
+    #[test]
+    fn uncertain_to_dead_letter() {
+        let predicate = Predicate::from_str(r#"#9980 or vote({ "vote": "yes" })"#).unwrap();
+
+        let mut stage = stage(predicate, BlockMode::Whole);
+        stage.dead_letter = Some(DeadLetterConfig::File {
+            path: "uncertain.jsonl".into(),
+        });
+
+        let txs = eval::testing::test_vectors();
+
+        // votes are unknown without the cbor, unless another pattern matches
+        let witnessed = ParsedTx {
+            witnesses: Some(WitnessSet {
+                vkeywitness: vec![VKeyWitness::default()],
+                ..Default::default()
+            }),
+            ..txs[0].clone()
+        };
+
+        let record = Record::ParsedTx(witnessed);
+        let selection = stage.select(&record).unwrap();
+        assert!(selection.uncertain);
+        assert!(selection.records.is_empty());
+
+        assert_eq!(
+            stage.uncertain_reason(&record),
+            r#"can't evaluate `vote({"vote":"yes"})`"#
+        );
+
+        let record = Record::ParsedTx(txs[1].clone());
+        let selection = stage.select(&record).unwrap();
+        assert!(!selection.uncertain);
+        assert_eq!(selection.records.len(), 1);
+
+        let record = Record::GenericJson(serde_json::json!({}));
+        assert_eq!(
+            stage.uncertain_reason(&record),
+            "the record can't be evaluated"
+        );
+    }
+}
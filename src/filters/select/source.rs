@@ -0,0 +1,279 @@
+//! Loading of predicates from an external file or url, so that they can be
+//! refreshed without restarting the pipeline.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+
+use crate::framework::Error;
+use crate::sinks::common::web::APP_USER_AGENT;
+
+use super::eval::{Pattern, Predicate, WatchlistPattern};
+
+/// How often a watched file is polled for changes
+const WATCH_INTERVAL: u64 = 1;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+    /// a single predicate, either as JSON or in the query syntax
+    #[default]
+    Predicate,
+
+    /// a list of patterns, matching if any of them does. Either a JSON array
+    /// of strings or one pattern per line.
+    Set,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PredicateSource {
+    pub path: Option<PathBuf>,
+    pub url: Option<String>,
+
+    #[serde(default)]
+    pub format: SourceFormat,
+
+    /// seconds between reloads of the source, loaded only once if absent
+    pub refresh_interval: Option<u64>,
+
+    /// poll the modification time of the file and reload it when it changes
+    #[serde(default)]
+    pub watch: bool,
+
+    /// timeout in milliseconds of the http requests
+    pub timeout: Option<u64>,
+}
+
+impl PredicateSource {
+    pub fn validate(&self) -> Result<(), Error> {
+        match (&self.path, &self.url) {
+            (Some(_), Some(_)) => Err(Error::config(
+                "predicate source needs either a path or a url, not both",
+            )),
+            (None, None) => Err(Error::config("predicate source needs a path or a url")),
+            (None, Some(_)) if self.watch => {
+                Err(Error::config("only file predicate sources can be watched"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolves a relative path against the directory of the daemon
+    pub fn resolve_path(&mut self, current_dir: &Path) {
+        self.path = self.path.as_ref().map(|x| current_dir.join(x));
+    }
+
+    /// Seconds between the checks of the source, if it needs to be checked
+    pub fn check_interval(&self) -> Option<u64> {
+        match self.watch {
+            true => Some(WATCH_INTERVAL),
+            false => self.refresh_interval,
+        }
+    }
+
+    pub fn build_client(&self) -> Result<reqwest::Client, reqwest::Error> {
+        reqwest::ClientBuilder::new()
+            .user_agent(APP_USER_AGENT)
+            .timeout(Duration::from_millis(self.timeout.unwrap_or(30000)))
+            .build()
+    }
+
+    /// Modification time of the source file. Changes of the file are detected
+    /// by comparing it against the one of the last load.
+    pub async fn modified(&self) -> Option<SystemTime> {
+        let path = self.path.as_ref()?;
+        let metadata = tokio::fs::metadata(path).await.ok()?;
+        metadata.modified().ok()
+    }
+
+    async fn fetch(&self, client: &reqwest::Client) -> Result<String, Error> {
+        if let Some(path) = &self.path {
+            return tokio::fs::read_to_string(path).await.map_err(Error::custom);
+        }
+
+        let url = self.url.as_deref().unwrap_or_default();
+
+        client
+            .get(url)
+            .send()
+            .await
+            .and_then(|x| x.error_for_status())
+            .map_err(Error::custom)?
+            .text()
+            .await
+            .map_err(Error::custom)
+    }
+
+    /// Fetches and parses the predicate, returning it along with its size
+    pub async fn load(&self, client: &reqwest::Client) -> Result<(Predicate, usize), Error> {
+        let content = self.fetch(client).await?;
+        parse(&content, self.format)
+    }
+}
+
+/// Number of patterns in a predicate
+fn count_patterns(predicate: &Predicate) -> usize {
+    match predicate {
+        // a watchlist stands for each of its members
+        Predicate::Match(x) => match x.deref() {
+            Pattern::Watchlist(x) => x.len(),
+            _ => 1,
+        },
+        Predicate::Not(x) => count_patterns(x),
+        Predicate::AnyOf(x) => x.iter().map(|x| count_patterns(x)).sum(),
+        Predicate::AllOf(x) => x.iter().map(|x| count_patterns(x)).sum(),
+    }
+}
+
+/// Parses the entries of a set. Addresses and stake addresses are indexed in
+/// a watchlist, any other pattern is evaluated on its own.
+fn parse_set(content: &str) -> Result<(Vec<Predicate>, usize), Error> {
+    let entries: Vec<String> = match content.trim_start().starts_with('[') {
+        true => serde_json::from_str(content).map_err(Error::parse)?,
+        false => content
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect(),
+    };
+
+    let mut watchlist = WatchlistPattern::default();
+
+    let mut predicates = entries
+        .iter()
+        .filter(|x| !watchlist.try_insert(x))
+        .map(|x| {
+            Pattern::from_str(x)
+                .map(Predicate::from)
+                .map_err(|e| Error::parse(format!("{x}: {e}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if !watchlist.is_empty() {
+        predicates.push(Pattern::Watchlist(watchlist).into());
+    }
+
+    Ok((predicates, entries.len()))
+}
+
+pub fn parse(content: &str, format: SourceFormat) -> Result<(Predicate, usize), Error> {
+    match format {
+        SourceFormat::Predicate if content.trim_start().starts_with('{') => {
+            let predicate = serde_json::from_str(content).map_err(Error::parse)?;
+            let size = count_patterns(&predicate);
+            Ok((predicate, size))
+        }
+        SourceFormat::Predicate => {
+            let predicate = Predicate::from_str(content.trim()).map_err(Error::parse)?;
+            let size = count_patterns(&predicate);
+            Ok((predicate, size))
+        }
+        SourceFormat::Set => {
+            let (predicates, size) = parse_set(content)?;
+            Ok((Predicate::any_of(predicates), size))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "addr1w8phkx6acpnf78fuvxn0mkew3l0fd058hzquvz7w36x4gtcyjy7wx";
+    const ASSET: &str = "asset17jd78wukhtrnmjh3fngzasxm8rck0l2r4hhyyt";
+
+    #[test]
+    fn parse_set_lines() {
+        let content = format!("{ADDRESS}\n\n  {ASSET}\n#127\n");
+
+        let (predicate, size) = parse(&content, SourceFormat::Set).unwrap();
+        assert_eq!(size, 3);
+
+        let mut watchlist = WatchlistPattern::default();
+        watchlist.try_insert(ADDRESS);
+
+        // addresses are indexed, other patterns evaluated one by one
+        let expected = Predicate::any_of(vec![
+            Predicate::from_str(ASSET).unwrap(),
+            Predicate::from_str("#127").unwrap(),
+            Pattern::Watchlist(watchlist).into(),
+        ]);
+        assert_eq!(predicate, expected);
+
+        let json = format!(r#"["{ADDRESS}", "{ASSET}"]"#);
+        let (_, size) = parse(&json, SourceFormat::Set).unwrap();
+        assert_eq!(size, 2);
+
+        assert!(parse("not-a-pattern", SourceFormat::Set).is_err());
+    }
+
+    #[test]
+    fn count_watchlist_members() {
+        let json = format!(
+            r#"{{ "match": {{ "watchlist": {{ "addresses": ["{ADDRESS}"], "policies": ["{}"] }} }} }}"#,
+            "d894897411707efa755a76deb66d26dfd50593f2e70863e1661e98a0"
+        );
+
+        let (_, size) = parse(&json, SourceFormat::Predicate).unwrap();
+        assert_eq!(size, 2);
+    }
+
+    #[test]
+    fn parse_predicate() {
+        let (predicate, size) = parse(
+            &format!("{ADDRESS} and not #127\n"),
+            SourceFormat::Predicate,
+        )
+        .unwrap();
+        assert_eq!(size, 2);
+        assert!(matches!(predicate, Predicate::AllOf(_)));
+
+        let json = format!(r##"{{ "any": ["{ADDRESS}", "#127", "#9980"] }}"##);
+        let (predicate, size) = parse(&json, SourceFormat::Predicate).unwrap();
+        assert_eq!(size, 3);
+        assert!(matches!(predicate, Predicate::AnyOf(_)));
+    }
+
+    #[test]
+    fn file_changes() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+
+        let source = PredicateSource {
+            path: Some(file.path().to_owned()),
+            url: None,
+            format: SourceFormat::Set,
+            refresh_interval: None,
+            watch: true,
+            timeout: None,
+        };
+
+        source.validate().unwrap();
+        assert_eq!(source.check_interval(), Some(WATCH_INTERVAL));
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        assert!(runtime.block_on(source.modified()).is_some());
+
+        let mut relative = PredicateSource {
+            path: Some("watchlist.txt".into()),
+            ..source.clone()
+        };
+
+        relative.resolve_path(Path::new("/etc/oura"));
+        assert_eq!(relative.path, Some("/etc/oura/watchlist.txt".into()));
+
+        let url = PredicateSource {
+            path: None,
+            url: Some("http://localhost".into()),
+            ..source.clone()
+        };
+
+        assert!(url.validate().is_err());
+    }
+}
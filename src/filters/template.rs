@@ -0,0 +1,278 @@
+//! A filter that renders each record through a Handlebars template
+
+use gasket::framework::*;
+use handlebars::{handlebars_helper, Handlebars};
+use pallas::network::miniprotocols::Point;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::framework::*;
+
+handlebars_helper!(to_hex: |x: str| hex::encode(x));
+
+handlebars_helper!(from_hex: |x: str| hex::decode(x)
+    .ok()
+    .and_then(|x| String::from_utf8(x).ok()));
+
+handlebars_helper!(to_bech32: |hrp: str, x: str| encode_bech32(hrp, x));
+
+handlebars_helper!(from_bech32: |x: str| bech32::decode(x)
+    .ok()
+    .map(|(_, data)| hex::encode(data)));
+
+handlebars_helper!(lovelace_to_ada: |x: Json| json_to_u64(x).map(format_ada));
+
+fn encode_bech32(hrp: &str, hex: &str) -> Option<String> {
+    let hrp = bech32::Hrp::parse(hrp).ok()?;
+    let data = hex::decode(hex).ok()?;
+    bech32::encode::<bech32::Bech32>(hrp, &data).ok()
+}
+
+/// Reads an unsigned quantity from either a JSON number or a numeric string
+fn json_to_u64(value: &JsonValue) -> Option<u64> {
+    match value {
+        JsonValue::Number(x) => x.as_u64(),
+        JsonValue::String(x) => x.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Formats a lovelace amount as ADA without going through floating point
+fn format_ada(lovelace: u64) -> String {
+    format!("{}.{:06}", lovelace / 1_000_000, lovelace % 1_000_000)
+}
+
+struct SlotToTimestamp(GenesisValues);
+
+impl handlebars::HelperDef for SlotToTimestamp {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &handlebars::Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc handlebars::Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+    ) -> Result<handlebars::ScopedJson<'rc>, handlebars::RenderError> {
+        let slot = h
+            .param(0)
+            .and_then(|x| json_to_u64(x.value()))
+            .ok_or_else(|| {
+                handlebars::RenderErrorReason::ParamTypeMismatchForName(
+                    "slot_to_timestamp",
+                    "slot".to_string(),
+                    "u64".to_string(),
+                )
+            })?;
+
+        let timestamp = self.0.slot_to_wallclock(slot);
+
+        Ok(handlebars::ScopedJson::Derived(JsonValue::from(timestamp)))
+    }
+}
+
+fn hbs_data(event: &str, point: &Point, record: &Record) -> JsonValue {
+    json!({
+        "event": event,
+        "point": match point {
+            Point::Origin => JsonValue::Null,
+            Point::Specific(slot, hash) => json!({
+                "slot": slot,
+                "hash": hex::encode(hash),
+            }),
+        },
+        "record": JsonValue::from(record.clone()),
+    })
+}
+
+#[derive(Stage)]
+#[stage(name = "filter-template", unit = "ChainEvent", worker = "Worker")]
+pub struct Stage {
+    templates: Handlebars<'static>,
+    output_format: OutputFormat,
+
+    pub input: FilterInputPort,
+    pub output: FilterOutputPort,
+
+    #[metric]
+    ops_count: gasket::metrics::Counter,
+}
+
+impl Stage {
+    fn render(&self, event: &str, point: &Point, record: &Record) -> Result<Record, Error> {
+        let data = hbs_data(event, point, record);
+
+        let rendered = self
+            .templates
+            .render("record", &data)
+            .map_err(Error::custom)?;
+
+        let output = match self.output_format {
+            OutputFormat::Json => serde_json::from_str(&rendered).map_err(Error::parse)?,
+            OutputFormat::Text => JsonValue::String(rendered),
+        };
+
+        Ok(Record::GenericJson(output))
+    }
+}
+
+#[derive(Default)]
+pub struct Worker;
+
+impl From<&Stage> for Worker {
+    fn from(_: &Stage) -> Self {
+        Self
+    }
+}
+
+gasket::impl_mapper!(|_worker: Worker, stage: Stage, unit: ChainEvent| => {
+    let output = match unit {
+        ChainEvent::Apply(p, r, _) => {
+            ChainEvent::Apply(p.clone(), stage.render("apply", p, r).or_panic()?, None)
+        }
+        ChainEvent::Undo(p, r, _) => {
+            ChainEvent::Undo(p.clone(), stage.render("undo", p, r).or_panic()?, None)
+        }
+        ChainEvent::Reset(p) => ChainEvent::Reset(p.clone()),
+    };
+
+    stage.ops_count.inc(1);
+
+    output
+});
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// parse the rendered text as JSON
+    #[default]
+    Json,
+
+    /// keep the rendered text as a JSON string
+    Text,
+}
+
+#[derive(Deserialize)]
+pub struct Config {
+    /// Handlebars template rendered against `{event, point, record}`
+    pub template: String,
+
+    #[serde(default)]
+    pub output_format: OutputFormat,
+}
+
+impl Config {
+    pub fn bootstrapper(self, ctx: &Context) -> Result<Stage, Error> {
+        let mut templates = Handlebars::new();
+
+        // the output is JSON or plain text, HTML escaping would only corrupt it
+        templates.register_escape_fn(handlebars::no_escape);
+
+        templates.register_helper("to_hex", Box::new(to_hex));
+        templates.register_helper("from_hex", Box::new(from_hex));
+        templates.register_helper("to_bech32", Box::new(to_bech32));
+        templates.register_helper("from_bech32", Box::new(from_bech32));
+        templates.register_helper("lovelace_to_ada", Box::new(lovelace_to_ada));
+
+        templates.register_helper(
+            "slot_to_timestamp",
+            Box::new(SlotToTimestamp(ctx.chain.clone().into())),
+        );
+
+        templates
+            .register_template_string("record", &self.template)
+            .map_err(Error::config)?;
+
+        let stage = Stage {
+            templates,
+            output_format: self.output_format,
+            ops_count: Default::default(),
+            input: Default::default(),
+            output: Default::default(),
+        };
+
+        Ok(stage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, output_format: OutputFormat, record: Record) -> JsonValue {
+        let ctx = Context {
+            chain: ChainConfig::Mainnet,
+            intersect: IntersectConfig::Tip,
+            finalize: None,
+            current_dir: Default::default(),
+            breadcrumbs: Breadcrumbs::new(0),
+        };
+
+        let stage = Config {
+            template: template.to_string(),
+            output_format,
+        }
+        .bootstrapper(&ctx)
+        .unwrap();
+
+        let point = Point::Specific(4492800, vec![0xab, 0xcd]);
+
+        match stage.render("apply", &point, &record).unwrap() {
+            Record::GenericJson(x) => x,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn renders_json_output() {
+        let output = render(
+            r#"{"kind": "{{event}}", "slot": {{point.slot}}, "fee": "{{record.fee}}"}"#,
+            OutputFormat::Json,
+            Record::GenericJson(json!({ "fee": 170000 })),
+        );
+
+        assert_eq!(
+            output,
+            json!({ "kind": "apply", "slot": 4492800, "fee": "170000" })
+        );
+    }
+
+    #[test]
+    fn renders_text_output() {
+        let output = render(
+            "{{point.hash}} <{{record.name}}>",
+            OutputFormat::Text,
+            Record::GenericJson(json!({ "name": "a&b" })),
+        );
+
+        assert_eq!(output, json!("abcd <a&b>"));
+    }
+
+    #[test]
+    fn encoding_helpers() {
+        let output = render(
+            r#"["{{to_hex "PATATE"}}", "{{from_hex "504154415445"}}", "{{to_bech32 "asset" record.fp}}", "{{from_bech32 "asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92"}}"]"#,
+            OutputFormat::Json,
+            Record::GenericJson(json!({ "fp": "8cd54e31e4ea696e42344ed563eb00269e2a1da5" })),
+        );
+
+        assert_eq!(
+            output,
+            json!([
+                "504154415445",
+                "PATATE",
+                "asset13n25uv0yaf5kus35fm2k86cqy60z58d9xmde92",
+                "8cd54e31e4ea696e42344ed563eb00269e2a1da5"
+            ])
+        );
+    }
+
+    #[test]
+    fn chain_helpers() {
+        let output = render(
+            r#"{"ada": {{lovelace_to_ada record.coin}}, "time": {{slot_to_timestamp point.slot}}}"#,
+            OutputFormat::Json,
+            Record::GenericJson(json!({ "coin": "1500001" })),
+        );
+
+        assert_eq!(output, json!({ "ada": 1.500001, "time": 1596059091 }));
+    }
+}
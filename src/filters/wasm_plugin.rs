@@ -1,10 +1,151 @@
 //! A filter that maps records by calling custom WASM plugins
 
+use std::collections::HashMap;
+
+use extism::{host_fn, UserData, PTR};
 use gasket::framework::*;
+use pallas::network::miniprotocols::Point;
 use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use tracing::{debug, error, info, warn};
 
 use crate::framework::*;
 
+/// Version of the envelope handed to `map_event` by the `v1` plugin ABI
+pub const ABI_VERSION: u32 = 1;
+
+/// Key-value state shared between the plugin calls
+#[derive(Default)]
+pub struct KvStore(HashMap<String, Vec<u8>>);
+
+/// Stage metrics the plugin can report to
+pub struct PluginMetrics {
+    counter: gasket::metrics::Counter,
+    gauge: gasket::metrics::Gauge,
+}
+
+/// Turns a poisoned lock of the host state into an error for the plugin call
+/// instead of bringing the stage down
+fn poisoned<T>(_: std::sync::PoisonError<T>) -> extism::Error {
+    extism::Error::msg("host state lock poisoned by a previous failure")
+}
+
+host_fn!(oura_log(_user_data: (); level: String, message: String) {
+    match level.as_str() {
+        "error" => error!(target: "wasm_plugin", "{message}"),
+        "warn" => warn!(target: "wasm_plugin", "{message}"),
+        "debug" => debug!(target: "wasm_plugin", "{message}"),
+        _ => info!(target: "wasm_plugin", "{message}"),
+    };
+
+    Ok(())
+});
+
+host_fn!(oura_kv_get(store: KvStore; key: String) -> Vec<u8> {
+    let store = store.get()?;
+    let store = store.lock().map_err(poisoned)?;
+
+    Ok(store.0.get(&key).cloned().unwrap_or_default())
+});
+
+host_fn!(oura_kv_set(store: KvStore; key: String, value: Vec<u8>) {
+    let store = store.get()?;
+    store.lock().map_err(poisoned)?.0.insert(key, value);
+
+    Ok(())
+});
+
+host_fn!(oura_kv_delete(store: KvStore; key: String) {
+    let store = store.get()?;
+    store.lock().map_err(poisoned)?.0.remove(&key);
+
+    Ok(())
+});
+
+host_fn!(oura_metric_inc(metrics: PluginMetrics; value: u64) {
+    let metrics = metrics.get()?;
+    metrics.lock().map_err(poisoned)?.counter.inc(value);
+
+    Ok(())
+});
+
+host_fn!(oura_metric_set(metrics: PluginMetrics; value: i64) {
+    let metrics = metrics.get()?;
+    metrics.lock().map_err(poisoned)?.gauge.set(value);
+
+    Ok(())
+});
+
+fn record_type(record: &Record) -> &'static str {
+    match record {
+        Record::CborBlock(_) => "cbor_block",
+        Record::CborTx(_) => "cbor_tx",
+        Record::GenericJson(_) => "generic_json",
+        Record::OuraV1Event(_) => "oura_v1_event",
+        Record::ParsedTx(_) => "parsed_tx",
+        Record::ParsedBlock(_) => "parsed_block",
+    }
+}
+
+fn deserialize_cbor<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    // accept both a bare hex string and the `{ "hex": ... }` shape used by the envelope
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Cbor {
+        Hex(String),
+        Wrapped { hex: String },
+    }
+
+    let hex = match Cbor::deserialize(deserializer)? {
+        Cbor::Hex(x) => x,
+        Cbor::Wrapped { hex } => hex,
+    };
+
+    hex::decode(hex).map_err(serde::de::Error::custom)
+}
+
+/// A plugin output item tagged with the type of record it holds, mirroring the `record_type`
+/// and `record` fields of the envelope
+#[allow(clippy::large_enum_variant)]
+#[derive(Deserialize)]
+#[serde(tag = "record_type", content = "record", rename_all = "snake_case")]
+enum TaggedRecord {
+    #[serde(deserialize_with = "deserialize_cbor")]
+    CborBlock(Vec<u8>),
+    #[serde(deserialize_with = "deserialize_cbor")]
+    CborTx(Vec<u8>),
+    GenericJson(JsonValue),
+    OuraV1Event(legacy_v1::Event),
+    ParsedTx(ParsedTx),
+    ParsedBlock(ParsedBlock),
+}
+
+impl From<TaggedRecord> for Record {
+    fn from(value: TaggedRecord) -> Self {
+        match value {
+            TaggedRecord::CborBlock(x) => Record::CborBlock(x),
+            TaggedRecord::CborTx(x) => Record::CborTx(x),
+            TaggedRecord::GenericJson(x) => Record::GenericJson(x),
+            TaggedRecord::OuraV1Event(x) => Record::OuraV1Event(x),
+            TaggedRecord::ParsedTx(x) => Record::ParsedTx(x),
+            TaggedRecord::ParsedBlock(x) => Record::ParsedBlock(x),
+        }
+    }
+}
+
+fn envelope(event: &str, point: &Point, record: Record) -> JsonValue {
+    json!({
+        "abi": ABI_VERSION,
+        "event": event,
+        "point": point_to_json(point.clone()),
+        "record_type": record_type(&record),
+        "record": JsonValue::from(record),
+    })
+}
+
 #[derive(Stage)]
 #[stage(name = "filter-wasm", unit = "ChainEvent", worker = "Worker")]
 pub struct Stage {
@@ -12,42 +153,96 @@ pub struct Stage {
     pub output: FilterOutputPort,
 
     plugin: extism::Plugin,
+    abi: Abi,
+    on_error: ErrorPolicy,
+    typed_output: bool,
 
     #[metric]
     ops_count: gasket::metrics::Counter,
+
+    #[metric]
+    error_count: gasket::metrics::Counter,
+
+    #[metric]
+    plugin_counter: gasket::metrics::Counter,
+
+    #[metric]
+    plugin_gauge: gasket::metrics::Gauge,
 }
 
 impl Stage {
-    fn map_record(&mut self, r: Record) -> Result<Vec<Record>, Error> {
-        let extism::convert::Json::<serde_json::Value>(output) = match r {
-            Record::CborBlock(x) => self.plugin.call("map_cbor_block", x).unwrap(),
-            Record::CborTx(x) => self.plugin.call("map_cbor_tx", x).unwrap(),
-            Record::ParsedTx(x) => self
-                .plugin
-                .call("map_u5c_tx", extism::convert::Json(x))
-                .unwrap(),
+    fn call_v0(&mut self, r: Record) -> Result<JsonValue, extism::Error> {
+        let extism::convert::Json::<JsonValue>(output) = match r {
+            Record::CborBlock(x) => self.plugin.call("map_cbor_block", x)?,
+            Record::CborTx(x) => self.plugin.call("map_cbor_tx", x)?,
+            Record::ParsedTx(x) => self.plugin.call("map_u5c_tx", extism::convert::Json(x))?,
             Record::ParsedBlock(x) => self
                 .plugin
-                .call("map_u5c_block", extism::convert::Json(x))
-                .unwrap(),
-            Record::GenericJson(x) => self
-                .plugin
-                .call("map_json", extism::convert::Json(x))
-                .unwrap(),
-            Record::OuraV1Event(x) => self
-                .plugin
-                .call("map_json", extism::convert::Json(x))
-                .unwrap(),
+                .call("map_u5c_block", extism::convert::Json(x))?,
+            Record::GenericJson(x) => self.plugin.call("map_json", extism::convert::Json(x))?,
+            Record::OuraV1Event(x) => self.plugin.call("map_json", extism::convert::Json(x))?,
         };
 
-        let output = match output {
-            serde_json::Value::Null => vec![],
-            serde_json::Value::Array(x) => x.into_iter().map(Record::GenericJson).collect(),
-            x => vec![Record::GenericJson(x)],
-        };
+        Ok(output)
+    }
+
+    fn call_v1(&mut self, envelope: JsonValue) -> Result<JsonValue, extism::Error> {
+        let extism::convert::Json::<JsonValue>(output) = self
+            .plugin
+            .call("map_event", extism::convert::Json(envelope))?;
 
         Ok(output)
     }
+
+    fn map_event(&mut self, unit: &ChainEvent) -> Result<Vec<ChainEvent>, MapError> {
+        let (point, output) = match unit {
+            ChainEvent::Apply(p, r, _) => match self.abi {
+                Abi::V0 => (p, self.call_v0(r.clone())?),
+                Abi::V1 => (p, self.call_v1(envelope("apply", p, r.clone()))?),
+            },
+            ChainEvent::Undo(p, r, _) => match self.abi {
+                Abi::V0 => (p, self.call_v0(r.clone())?),
+                Abi::V1 => (p, self.call_v1(envelope("undo", p, r.clone()))?),
+            },
+            ChainEvent::Reset(_) => return Ok(vec![unit.clone()]),
+        };
+
+        let items = match output {
+            JsonValue::Null => vec![],
+            JsonValue::Array(x) => x,
+            x => vec![x],
+        };
+
+        let records = match self.typed_output {
+            true => items
+                .into_iter()
+                .map(|x| serde_json::from_value::<TaggedRecord>(x).map(Record::from))
+                .collect::<Result<Vec<_>, _>>()?,
+            false => items.into_iter().map(Record::GenericJson).collect(),
+        };
+
+        let events = records
+            .into_iter()
+            .map(|r| match unit {
+                ChainEvent::Undo(..) => ChainEvent::Undo(point.clone(), r, None),
+                _ => ChainEvent::Apply(point.clone(), r, None),
+            })
+            .collect();
+
+        Ok(events)
+    }
+}
+
+/// Why the plugin couldn't map an event
+#[derive(Debug, thiserror::Error)]
+enum MapError {
+    /// the plugin call itself failed, which might be transient
+    #[error("plugin call failed: {0}")]
+    Call(#[from] extism::Error),
+
+    /// the plugin returned items that aren't valid tagged records
+    #[error("invalid plugin output: {0}")]
+    Output(#[from] serde_json::Error),
 }
 
 #[derive(Default)]
@@ -75,13 +270,35 @@ impl gasket::framework::Worker<Stage> for Worker {
     }
 
     async fn execute(&mut self, unit: &ChainEvent, stage: &mut Stage) -> Result<(), WorkerError> {
-        let output = unit
-            .clone()
-            .try_map_record_to_many(|x| stage.map_record(x))
-            .or_panic()?;
+        let output = match stage.map_event(unit) {
+            Ok(x) => x,
+            Err(err) => {
+                stage.error_count.inc(1);
+
+                match stage.on_error {
+                    ErrorPolicy::Panic => {
+                        error!(%err, "wasm plugin failed");
+                        return Err(WorkerError::Panic);
+                    }
+                    ErrorPolicy::Retry if matches!(err, MapError::Call(_)) => {
+                        warn!(%err, "wasm plugin failed, retrying");
+                        return Err(WorkerError::Retry);
+                    }
+                    // the same output would fail to decode again
+                    ErrorPolicy::Retry => {
+                        error!(%err, "wasm plugin output can't be decoded");
+                        return Err(WorkerError::Panic);
+                    }
+                    ErrorPolicy::Skip => {
+                        warn!(%err, point = ?unit.point(), "wasm plugin failed, skipping event");
+                        vec![]
+                    }
+                }
+            }
+        };
 
         for unit in output {
-            stage.output.send(unit.clone().into()).await.or_panic()?;
+            stage.output.send(unit.into()).await.or_panic()?;
             stage.ops_count.inc(1);
         }
 
@@ -89,22 +306,199 @@ impl gasket::framework::Worker<Stage> for Worker {
     }
 }
 
+/// Calling convention between the stage and the plugin
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Abi {
+    /// one export per record type (`map_cbor_block`, `map_u5c_tx`, `map_json`, ...) that
+    /// receives the bare record
+    #[default]
+    V0,
+
+    /// a single `map_event` export that receives the event envelope (abi version, event kind,
+    /// point, record type and record)
+    V1,
+}
+
+/// What to do with an event when the plugin call fails
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorPolicy {
+    /// stop the stage
+    #[default]
+    Panic,
+
+    /// retry the event according to the stage retry policy
+    Retry,
+
+    /// log the error and drop the event
+    Skip,
+}
+
 #[derive(Default, Deserialize)]
 pub struct Config {
     pub path: String,
+
+    #[serde(default)]
+    pub abi: Abi,
+
+    #[serde(default)]
+    pub on_error: ErrorPolicy,
+
+    /// Expect output items tagged with their record type instead of plain JSON
+    #[serde(default)]
+    pub typed_output: bool,
+
+    /// Values available to the plugin through the Extism config API
+    #[serde(default)]
+    pub config: HashMap<String, String>,
 }
 
 impl Config {
     pub fn bootstrapper(self, _ctx: &Context) -> Result<Stage, Error> {
         let wasm = extism::Wasm::file(self.path);
-        let manifest = extism::Manifest::new([wasm]);
-        let plugin = extism::Plugin::new(&manifest, [], true).map_err(Error::custom)?;
+        let manifest = extism::Manifest::new([wasm]).with_config(self.config.into_iter());
+
+        let plugin_counter = gasket::metrics::Counter::default();
+        let plugin_gauge = gasket::metrics::Gauge::default();
+
+        let store = UserData::new(KvStore::default());
+
+        let metrics = UserData::new(PluginMetrics {
+            counter: plugin_counter.clone(),
+            gauge: plugin_gauge.clone(),
+        });
+
+        let plugin = extism::PluginBuilder::new(manifest)
+            .with_wasi(true)
+            .with_function("oura_log", [PTR, PTR], [], UserData::default(), oura_log)
+            .with_function("oura_kv_get", [PTR], [PTR], store.clone(), oura_kv_get)
+            .with_function("oura_kv_set", [PTR, PTR], [], store.clone(), oura_kv_set)
+            .with_function("oura_kv_delete", [PTR], [], store, oura_kv_delete)
+            .with_function(
+                "oura_metric_inc",
+                [PTR],
+                [],
+                metrics.clone(),
+                oura_metric_inc,
+            )
+            .with_function("oura_metric_set", [PTR], [], metrics, oura_metric_set)
+            .build()
+            .map_err(Error::custom)?;
 
         Ok(Stage {
             input: Default::default(),
             output: Default::default(),
             ops_count: Default::default(),
+            error_count: Default::default(),
+            plugin_counter,
+            plugin_gauge,
             plugin,
+            abi: self.abi,
+            on_error: self.on_error,
+            typed_output: self.typed_output,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pallas::interop::utxorpc::spec::cardano::{big_int, BigInt};
+
+    use super::*;
+
+    fn stage() -> Stage {
+        stage_with(Config::default())
+    }
+
+    fn stage_with(config: Config) -> Stage {
+        let ctx = Context {
+            chain: ChainConfig::Mainnet,
+            intersect: IntersectConfig::Tip,
+            finalize: None,
+            current_dir: Default::default(),
+            breadcrumbs: Breadcrumbs::new(0),
+        };
+
+        Config {
+            path: "examples/wasm_basic/extract_fee/plugin.wasm".into(),
+            ..config
+        }
+        .bootstrapper(&ctx)
+        .unwrap()
+    }
+
+    #[test]
+    fn maps_v0_record() {
+        let mut stage = stage();
+
+        let tx = ParsedTx {
+            fee: Some(BigInt {
+                big_int: Some(big_int::BigInt::Int(170000)),
+            }),
+            ..Default::default()
+        };
+
+        let unit = ChainEvent::Undo(Point::Origin, Record::ParsedTx(tx), None);
+        let output = stage.map_event(&unit).unwrap();
+
+        assert!(matches!(
+            output.as_slice(),
+            [ChainEvent::Undo(Point::Origin, Record::GenericJson(_), _)]
+        ));
+    }
+
+    #[test]
+    fn decodes_tagged_records() {
+        let tx = ParsedTx {
+            hash: vec![0xab, 0xcd].into(),
+            fee: Some(BigInt {
+                big_int: Some(big_int::BigInt::Int(170000)),
+            }),
+            ..Default::default()
+        };
+
+        let tagged = json!({ "record_type": "parsed_tx", "record": json!(tx.clone()) });
+        let record = serde_json::from_value::<TaggedRecord>(tagged).unwrap();
+        assert!(matches!(Record::from(record), Record::ParsedTx(x) if x == tx));
+
+        let tagged = json!({ "record_type": "cbor_tx", "record": "abcd" });
+        let record = serde_json::from_value::<TaggedRecord>(tagged).unwrap();
+        assert!(matches!(Record::from(record), Record::CborTx(x) if x == [0xab, 0xcd]));
+
+        let tagged = json!({ "record_type": "cbor_block", "record": { "hex": "abcd" } });
+        let record = serde_json::from_value::<TaggedRecord>(tagged).unwrap();
+        assert!(matches!(Record::from(record), Record::CborBlock(x) if x == [0xab, 0xcd]));
+
+        let tagged = json!({ "record_type": "unknown", "record": {} });
+        assert!(serde_json::from_value::<TaggedRecord>(tagged).is_err());
+    }
+
+    #[test]
+    fn missing_export_is_an_error() {
+        let mut stage = stage();
+
+        let unit = ChainEvent::Apply(Point::Origin, Record::GenericJson(json!({})), None);
+
+        assert!(matches!(stage.map_event(&unit), Err(MapError::Call(_))));
+    }
+
+    #[test]
+    fn untagged_output_is_not_a_call_error() {
+        let mut stage = stage_with(Config {
+            typed_output: true,
+            ..Default::default()
+        });
+
+        let tx = ParsedTx {
+            fee: Some(BigInt {
+                big_int: Some(big_int::BigInt::Int(170000)),
+            }),
+            ..Default::default()
+        };
+
+        let unit = ChainEvent::Apply(Point::Origin, Record::ParsedTx(tx), None);
+
+        assert!(matches!(stage.map_event(&unit), Err(MapError::Output(_))));
+    }
+}
@@ -69,13 +69,13 @@ impl gasket::framework::Worker<Stage> for Worker {
         // `max_block_quantity` semantics. Undo/Reset still advance the policy's
         // notion of the latest point but don't count towards the block total.
         let point = match unit {
-            ChainEvent::Apply(point, _) => {
+            ChainEvent::Apply(point, ..) => {
                 self.blocks += 1;
                 stage.block_count.inc(1);
                 stage.latest_slot.set(point.slot_or_default() as i64);
                 point.clone()
             }
-            ChainEvent::Undo(point, _) => point.clone(),
+            ChainEvent::Undo(point, ..) => point.clone(),
             ChainEvent::Reset(point) => point.clone(),
         };
 
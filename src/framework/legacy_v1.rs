@@ -8,7 +8,7 @@ use serde::{
 };
 use serde_json::Value as JsonValue;
 
-use strum_macros::Display;
+use strum_macros::{Display, EnumDiscriminants};
 
 // We're duplicating the Era struct from Pallas for two reasons: a) we need it
 // to be serializable and we don't want to impose serde dependency on Pallas and
@@ -25,6 +25,7 @@ pub enum Era {
     Mary,
     Alonzo,
     Babbage,
+    Conway,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -83,6 +84,27 @@ impl From<CIP25AssetRecord> for EventData {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CIP68AssetRecord {
+    pub policy: String,
+    pub reference_asset: String,
+    pub user_asset: Option<String>,
+    pub user_label: Option<u32>,
+    pub version: Option<i64>,
+    pub name: Option<String>,
+    pub image: Option<String>,
+    pub media_type: Option<String>,
+    pub description: Option<String>,
+    pub attributes: Option<JsonValue>,
+    pub raw_json: JsonValue,
+}
+
+impl From<CIP68AssetRecord> for EventData {
+    fn from(x: CIP68AssetRecord) -> Self {
+        EventData::CIP68Asset(x)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CIP15AssetRecord {
     pub voting_key: String,
@@ -98,10 +120,46 @@ impl From<CIP15AssetRecord> for EventData {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CIP36DelegationRecord {
+    pub voting_key: String,
+    pub weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CIP36RegistrationRecord {
+    pub delegations: Vec<CIP36DelegationRecord>,
+    pub stake_pub: String,
+    pub reward_address: String,
+    pub nonce: i64,
+    pub voting_purpose: u64,
+    pub signature: Option<String>,
+    pub raw_json: JsonValue,
+}
+
+impl From<CIP36RegistrationRecord> for EventData {
+    fn from(x: CIP36RegistrationRecord) -> Self {
+        EventData::CIP36Registration(x)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CIP20MessageRecord {
+    pub messages: Vec<String>,
+    pub raw_json: JsonValue,
+}
+
+impl From<CIP20MessageRecord> for EventData {
+    fn from(x: CIP20MessageRecord) -> Self {
+        EventData::CIP20Message(x)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TxInputRecord {
     pub tx_id: String,
     pub index: u64,
+    pub spent_output: Option<TxOutputRecord>,
 }
 
 impl From<TxInputRecord> for EventData {
@@ -124,6 +182,19 @@ impl From<OutputAssetRecord> for EventData {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceScriptRecord {
+    pub script_hash: String,
+    pub language: String,
+    pub script_hex: String,
+}
+
+impl From<ReferenceScriptRecord> for EventData {
+    fn from(x: ReferenceScriptRecord) -> Self {
+        EventData::ReferenceScript(x)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TxOutputRecord {
     pub address: String,
@@ -131,6 +202,8 @@ pub struct TxOutputRecord {
     pub assets: Option<Vec<OutputAssetRecord>>,
     pub datum_hash: Option<String>,
     pub inline_datum: Option<PlutusDatumRecord>,
+    pub reference_script: Option<ReferenceScriptRecord>,
+    pub cbor_hex: Option<String>,
 }
 
 impl From<TxOutputRecord> for EventData {
@@ -158,6 +231,42 @@ pub struct WithdrawalRecord {
     pub coin: u64,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AnchorRecord {
+    pub url: String,
+    pub data_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GovProposalRecord {
+    pub deposit: u64,
+    pub reward_account: String,
+    pub action: String,
+    pub anchor: AnchorRecord,
+}
+
+impl From<GovProposalRecord> for EventData {
+    fn from(x: GovProposalRecord) -> Self {
+        EventData::GovProposal(x)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GovVoteRecord {
+    pub voter_role: String,
+    pub voter: StakeCredential,
+    pub action_tx_id: String,
+    pub action_index: u32,
+    pub vote: String,
+    pub anchor: Option<AnchorRecord>,
+}
+
+impl From<GovVoteRecord> for EventData {
+    fn from(x: GovVoteRecord) -> Self {
+        EventData::GovVote(x)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
 pub struct TransactionRecord {
     pub hash: String,
@@ -171,6 +280,7 @@ pub struct TransactionRecord {
     pub output_count: usize,
     pub mint_count: usize,
     pub total_output: u64,
+    pub treasury_donation: Option<u64>,
 
     // include_details
     pub metadata: Option<Vec<MetadataRecord>>,
@@ -186,7 +296,10 @@ pub struct TransactionRecord {
     pub plutus_redeemers: Option<Vec<PlutusRedeemerRecord>>,
     pub plutus_data: Option<Vec<PlutusDatumRecord>>,
     pub withdrawals: Option<Vec<WithdrawalRecord>>,
+    pub proposal_procedures: Option<Vec<GovProposalRecord>>,
+    pub voting_procedures: Option<Vec<GovVoteRecord>>,
     pub size: u32,
+    pub cbor_hex: Option<String>,
 }
 
 impl From<TransactionRecord> for EventData {
@@ -207,6 +320,8 @@ pub struct EventContext {
     pub output_idx: Option<usize>,
     pub output_address: Option<String>,
     pub certificate_idx: Option<usize>,
+    pub proposal_idx: Option<usize>,
+    pub vote_idx: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -215,16 +330,32 @@ pub enum StakeCredential {
     Scripthash(String),
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DRep {
+    AddrKeyhash(String),
+    Scripthash(String),
+    Abstain,
+    NoConfidence,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct VKeyWitnessRecord {
     pub vkey_hex: String,
     pub signature_hex: String,
+    pub cbor_hex: Option<String>,
+}
+
+impl From<VKeyWitnessRecord> for EventData {
+    fn from(x: VKeyWitnessRecord) -> Self {
+        EventData::VKeyWitness(x)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct NativeWitnessRecord {
     pub policy_id: String,
     pub script_json: JsonValue,
+    pub cbor_hex: Option<String>,
 }
 
 impl From<NativeWitnessRecord> for EventData {
@@ -237,6 +368,7 @@ impl From<NativeWitnessRecord> for EventData {
 pub struct PlutusWitnessRecord {
     pub script_hash: String,
     pub script_hex: String,
+    pub cbor_hex: Option<String>,
 }
 
 impl From<PlutusWitnessRecord> for EventData {
@@ -252,6 +384,13 @@ pub struct PlutusRedeemerRecord {
     pub ex_units_steps: u64,
     pub input_idx: u32,
     pub plutus_data: JsonValue,
+
+    /// what the redeemer unlocks: the spent input as `tx_id#index`, the
+    /// minting policy, the certificate as `kind:credential`, the reward
+    /// account, the voter credential or the proposal as
+    /// `action:reward_account`, depending on the purpose
+    pub target: Option<String>,
+    pub cbor_hex: Option<String>,
 }
 
 impl From<PlutusRedeemerRecord> for EventData {
@@ -264,6 +403,7 @@ impl From<PlutusRedeemerRecord> for EventData {
 pub struct PlutusDatumRecord {
     pub datum_hash: String,
     pub plutus_data: JsonValue,
+    pub cbor_hex: Option<String>,
 }
 
 impl From<PlutusDatumRecord> for EventData {
@@ -294,7 +434,12 @@ impl From<BlockRecord> for EventData {
     }
 }
 
-#[derive(Serialize, Deserialize, Display, Debug, Clone)]
+#[derive(Serialize, Deserialize, Display, EnumDiscriminants, Debug, Clone)]
+#[strum_discriminants(
+    name(EventKind),
+    derive(Deserialize, Hash),
+    serde(rename_all = "snake_case")
+)]
 #[serde(rename_all = "snake_case")]
 pub enum EventData {
     Block(BlockRecord),
@@ -311,13 +456,29 @@ pub enum EventData {
     PlutusWitness(PlutusWitnessRecord),
     PlutusRedeemer(PlutusRedeemerRecord),
     PlutusDatum(PlutusDatumRecord),
+    InlineDatum(PlutusDatumRecord),
+    ReferenceScript(ReferenceScriptRecord),
 
     #[serde(rename = "cip25_asset")]
+    #[strum_discriminants(serde(rename = "cip25_asset"))]
     CIP25Asset(CIP25AssetRecord),
 
     #[serde(rename = "cip15_asset")]
+    #[strum_discriminants(serde(rename = "cip15_asset"))]
     CIP15Asset(CIP15AssetRecord),
 
+    #[serde(rename = "cip68_asset")]
+    #[strum_discriminants(serde(rename = "cip68_asset"))]
+    CIP68Asset(CIP68AssetRecord),
+
+    #[serde(rename = "cip36_registration")]
+    #[strum_discriminants(serde(rename = "cip36_registration"))]
+    CIP36Registration(CIP36RegistrationRecord),
+
+    #[serde(rename = "cip20_message")]
+    #[strum_discriminants(serde(rename = "cip20_message"))]
+    CIP20Message(CIP20MessageRecord),
+
     Mint(MintRecord),
     Collateral {
         tx_id: String,
@@ -364,6 +525,68 @@ pub enum EventData {
         to_stake_credentials: Option<Vec<(StakeCredential, i64)>>,
         to_other_pot: Option<u64>,
     },
+    StakeRegistrationWithDeposit {
+        credential: StakeCredential,
+        deposit: u64,
+    },
+    StakeDeregistrationWithDeposit {
+        credential: StakeCredential,
+        deposit: u64,
+    },
+    VoteDelegation {
+        credential: StakeCredential,
+        drep: DRep,
+    },
+    StakeVoteDelegation {
+        credential: StakeCredential,
+        pool_hash: String,
+        drep: DRep,
+    },
+    StakeRegistrationDelegation {
+        credential: StakeCredential,
+        pool_hash: String,
+        deposit: u64,
+    },
+    VoteRegistrationDelegation {
+        credential: StakeCredential,
+        drep: DRep,
+        deposit: u64,
+    },
+    StakeVoteRegistrationDelegation {
+        credential: StakeCredential,
+        pool_hash: String,
+        drep: DRep,
+        deposit: u64,
+    },
+    CommitteeHotAuthorization {
+        committee_cold_credential: StakeCredential,
+        committee_hot_credential: StakeCredential,
+    },
+    CommitteeColdResignation {
+        committee_cold_credential: StakeCredential,
+        anchor: Option<AnchorRecord>,
+    },
+    #[serde(rename = "drep_registration")]
+    #[strum_discriminants(serde(rename = "drep_registration"))]
+    DRepRegistration {
+        credential: StakeCredential,
+        deposit: u64,
+        anchor: Option<AnchorRecord>,
+    },
+    #[serde(rename = "drep_deregistration")]
+    #[strum_discriminants(serde(rename = "drep_deregistration"))]
+    DRepDeregistration {
+        credential: StakeCredential,
+        deposit: u64,
+    },
+    #[serde(rename = "drep_update")]
+    #[strum_discriminants(serde(rename = "drep_update"))]
+    DRepUpdate {
+        credential: StakeCredential,
+        anchor: Option<AnchorRecord>,
+    },
+    GovProposal(GovProposalRecord),
+    GovVote(GovVoteRecord),
     RollBack {
         block_slot: u64,
         block_hash: String,
@@ -96,6 +96,7 @@ pub struct Context {
     pub breadcrumbs: Breadcrumbs,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum Record {
     CborBlock(Vec<u8>),
@@ -119,23 +120,25 @@ impl From<Record> for JsonValue {
     }
 }
 
+/// An event flowing through the pipeline. Apply and undo events carry,
+/// along with their record, the ID set by the fingerprint filter.
 #[derive(Debug, Clone)]
 pub enum ChainEvent {
-    Apply(Point, Record),
-    Undo(Point, Record),
+    Apply(Point, Record, Option<String>),
+    Undo(Point, Record, Option<String>),
     Reset(Point),
 }
 
 impl ChainEvent {
     pub fn apply(point: Point, record: impl Into<Record>) -> gasket::messaging::Message<Self> {
         gasket::messaging::Message {
-            payload: Self::Apply(point, record.into()),
+            payload: Self::Apply(point, record.into(), None),
         }
     }
 
     pub fn undo(point: Point, record: impl Into<Record>) -> gasket::messaging::Message<Self> {
         gasket::messaging::Message {
-            payload: Self::Undo(point, record.into()),
+            payload: Self::Undo(point, record.into(), None),
         }
     }
 
@@ -147,24 +150,24 @@ impl ChainEvent {
 
     pub fn point(&self) -> &Point {
         match self {
-            Self::Apply(x, _) => x,
-            Self::Undo(x, _) => x,
+            Self::Apply(x, ..) => x,
+            Self::Undo(x, ..) => x,
             Self::Reset(x) => x,
         }
     }
 
     pub fn record(&self) -> Option<&Record> {
         match self {
-            Self::Apply(_, x) => Some(x),
-            Self::Undo(_, x) => Some(x),
+            Self::Apply(_, x, _) => Some(x),
+            Self::Undo(_, x, _) => Some(x),
             _ => None,
         }
     }
 
     pub fn map_record(self, f: fn(Record) -> Record) -> Self {
         match self {
-            Self::Apply(p, x) => Self::Apply(p, f(x)),
-            Self::Undo(p, x) => Self::Undo(p, f(x)),
+            Self::Apply(p, x, id) => Self::Apply(p, f(x), id),
+            Self::Undo(p, x, id) => Self::Undo(p, f(x), id),
             Self::Reset(x) => Self::Reset(x),
         }
     }
@@ -174,8 +177,8 @@ impl ChainEvent {
         F: FnOnce(Record) -> Result<Record, E>,
     {
         let out = match self {
-            Self::Apply(p, x) => Self::Apply(p, f(x)?),
-            Self::Undo(p, x) => Self::Undo(p, f(x)?),
+            Self::Apply(p, x, id) => Self::Apply(p, f(x)?, id),
+            Self::Undo(p, x, id) => Self::Undo(p, f(x)?, id),
             Self::Reset(x) => Self::Reset(x),
         };
 
@@ -187,22 +190,33 @@ impl ChainEvent {
         F: FnOnce(Record) -> Result<Vec<Record>, E>,
     {
         let out = match self {
-            Self::Apply(p, x) => f(x)?
+            // the records that come out are new ones, without an id
+            Self::Apply(p, x, _) => f(x)?
                 .into_iter()
-                .map(|i| Self::Apply(p.clone(), i))
+                .map(|i| Self::Apply(p.clone(), i, None))
                 .collect(),
-            Self::Undo(p, x) => f(x)?
+            Self::Undo(p, x, _) => f(x)?
                 .into_iter()
-                .map(|i| Self::Undo(p.clone(), i))
+                .map(|i| Self::Undo(p.clone(), i, None))
                 .collect(),
             Self::Reset(x) => vec![Self::Reset(x)],
         };
 
         Ok(out)
     }
+
+    /// The ID set by the fingerprint filter, used by sinks as key or
+    /// deduplication header
+    pub fn fingerprint(&self) -> Option<&str> {
+        match self {
+            Self::Apply(_, _, x) => x.as_deref(),
+            Self::Undo(_, _, x) => x.as_deref(),
+            Self::Reset(_) => None,
+        }
+    }
 }
 
-fn point_to_json(point: Point) -> JsonValue {
+pub fn point_to_json(point: Point) -> JsonValue {
     match &point {
         pallas::network::miniprotocols::Point::Origin => JsonValue::from("origin"),
         pallas::network::miniprotocols::Point::Specific(slot, hash) => {
@@ -214,19 +228,31 @@ fn point_to_json(point: Point) -> JsonValue {
 impl From<ChainEvent> for JsonValue {
     fn from(value: ChainEvent) -> Self {
         match value {
-            ChainEvent::Apply(point, record) => {
-                json!({
+            ChainEvent::Apply(point, record, fingerprint) => {
+                let mut out = json!({
                     "event": "apply",
                     "point": point_to_json(point),
                     "record": JsonValue::from(record.clone())
-                })
+                });
+
+                if let Some(x) = fingerprint {
+                    out["fingerprint"] = JsonValue::from(x);
+                }
+
+                out
             }
-            ChainEvent::Undo(point, record) => {
-                json!({
+            ChainEvent::Undo(point, record, fingerprint) => {
+                let mut out = json!({
                     "event": "undo",
                     "point": point_to_json(point),
                     "record": JsonValue::from(record.clone())
-                })
+                });
+
+                if let Some(x) = fingerprint {
+                    out["fingerprint"] = JsonValue::from(x);
+                }
+
+                out
             }
             ChainEvent::Reset(point) => {
                 json!({
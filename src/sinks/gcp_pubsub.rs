@@ -1,6 +1,7 @@
 use gasket::framework::*;
 use google_cloud_googleapis::pubsub::v1::PubsubMessage;
 use google_cloud_pubsub::client::{Client, ClientConfig};
+use std::collections::HashMap;
 
 use serde::Deserialize;
 
@@ -37,8 +38,14 @@ impl gasket::framework::Worker<Stage> for Worker {
 
         let payload = serde_json::Value::from(record.unwrap()).to_string();
 
+        let attributes = unit
+            .fingerprint()
+            .map(|x| HashMap::from([("fingerprint".to_string(), x.to_string())]))
+            .unwrap_or_default();
+
         let message = PubsubMessage {
             data: payload.into(),
+            attributes,
             ..Default::default()
         };
 
@@ -60,6 +60,17 @@ impl gasket::framework::Worker<Stage> for Worker {
                 let kafka_record = Record::from_key_value(&stage.config.topic, &slot[..], payload);
                 self.producer.send(&kafka_record)
             }
+            // records without an id are spread like with the random strategy
+            PartitionStrategy::ByFingerprint => match unit.fingerprint() {
+                Some(key) => {
+                    let kafka_record = Record::from_key_value(&stage.config.topic, key, payload);
+                    self.producer.send(&kafka_record)
+                }
+                None => {
+                    let kafka_record = Record::from_value(&stage.config.topic, payload);
+                    self.producer.send(&kafka_record)
+                }
+            },
             PartitionStrategy::Random => {
                 let kafka_record = Record::from_value(&stage.config.topic, payload);
                 self.producer.send(&kafka_record)
@@ -93,6 +104,7 @@ pub struct Stage {
 #[derive(Debug, Clone, Deserialize)]
 pub enum PartitionStrategy {
     ByBlock,
+    ByFingerprint,
     Random,
 }
 
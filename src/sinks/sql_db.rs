@@ -44,11 +44,11 @@ impl gasket::framework::Worker<Stage> for Worker {
         let point = unit.point().clone();
 
         let template = match unit {
-            ChainEvent::Apply(p, r) => {
+            ChainEvent::Apply(p, r, _) => {
                 let data = hbs_data(p.clone(), Some(r.clone()));
                 stage.templates.render("apply", &data)
             }
-            ChainEvent::Undo(p, r) => {
+            ChainEvent::Undo(p, r, _) => {
                 let data = hbs_data(p.clone(), Some(r.clone()));
                 stage.templates.render("undo", &data)
             }
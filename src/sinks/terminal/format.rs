@@ -197,7 +197,7 @@ impl LogLine {
                 max_width,
                 format!("{{ hash: {hash} }}"),
             ),
-            EventData::TxInput(TxInputRecord { tx_id, index }) => LogLine::from_legacy_v1(
+            EventData::TxInput(TxInputRecord { tx_id, index, .. }) => LogLine::from_legacy_v1(
                 source,
                 "STXI",
                 Color::Blue,
@@ -285,6 +285,20 @@ impl LogLine {
                 max_width,
                 format!("{{ hash: {datum_hash} }}"),
             ),
+            EventData::InlineDatum(PlutusDatumRecord { datum_hash, .. }) => LogLine::from_legacy_v1(
+                source,
+                "DATUM",
+                Color::White,
+                max_width,
+                format!("{{ inline hash: {datum_hash} }}"),
+            ),
+            EventData::ReferenceScript(ReferenceScriptRecord { script_hash, language, .. }) => LogLine::from_legacy_v1(
+                source,
+                "REFSCR",
+                Color::White,
+                max_width,
+                format!("{{ {language} script: {script_hash} }}"),
+            ),
             EventData::PlutusRedeemer(PlutusRedeemerRecord { purpose, input_idx, .. }) => LogLine::from_legacy_v1(
                 source,
                 "REDEEM",
@@ -383,6 +397,159 @@ impl LogLine {
                 format!(
                     "{{ reserves: {from_reserves}, treasury: {from_treasury}, to_credentials: {to_stake_credentials:?}, to_other_pot: {to_other_pot:?} }}"),
             ),
+            EventData::StakeRegistrationWithDeposit { credential, deposit } => LogLine::from_legacy_v1(
+                source,
+                "STAKE+",
+                Color::Magenta,
+                max_width,
+                format!("{{ credential: {credential:?}, deposit: {deposit} }}"),
+            ),
+            EventData::StakeDeregistrationWithDeposit { credential, deposit } => LogLine::from_legacy_v1(
+                source,
+                "STAKE-",
+                Color::DarkMagenta,
+                max_width,
+                format!("{{ credential: {credential:?}, deposit: {deposit} }}"),
+            ),
+            EventData::VoteDelegation { credential, drep } => LogLine::from_legacy_v1(
+                source,
+                "VDELE",
+                Color::Magenta,
+                max_width,
+                format!("{{ credential: {credential:?}, drep: {drep:?} }}"),
+            ),
+            EventData::StakeVoteDelegation {
+                credential,
+                pool_hash,
+                drep,
+            } => LogLine::from_legacy_v1(
+                source,
+                "SVDELE",
+                Color::Magenta,
+                max_width,
+                format!("{{ credential: {credential:?}, pool: {pool_hash}, drep: {drep:?} }}"),
+            ),
+            EventData::StakeRegistrationDelegation {
+                credential,
+                pool_hash,
+                deposit,
+            } => LogLine::from_legacy_v1(
+                source,
+                "STAKE+",
+                Color::Magenta,
+                max_width,
+                format!("{{ credential: {credential:?}, pool: {pool_hash}, deposit: {deposit} }}"),
+            ),
+            EventData::VoteRegistrationDelegation {
+                credential,
+                drep,
+                deposit,
+            } => LogLine::from_legacy_v1(
+                source,
+                "STAKE+",
+                Color::Magenta,
+                max_width,
+                format!("{{ credential: {credential:?}, drep: {drep:?}, deposit: {deposit} }}"),
+            ),
+            EventData::StakeVoteRegistrationDelegation {
+                credential,
+                pool_hash,
+                drep,
+                deposit,
+            } => LogLine::from_legacy_v1(
+                source,
+                "STAKE+",
+                Color::Magenta,
+                max_width,
+                format!(
+                    "{{ credential: {credential:?}, pool: {pool_hash}, drep: {drep:?}, deposit: {deposit} }}"),
+            ),
+            EventData::CommitteeHotAuthorization {
+                committee_cold_credential,
+                committee_hot_credential,
+            } => LogLine::from_legacy_v1(
+                source,
+                "CC+",
+                Color::Magenta,
+                max_width,
+                format!(
+                    "{{ cold: {committee_cold_credential:?}, hot: {committee_hot_credential:?} }}"),
+            ),
+            EventData::CommitteeColdResignation {
+                committee_cold_credential,
+                anchor,
+            } => LogLine::from_legacy_v1(
+                source,
+                "CC-",
+                Color::DarkMagenta,
+                max_width,
+                format!(
+                    "{{ cold: {committee_cold_credential:?}, anchor: {:?} }}",
+                    anchor.as_ref().map(|x| &x.url)
+                ),
+            ),
+            EventData::DRepRegistration {
+                credential,
+                deposit,
+                anchor,
+            } => LogLine::from_legacy_v1(
+                source,
+                "DREP+",
+                Color::Magenta,
+                max_width,
+                format!(
+                    "{{ credential: {credential:?}, deposit: {deposit}, anchor: {:?} }}",
+                    anchor.as_ref().map(|x| &x.url)
+                ),
+            ),
+            EventData::DRepDeregistration { credential, deposit } => LogLine::from_legacy_v1(
+                source,
+                "DREP-",
+                Color::DarkMagenta,
+                max_width,
+                format!("{{ credential: {credential:?}, deposit: {deposit} }}"),
+            ),
+            EventData::DRepUpdate { credential, anchor } => LogLine::from_legacy_v1(
+                source,
+                "DREP",
+                Color::Magenta,
+                max_width,
+                format!(
+                    "{{ credential: {credential:?}, anchor: {:?} }}",
+                    anchor.as_ref().map(|x| &x.url)
+                ),
+            ),
+            EventData::GovProposal(GovProposalRecord {
+                action,
+                deposit,
+                anchor,
+                ..
+            }) => LogLine::from_legacy_v1(
+                source,
+                "PROPSL",
+                Color::DarkCyan,
+                max_width,
+                format!(
+                    "{{ action: {action}, deposit: {deposit}, anchor: {} }}",
+                    anchor.url
+                ),
+            ),
+            EventData::GovVote(GovVoteRecord {
+                voter_role,
+                voter,
+                action_tx_id,
+                action_index,
+                vote,
+                ..
+            }) => LogLine::from_legacy_v1(
+                source,
+                "VOTE",
+                Color::DarkCyan,
+                max_width,
+                format!(
+                    "{{ {voter_role}: {voter:?}, action: {action_tx_id}#{action_index}, vote: {vote} }}"
+                ),
+            ),
             EventData::RollBack {
                 block_slot,
                 block_hash,
@@ -419,6 +586,49 @@ impl LogLine {
                     image.as_deref().unwrap_or("?")
                 ),
             ),
+            EventData::CIP68Asset(CIP68AssetRecord {
+                policy,
+                reference_asset,
+                name,
+                image,
+                ..
+            }) => LogLine::from_legacy_v1(
+                source,
+                "CIP68",
+                Color::DarkYellow,
+                max_width,
+                format!(
+                    "{{ policy: {}, reference: {}, name: {}, image: {} }}",
+                    policy,
+                    reference_asset,
+                    name.as_deref().unwrap_or("?"),
+                    image.as_deref().unwrap_or("?")
+                ),
+            ),
+            EventData::CIP36Registration(CIP36RegistrationRecord {
+                delegations,
+                stake_pub,
+                voting_purpose,
+                ..
+            }) => LogLine::from_legacy_v1(
+                source,
+                "CIP36",
+                Color::DarkYellow,
+                max_width,
+                format!(
+                    "{{ delegations: {}, stake pub: {stake_pub}, purpose: {voting_purpose} }}",
+                    delegations.len()
+                ),
+            ),
+            EventData::CIP20Message(CIP20MessageRecord { messages, .. }) => {
+                LogLine::from_legacy_v1(
+                    source,
+                    "CIP20",
+                    Color::DarkYellow,
+                    max_width,
+                    format!("{{ message: {} }}", messages.join(" ")),
+                )
+            }
             EventData::CIP15Asset(CIP15AssetRecord {
                 voting_key,
                 stake_pub,
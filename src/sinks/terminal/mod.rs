@@ -64,10 +64,10 @@ impl gasket::framework::Worker<Stage> for Worker {
         let point = unit.point().clone();
 
         let line = match unit {
-            ChainEvent::Apply(_, record) => {
+            ChainEvent::Apply(_, record, _) => {
                 LogLine::handle(record, width, &stage.config.adahandle_policy)
             }
-            ChainEvent::Undo(_, record) => {
+            ChainEvent::Undo(_, record, _) => {
                 LogLine::handle(record, width, &stage.config.adahandle_policy)
             }
             ChainEvent::Reset(point) => LogLine::reset(point.clone()),
@@ -54,14 +54,17 @@ impl gasket::framework::Worker<Stage> for Worker {
             Point::Specific(a, b) => format!("{a},{}", hex::encode(b)),
         };
 
-        let request = self
+        let mut request = self
             .client
             .post(&stage.config.url)
             .header("x-oura-chainsync-action", "apply")
-            .header("x-oura-chainsync-point", point_header)
-            .json(&body)
-            .build()
-            .or_panic()?;
+            .header("x-oura-chainsync-point", point_header);
+
+        if let Some(fingerprint) = unit.fingerprint() {
+            request = request.header("x-oura-fingerprint", fingerprint);
+        }
+
+        let request = request.json(&body).build().or_panic()?;
 
         self.client
             .execute(request)
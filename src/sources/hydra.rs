@@ -176,13 +176,17 @@ impl Worker {
         let point = next.pseudo_point();
 
         // First, apply raw json messages regardless of message type
-        let json_evt = ChainEvent::Apply(point.clone(), Record::GenericJson(next.raw_json.clone()));
+        let json_evt = ChainEvent::Apply(
+            point.clone(),
+            Record::GenericJson(next.raw_json.clone()),
+            None,
+        );
         stage.output.send(json_evt.into()).await.or_panic()?;
         stage.ops_count.inc(1);
 
         // Apply CborTx events for any txs
         if let HydraMessagePayload::TxValid { tx } = next.payload {
-            let evt = ChainEvent::Apply(point.clone(), Record::CborTx(tx));
+            let evt = ChainEvent::Apply(point.clone(), Record::CborTx(tx), None);
             stage.output.send(evt.into()).await.or_panic()?;
             stage.ops_count.inc(1);
 
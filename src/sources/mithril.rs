@@ -325,7 +325,7 @@ impl gasket::framework::Worker<Stage> for Worker {
                 .collect();
 
             for (point, block) in blocks {
-                let event = ChainEvent::Apply(point, Record::CborBlock(block));
+                let event = ChainEvent::Apply(point, Record::CborBlock(block), None);
                 stage.output.send(event.into()).await.or_panic()?;
             }
         }
@@ -103,7 +103,7 @@ impl Worker {
 
                 debug!(slot, %hash, "chain sync roll forward");
 
-                let evt = ChainEvent::Apply(point.clone(), Record::CborBlock(cbor.to_vec()));
+                let evt = ChainEvent::Apply(point.clone(), Record::CborBlock(cbor.to_vec()), None);
 
                 stage.output.send(evt.into()).await.or_panic()?;
 
@@ -120,6 +120,7 @@ impl Worker {
                 let evt = ChainEvent::Apply(
                     pallas::network::miniprotocols::Point::Specific(slot, hash.to_vec()),
                     Record::CborBlock(block),
+                    None,
                 );
 
                 stage.output.send(evt.into()).await.or_panic()?;
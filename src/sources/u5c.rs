@@ -56,7 +56,7 @@ impl Worker {
             TipEvent::Apply(block) => {
                 let (point, record) = self.block_to_record(stage, block)?;
 
-                let evt = ChainEvent::Apply(point.clone(), record);
+                let evt = ChainEvent::Apply(point.clone(), record, None);
 
                 stage.output.send(evt.into()).await.or_panic()?;
                 stage.chain_tip.set(point.slot_or_default() as i64);
@@ -64,7 +64,7 @@ impl Worker {
             TipEvent::Undo(block) => {
                 let (point, record) = self.block_to_record(stage, block)?;
 
-                let evt = ChainEvent::Undo(point.clone(), record);
+                let evt = ChainEvent::Undo(point.clone(), record, None);
 
                 stage.output.send(evt.into()).await.or_panic()?;
                 stage.chain_tip.set(point.slot_or_default() as i64);